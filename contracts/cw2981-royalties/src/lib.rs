@@ -145,6 +145,10 @@ mod tests {
             symbol: "SPACE".to_string(),
             minter: None,
             withdraw_address: None,
+            eviction_policy: None,
+            rental_config: None,
+            max_history: None,
+            base_uri: None,
         };
         entry::instantiate(deps.as_mut(), mock_env(), info.clone(), init_msg).unwrap();
 
@@ -179,6 +183,10 @@ mod tests {
             symbol: "SPACE".to_string(),
             minter: None,
             withdraw_address: None,
+            eviction_policy: None,
+            rental_config: None,
+            max_history: None,
+            base_uri: None,
         };
         entry::instantiate(deps.as_mut(), mock_env(), info.clone(), init_msg).unwrap();
 
@@ -210,6 +218,10 @@ mod tests {
             symbol: "SPACE".to_string(),
             minter: None,
             withdraw_address: None,
+            eviction_policy: None,
+            rental_config: None,
+            max_history: None,
+            base_uri: None,
         };
         entry::instantiate(deps.as_mut(), mock_env(), info.clone(), init_msg).unwrap();
 
@@ -251,6 +263,10 @@ mod tests {
             symbol: "SPACE".to_string(),
             minter: None,
             withdraw_address: None,
+            eviction_policy: None,
+            rental_config: None,
+            max_history: None,
+            base_uri: None,
         };
         entry::instantiate(deps.as_mut(), mock_env(), info.clone(), init_msg).unwrap();
 
@@ -340,6 +356,10 @@ mod tests {
             symbol: "SPACE".to_string(),
             minter: None,
             withdraw_address: None,
+            eviction_policy: None,
+            rental_config: None,
+            max_history: None,
+            base_uri: None,
         };
         entry::instantiate(deps.as_mut(), env.clone(), info.clone(), init_msg).unwrap();
 
@@ -2,17 +2,21 @@
 use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
 
 use cosmwasm_std::{
-    from_json, to_json_binary, Addr, Coin, CosmosMsg, DepsMut, Empty, Response, StdError, WasmMsg,
+    from_json, to_json_binary, Addr, Coin, CosmosMsg, Deps, DepsMut, Empty, Env, Response,
+    StdError, WasmMsg,
 };
 
 use cw721::{
     Approval, ApprovalResponse, ContractInfoResponse, Cw721Query, Cw721ReceiveMsg, Expiration,
-    NftInfoResponse, OperatorResponse, OperatorsResponse, OwnerOfResponse,
+    NftInfoResponse, OperatorResponse, OperatorsResponse, OwnerOfResponse, TokensResponse,
+    UserOfResponse,
 };
 use cw_ownable::OwnershipError;
 
+use crate::msg::{CanUseResponse, ReservationResponse, UserRecordResponse};
 use crate::{
-    ContractError, Cw721Contract, ExecuteMsg, Extension, InstantiateMsg, MinterResponse, QueryMsg,
+    ContractError, Cw721Contract, EvictionPolicy, ExecuteMsg, Extension, InstantiateMsg,
+    MinterResponse, QueryMsg, RentalConfig,
 };
 
 const MINTER: &str = "merlin";
@@ -26,6 +30,10 @@ fn setup_contract(deps: DepsMut<'_>) -> Cw721Contract<'static, Extension, Empty,
         symbol: SYMBOL.to_string(),
         minter: Some(String::from(MINTER)),
         withdraw_address: None,
+        eviction_policy: None,
+        rental_config: None,
+        max_history: None,
+        base_uri: None,
     };
     let info = mock_info("creator", &[]);
     let res = contract.instantiate(deps, mock_env(), info, msg).unwrap();
@@ -43,6 +51,10 @@ fn proper_instantiation() {
         symbol: SYMBOL.to_string(),
         minter: Some(String::from(MINTER)),
         withdraw_address: Some(String::from(MINTER)),
+        eviction_policy: None,
+        rental_config: None,
+        max_history: None,
+        base_uri: None,
     };
     let info = mock_info("creator", &[]);
 
@@ -61,6 +73,7 @@ fn proper_instantiation() {
         ContractInfoResponse {
             name: CONTRACT_NAME.to_string(),
             symbol: SYMBOL.to_string(),
+            base_uri: None,
         }
     );
 
@@ -1001,3 +1014,1291 @@ fn query_tokens_by_owner() {
         .unwrap();
     assert_eq!(&by_demeter[1..], &tokens.tokens[..]);
 }
+
+#[test]
+fn query_tokens_by_user() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+    let minter = mock_info(MINTER, &[]);
+    let demeter = String::from("demeter");
+
+    // Mint a few tokens to the same owner
+    let token_ids = vec!["grow1".to_string(), "grow2".to_string(), "sing".to_string()];
+    for token_id in &token_ids {
+        let mint_msg = ExecuteMsg::Mint {
+            token_id: token_id.clone(),
+            owner: demeter.clone(),
+            token_uri: None,
+            extension: None,
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), minter.clone(), mint_msg)
+            .unwrap();
+    }
+
+    // rent two of them out to ceres, one with a short expiration
+    let env = mock_env();
+    let owner = mock_info(&demeter, &[]);
+    let set_user_msg = ExecuteMsg::SetUser {
+        token_id: token_ids[0].clone(),
+        user: String::from("ceres"),
+        expires: Some(Expiration::AtHeight(env.block.height + 1)),
+    };
+    contract
+        .execute(deps.as_mut(), env.clone(), owner.clone(), set_user_msg)
+        .unwrap();
+    let set_user_msg = ExecuteMsg::SetUser {
+        token_id: token_ids[2].clone(),
+        user: String::from("ceres"),
+        expires: Some(Expiration::AtHeight(env.block.height + 100)),
+    };
+    contract
+        .execute(deps.as_mut(), env.clone(), owner, set_user_msg)
+        .unwrap();
+
+    // all tokens used by ceres
+    let tokens = contract
+        .tokens_used_by(
+            deps.as_ref(),
+            env.clone(),
+            "ceres".into(),
+            false,
+            None,
+            None,
+        )
+        .unwrap();
+    assert_eq!(
+        vec![token_ids[0].clone(), token_ids[2].clone()],
+        tokens.tokens
+    );
+
+    // paginate
+    let tokens = contract
+        .tokens_used_by(
+            deps.as_ref(),
+            env.clone(),
+            "ceres".into(),
+            false,
+            None,
+            Some(1),
+        )
+        .unwrap();
+    assert_eq!(vec![token_ids[0].clone()], tokens.tokens);
+    let tokens = contract
+        .tokens_used_by(
+            deps.as_ref(),
+            env.clone(),
+            "ceres".into(),
+            false,
+            Some(token_ids[0].clone()),
+            None,
+        )
+        .unwrap();
+    assert_eq!(vec![token_ids[2].clone()], tokens.tokens);
+
+    // nothing for demeter, who only owns them
+    let tokens = contract
+        .tokens_used_by(deps.as_ref(), env.clone(), demeter, false, None, None)
+        .unwrap();
+    assert!(tokens.tokens.is_empty());
+
+    // expired users are filtered out unless include_expired is set
+    let mut late_env = env;
+    late_env.block.height += 10;
+    let query_msg = QueryMsg::TokensUsedBy {
+        user: String::from("ceres"),
+        include_expired: None,
+        start_after: None,
+        limit: None,
+    };
+    let tokens: TokensResponse = from_json(
+        contract
+            .query(deps.as_ref(), late_env.clone(), query_msg)
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(vec![token_ids[2].clone()], tokens.tokens);
+
+    let tokens = contract
+        .tokens_used_by(deps.as_ref(), late_env, "ceres".into(), true, None, None)
+        .unwrap();
+    assert_eq!(
+        vec![token_ids[0].clone(), token_ids[2].clone()],
+        tokens.tokens
+    );
+}
+
+#[test]
+fn removing_user() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+
+    // Mint a token
+    let token_id = "grow".to_string();
+    let mint_msg = ExecuteMsg::Mint {
+        token_id: token_id.clone(),
+        owner: String::from("demeter"),
+        token_uri: None,
+        extension: None,
+    };
+    let minter = mock_info(MINTER, &[]);
+    contract
+        .execute(deps.as_mut(), mock_env(), minter, mint_msg)
+        .unwrap();
+
+    // nothing to remove yet
+    let owner = mock_info("demeter", &[]);
+    let remove_msg = ExecuteMsg::RemoveUser {
+        token_id: token_id.clone(),
+    };
+    let err = contract
+        .execute(deps.as_mut(), mock_env(), owner.clone(), remove_msg.clone())
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::UserNotFound {
+            user: String::new()
+        }
+    );
+
+    // rent it out
+    let env = mock_env();
+    let set_user_msg = ExecuteMsg::SetUser {
+        token_id: token_id.clone(),
+        user: String::from("ceres"),
+        expires: Some(Expiration::AtHeight(env.block.height + 10)),
+    };
+    contract
+        .execute(deps.as_mut(), env.clone(), owner.clone(), set_user_msg)
+        .unwrap();
+
+    // random cannot evict
+    let random = mock_info("random", &[]);
+    let err = contract
+        .execute(deps.as_mut(), env.clone(), random, remove_msg.clone())
+        .unwrap_err();
+    assert_eq!(err, ContractError::Ownership(OwnershipError::NotOwner));
+
+    // with the default policy, the user cannot leave early
+    let user = mock_info("ceres", &[]);
+    let err = contract
+        .execute(deps.as_mut(), env.clone(), user, remove_msg.clone())
+        .unwrap_err();
+    assert_eq!(err, ContractError::Ownership(OwnershipError::NotOwner));
+
+    // but the owner can evict
+    let res = contract
+        .execute(deps.as_mut(), env.clone(), owner, remove_msg)
+        .unwrap();
+    assert_eq!(
+        res,
+        Response::new()
+            .add_attribute("action", "remove_user")
+            .add_attribute("sender", "demeter")
+            .add_attribute("user", "ceres")
+            .add_attribute("token_id", token_id.clone())
+    );
+
+    let res = contract
+        .user_of(deps.as_ref(), env, token_id.clone())
+        .unwrap();
+    assert_eq!(res.user, "");
+}
+
+#[test]
+fn removing_user_with_eviction_policy() {
+    let mut deps = mock_dependencies();
+    let contract = Cw721Contract::<Extension, Empty, Empty, Empty>::default();
+    let msg = InstantiateMsg {
+        name: CONTRACT_NAME.to_string(),
+        symbol: SYMBOL.to_string(),
+        minter: Some(String::from(MINTER)),
+        withdraw_address: None,
+        eviction_policy: Some(EvictionPolicy::User),
+        rental_config: None,
+        max_history: None,
+        base_uri: None,
+    };
+    contract
+        .instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg)
+        .unwrap();
+
+    let owner = mock_info("demeter", &[]);
+    let user = mock_info("ceres", &[]);
+    let env = mock_env();
+    for token_id in ["grow1", "grow2"] {
+        let mint_msg = ExecuteMsg::Mint {
+            token_id: token_id.to_string(),
+            owner: String::from("demeter"),
+            token_uri: None,
+            extension: None,
+        };
+        contract
+            .execute(deps.as_mut(), env.clone(), mock_info(MINTER, &[]), mint_msg)
+            .unwrap();
+        let set_user_msg = ExecuteMsg::SetUser {
+            token_id: token_id.to_string(),
+            user: String::from("ceres"),
+            expires: Some(Expiration::AtHeight(env.block.height + 10)),
+        };
+        contract
+            .execute(deps.as_mut(), env.clone(), owner.clone(), set_user_msg)
+            .unwrap();
+    }
+
+    // owner cannot evict an active user
+    let remove_msg = ExecuteMsg::RemoveUser {
+        token_id: "grow1".to_string(),
+    };
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            owner.clone(),
+            remove_msg.clone(),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // the user may give it up
+    contract
+        .execute(deps.as_mut(), env.clone(), user, remove_msg)
+        .unwrap();
+
+    // once expired, the owner can clear it
+    let mut late_env = env;
+    late_env.block.height += 10;
+    let remove_msg = ExecuteMsg::RemoveUser {
+        token_id: "grow2".to_string(),
+    };
+    contract
+        .execute(deps.as_mut(), late_env.clone(), owner, remove_msg)
+        .unwrap();
+
+    let tokens = contract
+        .tokens_used_by(deps.as_ref(), late_env, "ceres".into(), true, None, None)
+        .unwrap();
+    assert!(tokens.tokens.is_empty());
+}
+
+#[test]
+fn transferring_nft_clears_user() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+    let env = mock_env();
+
+    let token_id = "melt".to_string();
+    let mint_msg = ExecuteMsg::Mint {
+        token_id: token_id.clone(),
+        owner: String::from("venus"),
+        token_uri: None,
+        extension: None,
+    };
+    contract
+        .execute(deps.as_mut(), env.clone(), mock_info(MINTER, &[]), mint_msg)
+        .unwrap();
+    let set_user_msg = ExecuteMsg::SetUser {
+        token_id: token_id.clone(),
+        user: String::from("ceres"),
+        expires: Some(Expiration::AtHeight(env.block.height + 10)),
+    };
+    contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("venus", &[]),
+            set_user_msg,
+        )
+        .unwrap();
+
+    // by default the user is dropped on transfer
+    let transfer_msg = ExecuteMsg::TransferNft {
+        recipient: String::from("random"),
+        token_id: token_id.clone(),
+    };
+    let res = contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("venus", &[]),
+            transfer_msg,
+        )
+        .unwrap();
+    assert_eq!(
+        res,
+        Response::new()
+            .add_attribute("action", "transfer_nft")
+            .add_attribute("sender", "venus")
+            .add_attribute("recipient", "random")
+            .add_attribute("token_id", token_id.clone())
+            .add_attribute("removed_user", "ceres")
+    );
+
+    let res = contract
+        .user_of(deps.as_ref(), env.clone(), token_id)
+        .unwrap();
+    assert_eq!(res.user, "");
+    let tokens = contract
+        .tokens_used_by(deps.as_ref(), env, "ceres".into(), true, None, None)
+        .unwrap();
+    assert!(tokens.tokens.is_empty());
+}
+
+#[test]
+fn transferring_nft_persists_user_until_expiry() {
+    let mut deps = mock_dependencies();
+    let contract = Cw721Contract::<Extension, Empty, Empty, Empty>::default();
+    let msg = InstantiateMsg {
+        name: CONTRACT_NAME.to_string(),
+        symbol: SYMBOL.to_string(),
+        minter: Some(String::from(MINTER)),
+        withdraw_address: None,
+        eviction_policy: None,
+        rental_config: Some(RentalConfig::PersistUntilExpiry),
+        max_history: None,
+        base_uri: None,
+    };
+    contract
+        .instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg)
+        .unwrap();
+    let env = mock_env();
+
+    let token_id = "melt".to_string();
+    let mint_msg = ExecuteMsg::Mint {
+        token_id: token_id.clone(),
+        owner: String::from("venus"),
+        token_uri: None,
+        extension: None,
+    };
+    contract
+        .execute(deps.as_mut(), env.clone(), mock_info(MINTER, &[]), mint_msg)
+        .unwrap();
+    let set_user_msg = ExecuteMsg::SetUser {
+        token_id: token_id.clone(),
+        user: String::from("ceres"),
+        expires: Some(Expiration::AtHeight(env.block.height + 10)),
+    };
+    contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("venus", &[]),
+            set_user_msg,
+        )
+        .unwrap();
+
+    // the active user survives a transfer
+    let transfer_msg = ExecuteMsg::TransferNft {
+        recipient: String::from("random"),
+        token_id: token_id.clone(),
+    };
+    let res = contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("venus", &[]),
+            transfer_msg,
+        )
+        .unwrap();
+    assert!(!res.attributes.iter().any(|a| a.key == "removed_user"));
+    let res = contract
+        .user_of(deps.as_ref(), env.clone(), token_id.clone())
+        .unwrap();
+    assert_eq!(res.user, "ceres");
+
+    // and prevents burning
+    let burn_msg = ExecuteMsg::Burn {
+        token_id: token_id.clone(),
+    };
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("random", &[]),
+            burn_msg.clone(),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::UserNotExpired {});
+
+    // once expired, the user is dropped on burn
+    let mut late_env = env;
+    late_env.block.height += 10;
+    let res = contract
+        .execute(deps.as_mut(), late_env, mock_info("random", &[]), burn_msg)
+        .unwrap();
+    assert_eq!(
+        res,
+        Response::new()
+            .add_attribute("action", "burn")
+            .add_attribute("sender", "random")
+            .add_attribute("token_id", token_id)
+            .add_attribute("removed_user", "ceres")
+    );
+}
+
+#[test]
+fn setting_user_by_approved_spender() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+    let env = mock_env();
+
+    let token_id = "melt".to_string();
+    let mint_msg = ExecuteMsg::Mint {
+        token_id: token_id.clone(),
+        owner: String::from("venus"),
+        token_uri: None,
+        extension: None,
+    };
+    contract
+        .execute(deps.as_mut(), env.clone(), mock_info(MINTER, &[]), mint_msg)
+        .unwrap();
+
+    let set_user_msg = ExecuteMsg::SetUser {
+        token_id: token_id.clone(),
+        user: String::from("ceres"),
+        expires: Some(Expiration::AtHeight(env.block.height + 10)),
+    };
+
+    // random cannot set a user
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("random", &[]),
+            set_user_msg.clone(),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // once approved for the token, it can
+    let approve_msg = ExecuteMsg::Approve {
+        spender: String::from("random"),
+        token_id: token_id.clone(),
+        expires: None,
+    };
+    contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("venus", &[]),
+            approve_msg,
+        )
+        .unwrap();
+    contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("random", &[]),
+            set_user_msg,
+        )
+        .unwrap();
+
+    let res = contract.user_of(deps.as_ref(), env, token_id).unwrap();
+    assert_eq!(res.user, "ceres");
+}
+
+#[test]
+fn reserving_nft() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+    let env = mock_env();
+    let now = env.block.time;
+
+    let token_id = "melt".to_string();
+    let mint_msg = ExecuteMsg::Mint {
+        token_id: token_id.clone(),
+        owner: String::from("venus"),
+        token_uri: None,
+        extension: None,
+    };
+    contract
+        .execute(deps.as_mut(), env.clone(), mock_info(MINTER, &[]), mint_msg)
+        .unwrap();
+
+    // ceres is the user for the next 100 seconds
+    let set_user_msg = ExecuteMsg::SetUser {
+        token_id: token_id.clone(),
+        user: String::from("ceres"),
+        expires: Some(Expiration::AtTime(now.plus_seconds(100))),
+    };
+    contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("venus", &[]),
+            set_user_msg,
+        )
+        .unwrap();
+
+    let reserve = |user: &str, start: u64, end: u64| ExecuteMsg::Reserve {
+        token_id: token_id.clone(),
+        user: String::from(user),
+        start: now.plus_seconds(start),
+        end: now.plus_seconds(end),
+    };
+
+    // random cannot book the token
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("random", &[]),
+            reserve("juno", 100, 200),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // a reservation must end after it starts
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("venus", &[]),
+            reserve("juno", 200, 200),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::InvalidReservation {});
+
+    // it cannot overlap the current user
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("venus", &[]),
+            reserve("juno", 50, 200),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::ReservationConflict {});
+
+    // book juno right after ceres and pluto after a gap
+    contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("venus", &[]),
+            reserve("pluto", 300, 400),
+        )
+        .unwrap();
+    let res = contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("venus", &[]),
+            reserve("juno", 100, 200),
+        )
+        .unwrap();
+    assert_eq!(
+        res,
+        Response::new()
+            .add_attribute("action", "reserve")
+            .add_attribute("sender", "venus")
+            .add_attribute("user", "juno")
+            .add_attribute("token_id", token_id.clone())
+            .add_attribute("start", now.plus_seconds(100).to_string())
+            .add_attribute("end", now.plus_seconds(200).to_string())
+    );
+
+    // neither the reservation before nor the one after may overlap
+    for (start, end) in [(150, 250), (250, 350), (0, 1000)] {
+        let err = contract
+            .execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("venus", &[]),
+                reserve("mars", start, end),
+            )
+            .unwrap_err();
+        assert_eq!(err, ContractError::ReservationConflict {});
+    }
+
+    // setting a user that outlasts the next reservation is rejected too
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("venus", &[]),
+            ExecuteMsg::SetUser {
+                token_id: token_id.clone(),
+                user: String::from("mars"),
+                expires: None,
+            },
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::ReservationConflict {});
+
+    // reservations are listed by start, per token and per user
+    let res = contract
+        .reservations(deps.as_ref(), env.clone(), token_id.clone(), None, None)
+        .unwrap();
+    assert_eq!(
+        res.reservations,
+        vec![
+            ReservationResponse {
+                token_id: token_id.clone(),
+                user: String::from("juno"),
+                start: now.plus_seconds(100),
+                end: now.plus_seconds(200),
+            },
+            ReservationResponse {
+                token_id: token_id.clone(),
+                user: String::from("pluto"),
+                start: now.plus_seconds(300),
+                end: now.plus_seconds(400),
+            },
+        ]
+    );
+    let res = contract
+        .reservations(
+            deps.as_ref(),
+            env.clone(),
+            token_id.clone(),
+            Some(now.plus_seconds(100)),
+            None,
+        )
+        .unwrap();
+    assert_eq!(res.reservations.len(), 1);
+    assert_eq!(res.reservations[0].user, "pluto");
+    let res = contract
+        .user_reservations(deps.as_ref(), env.clone(), String::from("juno"), None, None)
+        .unwrap();
+    assert_eq!(res.reservations.len(), 1);
+    assert_eq!(res.reservations[0].start, now.plus_seconds(100));
+    let res = contract
+        .user_reservations(
+            deps.as_ref(),
+            env.clone(),
+            String::from("juno"),
+            Some((token_id.clone(), now.plus_seconds(100))),
+            None,
+        )
+        .unwrap();
+    assert!(res.reservations.is_empty());
+
+    // once juno's reservation starts, queries already see juno as the user
+    let mut later_env = env.clone();
+    later_env.block.time = now.plus_seconds(150);
+    let res = contract
+        .user_of(deps.as_ref(), later_env.clone(), token_id.clone())
+        .unwrap();
+    assert_eq!(
+        res,
+        UserOfResponse {
+            user: String::from("juno"),
+            expires: Expiration::AtTime(now.plus_seconds(200)),
+        }
+    );
+    let res = contract
+        .all_nft_info(deps.as_ref(), later_env.clone(), token_id.clone(), false)
+        .unwrap();
+    assert_eq!(res.user.user, "juno");
+    let res = contract
+        .tokens_used_by(
+            deps.as_ref(),
+            later_env.clone(),
+            "juno".into(),
+            false,
+            None,
+            None,
+        )
+        .unwrap();
+    assert_eq!(res.tokens, vec![token_id.clone()]);
+    let res = contract
+        .tokens_used_by(deps.as_ref(), env.clone(), "juno".into(), false, None, None)
+        .unwrap();
+    assert!(res.tokens.is_empty());
+
+    // and the next write stores it, so juno can give up the token early
+    contract
+        .execute(
+            deps.as_mut(),
+            later_env.clone(),
+            mock_info("venus", &[]),
+            ExecuteMsg::RemoveUser {
+                token_id: token_id.clone(),
+            },
+        )
+        .unwrap();
+    let res = contract
+        .user_of(deps.as_ref(), later_env.clone(), token_id.clone())
+        .unwrap();
+    assert_eq!(res.user, "");
+    let res = contract
+        .reservations(deps.as_ref(), later_env, token_id.clone(), None, None)
+        .unwrap();
+    assert_eq!(res.reservations.len(), 1);
+
+    // between reservations nobody is the user, then pluto is
+    let mut gap_env = env.clone();
+    gap_env.block.time = now.plus_seconds(250);
+    let res = contract
+        .user_of(deps.as_ref(), gap_env, token_id.clone())
+        .unwrap();
+    assert_eq!(res.user, "");
+    let mut last_env = env;
+    last_env.block.time = now.plus_seconds(300);
+    let res = contract.user_of(deps.as_ref(), last_env, token_id).unwrap();
+    assert_eq!(res.user, "pluto");
+}
+
+#[test]
+fn cancelling_reservation() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+    let env = mock_env();
+    let now = env.block.time;
+
+    let token_id = "melt".to_string();
+    let mint_msg = ExecuteMsg::Mint {
+        token_id: token_id.clone(),
+        owner: String::from("venus"),
+        token_uri: None,
+        extension: None,
+    };
+    contract
+        .execute(deps.as_mut(), env.clone(), mock_info(MINTER, &[]), mint_msg)
+        .unwrap();
+    for (user, start) in [("juno", 100), ("pluto", 300)] {
+        let reserve_msg = ExecuteMsg::Reserve {
+            token_id: token_id.clone(),
+            user: String::from(user),
+            start: now.plus_seconds(start),
+            end: now.plus_seconds(start + 100),
+        };
+        contract
+            .execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("venus", &[]),
+                reserve_msg,
+            )
+            .unwrap();
+    }
+
+    let cancel_msg = ExecuteMsg::CancelReservation {
+        token_id: token_id.clone(),
+        start: now.plus_seconds(100),
+    };
+
+    // random cannot cancel, the reserved user can
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("random", &[]),
+            cancel_msg.clone(),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let mut started_env = env.clone();
+    started_env.block.time = now.plus_seconds(100);
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            started_env,
+            mock_info("juno", &[]),
+            cancel_msg.clone(),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::ReservationStarted {});
+    contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("juno", &[]),
+            cancel_msg,
+        )
+        .unwrap();
+    let res = contract
+        .reservations(deps.as_ref(), env.clone(), token_id.clone(), None, None)
+        .unwrap();
+    assert_eq!(res.reservations.len(), 1);
+    assert_eq!(res.reservations[0].user, "pluto");
+
+    // bookings made by the previous owner are dropped on transfer
+    let transfer_msg = ExecuteMsg::TransferNft {
+        recipient: String::from("random"),
+        token_id: token_id.clone(),
+    };
+    contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("venus", &[]),
+            transfer_msg,
+        )
+        .unwrap();
+    let res = contract
+        .reservations(deps.as_ref(), env.clone(), token_id, None, None)
+        .unwrap();
+    assert!(res.reservations.is_empty());
+    let res = contract
+        .user_reservations(deps.as_ref(), env, String::from("pluto"), None, None)
+        .unwrap();
+    assert!(res.reservations.is_empty());
+}
+
+#[test]
+fn recording_user_history() {
+    let mut deps = mock_dependencies();
+    let contract = Cw721Contract::<Extension, Empty, Empty, Empty>::default();
+    let msg = InstantiateMsg {
+        name: CONTRACT_NAME.to_string(),
+        symbol: SYMBOL.to_string(),
+        minter: Some(String::from(MINTER)),
+        withdraw_address: None,
+        eviction_policy: None,
+        rental_config: None,
+        max_history: Some(2),
+        base_uri: None,
+    };
+    contract
+        .instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg)
+        .unwrap();
+    let env = mock_env();
+    let now = env.block.time;
+
+    let token_id = "melt".to_string();
+    let mint_msg = ExecuteMsg::Mint {
+        token_id: token_id.clone(),
+        owner: String::from("venus"),
+        token_uri: None,
+        extension: None,
+    };
+    contract
+        .execute(deps.as_mut(), env.clone(), mock_info(MINTER, &[]), mint_msg)
+        .unwrap();
+
+    // ceres is set by the owner and evicted by an operator for juno, who then gets
+    // more time, then mars books the token
+    let approve_all_msg = ExecuteMsg::ApproveAll {
+        operator: String::from("random"),
+        expires: None,
+    };
+    contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("venus", &[]),
+            approve_all_msg,
+        )
+        .unwrap();
+    for (sender, user, expires) in [
+        ("venus", "ceres", 10),
+        ("random", "juno", 20),
+        ("random", "juno", 30),
+    ] {
+        let set_user_msg = ExecuteMsg::SetUser {
+            token_id: token_id.clone(),
+            user: String::from(user),
+            expires: Some(Expiration::AtTime(now.plus_seconds(expires))),
+        };
+        contract
+            .execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(sender, &[]),
+                set_user_msg,
+            )
+            .unwrap();
+    }
+    let reserve_msg = ExecuteMsg::Reserve {
+        token_id: token_id.clone(),
+        user: String::from("mars"),
+        start: now.plus_seconds(100),
+        end: now.plus_seconds(200),
+    };
+    contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("venus", &[]),
+            reserve_msg,
+        )
+        .unwrap();
+
+    let res = contract
+        .user_history(deps.as_ref(), token_id.clone(), None, None)
+        .unwrap();
+    assert_eq!(
+        res.history,
+        vec![
+            UserRecordResponse {
+                id: 0,
+                user: String::from("ceres"),
+                set_by: String::from("venus"),
+                start_height: Some(env.block.height),
+                start_time: now,
+                expires: Expiration::AtTime(now.plus_seconds(10)),
+                end_height: Some(env.block.height),
+                end_time: Some(now),
+            },
+            UserRecordResponse {
+                id: 1,
+                user: String::from("juno"),
+                set_by: String::from("random"),
+                start_height: Some(env.block.height),
+                start_time: now,
+                expires: Expiration::AtTime(now.plus_seconds(30)),
+                end_height: None,
+                end_time: None,
+            },
+        ]
+    );
+
+    // the reservation is recorded once it is stored as the user, dropping the oldest entry,
+    // and ends when it is removed early
+    let mut later_env = env;
+    later_env.block.height += 100;
+    later_env.block.time = now.plus_seconds(150);
+    contract
+        .execute(
+            deps.as_mut(),
+            later_env.clone(),
+            mock_info("venus", &[]),
+            ExecuteMsg::RemoveUser {
+                token_id: token_id.clone(),
+            },
+        )
+        .unwrap();
+
+    let res = contract
+        .user_history(deps.as_ref(), token_id.clone(), Some(1), None)
+        .unwrap();
+    assert_eq!(
+        res.history,
+        vec![UserRecordResponse {
+            id: 2,
+            user: String::from("mars"),
+            set_by: String::from("venus"),
+            start_height: None,
+            start_time: now.plus_seconds(100),
+            expires: Expiration::AtTime(now.plus_seconds(200)),
+            end_height: Some(later_env.block.height),
+            end_time: Some(later_env.block.time),
+        }]
+    );
+    let res = contract
+        .user_history(deps.as_ref(), token_id.clone(), None, None)
+        .unwrap();
+    assert_eq!(
+        res.history.iter().map(|r| r.id).collect::<Vec<_>>(),
+        vec![1, 2]
+    );
+
+    // a reservation that ends before the next write is still recorded
+    let reserve_msg = ExecuteMsg::Reserve {
+        token_id: token_id.clone(),
+        user: String::from("pluto"),
+        start: now.plus_seconds(300),
+        end: now.plus_seconds(400),
+    };
+    contract
+        .execute(
+            deps.as_mut(),
+            later_env.clone(),
+            mock_info("venus", &[]),
+            reserve_msg,
+        )
+        .unwrap();
+    let mut last_env = later_env;
+    last_env.block.height += 100;
+    last_env.block.time = now.plus_seconds(500);
+    let transfer_msg = ExecuteMsg::TransferNft {
+        recipient: String::from("ceres"),
+        token_id: token_id.clone(),
+    };
+    contract
+        .execute(
+            deps.as_mut(),
+            last_env.clone(),
+            mock_info("venus", &[]),
+            transfer_msg,
+        )
+        .unwrap();
+
+    let res = contract
+        .user_history(deps.as_ref(), token_id.clone(), Some(2), None)
+        .unwrap();
+    assert_eq!(
+        res.history,
+        vec![UserRecordResponse {
+            id: 3,
+            user: String::from("pluto"),
+            set_by: String::from("venus"),
+            start_height: None,
+            start_time: now.plus_seconds(300),
+            expires: Expiration::AtTime(now.plus_seconds(400)),
+            end_height: None,
+            end_time: None,
+        }]
+    );
+
+    // a user dropped by a transfer ends at the block of the transfer
+    let set_user_msg = ExecuteMsg::SetUser {
+        token_id: token_id.clone(),
+        user: String::from("vesta"),
+        expires: Some(Expiration::AtTime(now.plus_seconds(1000))),
+    };
+    contract
+        .execute(
+            deps.as_mut(),
+            last_env.clone(),
+            mock_info("ceres", &[]),
+            set_user_msg,
+        )
+        .unwrap();
+    let mut transfer_env = last_env;
+    transfer_env.block.height += 1;
+    transfer_env.block.time = now.plus_seconds(600);
+    let transfer_msg = ExecuteMsg::TransferNft {
+        recipient: String::from("venus"),
+        token_id: token_id.clone(),
+    };
+    contract
+        .execute(
+            deps.as_mut(),
+            transfer_env.clone(),
+            mock_info("ceres", &[]),
+            transfer_msg,
+        )
+        .unwrap();
+    let res = contract
+        .user_history(deps.as_ref(), token_id, Some(3), None)
+        .unwrap();
+    assert_eq!(res.history[0].user, "vesta");
+    assert_eq!(res.history[0].end_height, Some(transfer_env.block.height));
+    assert_eq!(res.history[0].end_time, Some(transfer_env.block.time));
+}
+
+#[test]
+fn query_can_use() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+    let env = mock_env();
+
+    let token_id = "melt".to_string();
+    let mint_msg = ExecuteMsg::Mint {
+        token_id: token_id.clone(),
+        owner: String::from("venus"),
+        token_uri: None,
+        extension: None,
+    };
+    contract
+        .execute(deps.as_mut(), env.clone(), mock_info(MINTER, &[]), mint_msg)
+        .unwrap();
+    let can_use = |deps: Deps, env: &Env, address: &str| {
+        contract
+            .can_use(deps, env.clone(), token_id.clone(), String::from(address))
+            .unwrap()
+    };
+
+    // without a user, only the owner can use the token
+    assert_eq!(can_use(deps.as_ref(), &env, "venus"), CanUseResponse::Owner);
+    assert_eq!(can_use(deps.as_ref(), &env, "ceres"), CanUseResponse::None);
+
+    // an active user has exclusive use
+    let expires = Expiration::AtHeight(env.block.height + 10);
+    let set_user_msg = ExecuteMsg::SetUser {
+        token_id: token_id.clone(),
+        user: String::from("ceres"),
+        expires: Some(expires),
+    };
+    contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("venus", &[]),
+            set_user_msg,
+        )
+        .unwrap();
+    assert_eq!(
+        can_use(deps.as_ref(), &env, "ceres"),
+        CanUseResponse::ActiveUser { expires }
+    );
+    assert_eq!(can_use(deps.as_ref(), &env, "venus"), CanUseResponse::None);
+    assert!(!can_use(deps.as_ref(), &env, "venus").can_use());
+
+    // once it expires the owner gets the token back
+    let mut later_env = env;
+    later_env.block.height += 10;
+    assert_eq!(
+        can_use(deps.as_ref(), &later_env, "ceres"),
+        CanUseResponse::None
+    );
+    assert_eq!(
+        can_use(deps.as_ref(), &later_env, "venus"),
+        CanUseResponse::Owner
+    );
+    assert!(can_use(deps.as_ref(), &later_env, "venus").can_use());
+}
+
+#[test]
+fn base_uri() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+    let minter = mock_info(MINTER, &[]);
+
+    for (token_id, token_uri) in [("1", None), ("2", Some("ipfs://QmOwn/2.json".to_string()))] {
+        let mint_msg = ExecuteMsg::Mint {
+            token_id: token_id.to_string(),
+            owner: String::from("medusa"),
+            token_uri,
+            extension: None,
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), minter.clone(), mint_msg)
+            .unwrap();
+    }
+
+    // without a base uri, tokens minted without a token uri have none
+    let info = contract.nft_info(deps.as_ref(), "1".to_string()).unwrap();
+    assert_eq!(info.token_uri, None);
+
+    // only the owner can set the base uri
+    let update_msg = ExecuteMsg::UpdateBaseUri {
+        base_uri: Some("ipfs://QmBase/".to_string()),
+    };
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("random", &[]),
+            update_msg.clone(),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::Ownership(OwnershipError::NotOwner));
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            minter.clone(),
+            update_msg.clone(),
+        )
+        .unwrap();
+    let info = contract.contract_info(deps.as_ref()).unwrap();
+    assert_eq!(info.base_uri, Some("ipfs://QmBase/".to_string()));
+
+    // the token uri is derived from it unless one is stored
+    let info = contract.nft_info(deps.as_ref(), "1".to_string()).unwrap();
+    assert_eq!(info.token_uri, Some("ipfs://QmBase/1".to_string()));
+    let info = contract
+        .all_nft_info(deps.as_ref(), mock_env(), "1".to_string(), false)
+        .unwrap();
+    assert_eq!(info.info.token_uri, Some("ipfs://QmBase/1".to_string()));
+    let info = contract.nft_info(deps.as_ref(), "2".to_string()).unwrap();
+    assert_eq!(info.token_uri, Some("ipfs://QmOwn/2.json".to_string()));
+
+    // once frozen it cannot change anymore
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            minter.clone(),
+            ExecuteMsg::Freeze {},
+        )
+        .unwrap();
+    let frozen: bool = from_json(
+        contract
+            .query(deps.as_ref(), mock_env(), QueryMsg::Frozen {})
+            .unwrap(),
+    )
+    .unwrap();
+    assert!(frozen);
+    let err = contract
+        .execute(deps.as_mut(), mock_env(), minter, update_msg)
+        .unwrap_err();
+    assert_eq!(err, ContractError::Frozen {});
+}
+
+#[test]
+fn replacing_user_with_eviction_policy() {
+    for (policy, owner_error) in [
+        (EvictionPolicy::Owner, None),
+        (EvictionPolicy::User, Some(ContractError::Unauthorized {})),
+        (
+            EvictionPolicy::Nobody,
+            Some(ContractError::UserNotExpired {}),
+        ),
+    ] {
+        let mut deps = mock_dependencies();
+        let contract = Cw721Contract::<Extension, Empty, Empty, Empty>::default();
+        let msg = InstantiateMsg {
+            name: CONTRACT_NAME.to_string(),
+            symbol: SYMBOL.to_string(),
+            minter: Some(String::from(MINTER)),
+            withdraw_address: None,
+            eviction_policy: Some(policy),
+            rental_config: None,
+            max_history: None,
+            base_uri: None,
+        };
+        contract
+            .instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg)
+            .unwrap();
+        let mint_msg = ExecuteMsg::Mint {
+            token_id: "grow".to_string(),
+            owner: String::from("demeter"),
+            token_uri: None,
+            extension: None,
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info(MINTER, &[]), mint_msg)
+            .unwrap();
+
+        let owner = mock_info("demeter", &[]);
+        let env = mock_env();
+        let set_user = |user: &str, height: u64| ExecuteMsg::SetUser {
+            token_id: "grow".to_string(),
+            user: user.to_string(),
+            expires: Some(Expiration::AtHeight(height)),
+        };
+        contract
+            .execute(
+                deps.as_mut(),
+                env.clone(),
+                owner.clone(),
+                set_user("ceres", env.block.height + 10),
+            )
+            .unwrap();
+
+        // the owner can always extend the rental of the active user
+        contract
+            .execute(
+                deps.as_mut(),
+                env.clone(),
+                owner.clone(),
+                set_user("ceres", env.block.height + 20),
+            )
+            .unwrap();
+
+        // replacing it or cutting it short evicts it, as the policy allows
+        for msg in [
+            set_user("pluto", env.block.height + 20),
+            set_user("ceres", env.block.height + 5),
+        ] {
+            let res = contract.execute(deps.as_mut(), env.clone(), owner.clone(), msg);
+            match &owner_error {
+                Some(err) => assert_eq!(res.unwrap_err(), *err),
+                None => {
+                    res.unwrap();
+                }
+            }
+        }
+
+        // once expired, anyone can be set
+        let mut late_env = env;
+        late_env.block.height += 20;
+        contract
+            .execute(
+                deps.as_mut(),
+                late_env.clone(),
+                owner,
+                set_user("pluto", late_env.block.height + 10),
+            )
+            .unwrap();
+        let res = contract
+            .user_of(deps.as_ref(), late_env, "grow".to_string())
+            .unwrap();
+        assert_eq!(res.user, "pluto");
+    }
+}
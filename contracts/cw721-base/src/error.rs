@@ -25,9 +25,27 @@ pub enum ContractError {
     #[error("Cannot set user that is already expired")]
     ExpiredUser {},
 
+    #[error("User not found for: {user}")]
+    UserNotFound { user: String },
+
+    #[error("Cannot remove a user before it expires")]
+    UserNotExpired {},
+
+    #[error("Reservation must end after it starts and cannot start in the past")]
+    InvalidReservation {},
+
+    #[error("Reservation overlaps the current user or another reservation")]
+    ReservationConflict {},
+
+    #[error("Reservation has already started")]
+    ReservationStarted {},
+
     #[error("Approval not found for: {spender}")]
     ApprovalNotFound { spender: String },
 
     #[error("No withdraw address set")]
     NoWithdrawAddress {},
+
+    #[error("The collection is frozen")]
+    Frozen {},
 }
@@ -3,14 +3,16 @@ use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 use cosmwasm_std::{
-    Addr, BankMsg, Binary, Coin, CustomMsg, Deps, DepsMut, Env, MessageInfo, Response, Storage,
+    Addr, Attribute, BankMsg, Binary, BlockInfo, Coin, CustomMsg, Deps, DepsMut, Env, MessageInfo,
+    Order, Response, StdResult, Storage, Timestamp,
 };
+use cw_storage_plus::Bound;
 
 use cw721::{ContractInfoResponse, Cw721Execute, Cw721ReceiveMsg, Expiration};
 
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, InstantiateMsg};
-use crate::state::{Approval, Cw721Contract, TokenInfo, User};
+use crate::msg::{EvictionPolicy, ExecuteMsg, InstantiateMsg, RentalConfig};
+use crate::state::{Approval, Cw721Contract, Reservation, TokenInfo, User, UserRecord};
 
 impl<'a, T, C, E, Q> Cw721Contract<'a, T, C, E, Q>
 where
@@ -29,6 +31,7 @@ where
         let contract_info = ContractInfoResponse {
             name: msg.name,
             symbol: msg.symbol,
+            base_uri: msg.base_uri,
         };
         self.contract_info.save(deps.storage, &contract_info)?;
 
@@ -38,6 +41,14 @@ where
         };
         cw_ownable::initialize_owner(deps.storage, deps.api, Some(owner.as_ref()))?;
 
+        self.eviction_policy
+            .save(deps.storage, &msg.eviction_policy.unwrap_or_default())?;
+        self.rental_config
+            .save(deps.storage, &msg.rental_config.unwrap_or_default())?;
+        if let Some(max_history) = msg.max_history {
+            self.max_history.save(deps.storage, &max_history)?;
+        }
+
         if let Some(address) = msg.withdraw_address {
             self.set_withdraw_address(deps, &owner, address)?;
         }
@@ -85,6 +96,16 @@ where
                 user,
                 expires
             } => self.set_user(deps, env, info, token_id, user, expires),
+            ExecuteMsg::RemoveUser { token_id } => self.remove_user(deps, env, info, token_id),
+            ExecuteMsg::Reserve {
+                token_id,
+                user,
+                start,
+                end,
+            } => self.reserve(deps, env, info, token_id, user, start, end),
+            ExecuteMsg::CancelReservation { token_id, start } => {
+                self.cancel_reservation(deps, env, info, token_id, start)
+            }
             ExecuteMsg::Burn { token_id } => self.burn(deps, env, info, token_id),
             ExecuteMsg::UpdateOwnership(action) => Self::update_ownership(deps, env, info, action),
             ExecuteMsg::Extension { msg: _ } => Ok(Response::default()),
@@ -95,6 +116,10 @@ where
                 self.remove_withdraw_address(deps.storage, &info.sender)
             }
             ExecuteMsg::WithdrawFunds { amount } => self.withdraw_funds(deps.storage, &amount),
+            ExecuteMsg::UpdateBaseUri { base_uri } => {
+                self.update_base_uri(deps.storage, &info.sender, base_uri)
+            }
+            ExecuteMsg::Freeze {} => self.freeze(deps.storage, &info.sender),
         }
     }
 }
@@ -141,6 +166,114 @@ where
             .add_attribute("token_id", token_id))
     }
 
+    #[allow(clippy::too_many_arguments)]
+    pub fn reserve(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        user: String,
+        start: Timestamp,
+        end: Timestamp,
+    ) -> Result<Response<C>, ContractError> {
+        let mut token = self.tokens.load(deps.storage, &token_id)?;
+        self.can_set_new_user(deps.as_ref(), &env, &info, &token)?;
+        let user_addr = deps.api.addr_validate(&user)?;
+        if end <= start || start < env.block.time {
+            return Err(ContractError::InvalidReservation {});
+        }
+
+        self._activate_reservation(deps.storage, &env.block, &token_id, &mut token)?;
+        self.tokens.save(deps.storage, &token_id, &token)?;
+
+        // the current user must be gone by the time the reservation starts
+        if token.user.is_active(&env.block) {
+            match token.user.expires {
+                Expiration::AtTime(expires) if expires <= start => {}
+                _ => return Err(ContractError::ReservationConflict {}),
+            }
+        }
+
+        // neither the reservation before nor the one after may overlap
+        let previous = self
+            .reservations
+            .prefix(&token_id)
+            .range(
+                deps.storage,
+                None,
+                Some(Bound::inclusive(start.nanos())),
+                Order::Descending,
+            )
+            .next()
+            .transpose()?;
+        if matches!(previous, Some((_, ref r)) if r.end > start) {
+            return Err(ContractError::ReservationConflict {});
+        }
+        let next = self
+            .reservations
+            .prefix(&token_id)
+            .range(
+                deps.storage,
+                Some(Bound::exclusive(start.nanos())),
+                None,
+                Order::Ascending,
+            )
+            .next()
+            .transpose()?;
+        if matches!(next, Some((_, ref r)) if r.start < end) {
+            return Err(ContractError::ReservationConflict {});
+        }
+
+        let reservation = Reservation {
+            user: user_addr,
+            reserved_by: info.sender.clone(),
+            start,
+            end,
+        };
+        self.reservations
+            .save(deps.storage, (&token_id, start.nanos()), &reservation)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "reserve")
+            .add_attribute("sender", info.sender)
+            .add_attribute("user", user)
+            .add_attribute("token_id", token_id)
+            .add_attribute("start", start.to_string())
+            .add_attribute("end", end.to_string()))
+    }
+
+    pub fn cancel_reservation(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        start: Timestamp,
+    ) -> Result<Response<C>, ContractError> {
+        let token = self.tokens.load(deps.storage, &token_id)?;
+        let reservation = self
+            .reservations
+            .load(deps.storage, (&token_id, start.nanos()))?;
+        // a reservation that started is the user now, see RemoveUser
+        if reservation.start <= env.block.time {
+            return Err(ContractError::ReservationStarted {});
+        }
+        if reservation.user != info.sender {
+            self.can_set_new_user(deps.as_ref(), &env, &info, &token)?;
+        }
+
+        self.reservations
+            .remove(deps.storage, (&token_id, start.nanos()))?;
+
+        Ok(Response::new()
+            .add_attribute("action", "cancel_reservation")
+            .add_attribute("sender", info.sender)
+            .add_attribute("user", reservation.user)
+            .add_attribute("token_id", token_id)
+            .add_attribute("start", start.to_string()))
+    }
+
     pub fn update_ownership(
         deps: DepsMut,
         env: Env,
@@ -183,6 +316,37 @@ where
         }
     }
 
+    pub fn update_base_uri(
+        &self,
+        storage: &mut dyn Storage,
+        sender: &Addr,
+        base_uri: Option<String>,
+    ) -> Result<Response<C>, ContractError> {
+        cw_ownable::assert_owner(storage, sender)?;
+        if self.is_frozen(storage)? {
+            return Err(ContractError::Frozen {});
+        }
+        let mut contract_info = self.contract_info.load(storage)?;
+        contract_info.base_uri = base_uri;
+        self.contract_info.save(storage, &contract_info)?;
+        Ok(Response::new()
+            .add_attribute("action", "update_base_uri")
+            .add_attribute("base_uri", contract_info.base_uri.unwrap_or_default()))
+    }
+
+    pub fn freeze(
+        &self,
+        storage: &mut dyn Storage,
+        sender: &Addr,
+    ) -> Result<Response<C>, ContractError> {
+        cw_ownable::assert_owner(storage, sender)?;
+        if self.is_frozen(storage)? {
+            return Err(ContractError::Frozen {});
+        }
+        self.frozen.save(storage, &true)?;
+        Ok(Response::new().add_attribute("action", "freeze"))
+    }
+
     pub fn withdraw_funds(
         &self,
         storage: &mut dyn Storage,
@@ -223,13 +387,14 @@ where
         recipient: String,
         token_id: String,
     ) -> Result<Response<C>, ContractError> {
-        self._transfer_nft(deps, &env, &info, &recipient, &token_id)?;
+        let (_, dropped_user) = self._transfer_nft(deps, &env, &info, &recipient, &token_id)?;
 
         Ok(Response::new()
             .add_attribute("action", "transfer_nft")
             .add_attribute("sender", info.sender)
             .add_attribute("recipient", recipient)
-            .add_attribute("token_id", token_id))
+            .add_attribute("token_id", token_id)
+            .add_attributes(dropped_user_attributes(dropped_user)))
     }
 
     fn send_nft(
@@ -242,7 +407,7 @@ where
         msg: Binary,
     ) -> Result<Response<C>, ContractError> {
         // Transfer token
-        self._transfer_nft(deps, &env, &info, &contract, &token_id)?;
+        let (_, dropped_user) = self._transfer_nft(deps, &env, &info, &contract, &token_id)?;
 
         let send = Cw721ReceiveMsg {
             sender: info.sender.to_string(),
@@ -256,7 +421,8 @@ where
             .add_attribute("action", "send_nft")
             .add_attribute("sender", info.sender)
             .add_attribute("recipient", contract)
-            .add_attribute("token_id", token_id))
+            .add_attribute("token_id", token_id)
+            .add_attributes(dropped_user_attributes(dropped_user)))
     }
 
     fn set_user(
@@ -280,6 +446,26 @@ where
             )
     }
 
+    fn remove_user(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+    ) -> Result<Response<C>, ContractError> {
+        let token = self.tokens.load(deps.storage, &token_id)?;
+        let user = self
+            .current_user(deps.storage, &env.block, &token_id, &token)?
+            .user;
+        self._update_user(deps, &env, &info, user.as_str(), &token_id, false, None)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "remove_user")
+            .add_attribute("sender", info.sender)
+            .add_attribute("user", user)
+            .add_attribute("token_id", token_id))
+    }
+
     fn approve(
         &self,
         deps: DepsMut,
@@ -364,8 +550,10 @@ where
         info: MessageInfo,
         token_id: String,
     ) -> Result<Response<C>, ContractError> {
-        let token = self.tokens.load(deps.storage, &token_id)?;
+        let mut token = self.tokens.load(deps.storage, &token_id)?;
         self.check_can_send(deps.as_ref(), &env, &info, &token)?;
+        let dropped_user =
+            self._drop_user(deps.storage, &env.block, &token_id, &mut token, true)?;
 
         self.tokens.remove(deps.storage, &token_id)?;
         self.decrement_tokens(deps.storage)?;
@@ -373,7 +561,8 @@ where
         Ok(Response::new()
             .add_attribute("action", "burn")
             .add_attribute("sender", info.sender)
-            .add_attribute("token_id", token_id))
+            .add_attribute("token_id", token_id)
+            .add_attributes(dropped_user_attributes(dropped_user)))
     }
 }
 
@@ -385,6 +574,7 @@ where
     E: CustomMsg,
     Q: CustomMsg,
 {
+    /// Returns the updated token along with the user that was dropped by the transfer, if any
     pub fn _transfer_nft(
         &self,
         deps: DepsMut,
@@ -392,15 +582,184 @@ where
         info: &MessageInfo,
         recipient: &str,
         token_id: &str,
-    ) -> Result<TokenInfo<T>, ContractError> {
+    ) -> Result<(TokenInfo<T>, Option<Addr>), ContractError> {
         let mut token = self.tokens.load(deps.storage, token_id)?;
         // ensure we have permissions
         self.check_can_send(deps.as_ref(), env, info, &token)?;
         // set owner and remove existing approvals
         token.owner = deps.api.addr_validate(recipient)?;
         token.approvals = vec![];
+        // clear the user according to the rental config
+        let dropped_user = self._drop_user(deps.storage, &env.block, token_id, &mut token, false)?;
         self.tokens.save(deps.storage, token_id, &token)?;
-        Ok(token)
+        Ok((token, dropped_user))
+    }
+
+    /// Clears the user of a token that is changing hands, as decided by the `RentalConfig`.
+    /// Expired users are always cleared. With `PersistUntilExpiry` an active user stays on
+    /// transfer and prevents the token from being burned. Reservations are dropped along
+    /// with the user, except on a transfer with `PersistUntilExpiry`.
+    pub fn _drop_user(
+        &self,
+        storage: &mut dyn Storage,
+        block: &BlockInfo,
+        token_id: &str,
+        token: &mut TokenInfo<T>,
+        burn: bool,
+    ) -> Result<Option<Addr>, ContractError> {
+        self._activate_reservation(storage, block, token_id, token)?;
+        let persist = self.rental_config(storage)? == RentalConfig::PersistUntilExpiry;
+        let active = token.user.is_active(block);
+        if burn && persist && active {
+            return Err(ContractError::UserNotExpired {});
+        }
+        if burn || !persist {
+            self._clear_reservations(storage, token_id)?;
+        }
+        if token.user.user.as_str().is_empty() || (persist && active) {
+            return Ok(None);
+        }
+        if active {
+            self._end_user_record(storage, block, token_id, &token.user.user)?;
+        }
+        let user = std::mem::take(&mut token.user);
+        Ok(Some(user.user))
+    }
+
+    /// Stores a reservation that has started as the user of the token. Queries resolve it
+    /// through `current_user`, this makes it stick on the next write to the token.
+    /// Reservations that ended in the meantime are moved to the history first.
+    pub fn _activate_reservation(
+        &self,
+        storage: &mut dyn Storage,
+        block: &BlockInfo,
+        token_id: &str,
+        token: &mut TokenInfo<T>,
+    ) -> StdResult<()> {
+        self._record_ended_reservations(storage, block, token_id)?;
+        if token.user.is_active(block) {
+            return Ok(());
+        }
+        if let Some(reservation) = self.active_reservation(storage, block, token_id)? {
+            self.reservations
+                .remove(storage, (token_id, reservation.start.nanos()))?;
+            let record = UserRecord {
+                user: reservation.user.clone(),
+                set_by: reservation.reserved_by.clone(),
+                start_height: None,
+                start_time: reservation.start,
+                expires: Expiration::AtTime(reservation.end),
+                end_height: None,
+                end_time: None,
+            };
+            self._record_user(storage, token_id, record)?;
+            token.user = reservation.into_user();
+        }
+        Ok(())
+    }
+
+    /// Moves reservations that ended without ever being stored as the user to the history
+    pub fn _record_ended_reservations(
+        &self,
+        storage: &mut dyn Storage,
+        block: &BlockInfo,
+        token_id: &str,
+    ) -> StdResult<()> {
+        let ended = self
+            .reservations
+            .prefix(token_id)
+            .range(storage, None, None, Order::Ascending)
+            .take_while(|r| r.as_ref().map_or(true, |(_, r)| r.start < block.time))
+            .filter(|r| r.as_ref().map_or(true, |(_, r)| r.is_ended(block)))
+            .collect::<StdResult<Vec<_>>>()?;
+        for (start, reservation) in ended {
+            self.reservations.remove(storage, (token_id, start))?;
+            let record = UserRecord {
+                user: reservation.user,
+                set_by: reservation.reserved_by,
+                start_height: None,
+                start_time: reservation.start,
+                expires: Expiration::AtTime(reservation.end),
+                end_height: None,
+                end_time: None,
+            };
+            self._record_user(storage, token_id, record)?;
+        }
+        Ok(())
+    }
+
+    /// Adds a user to the history of the token, dropping the oldest entries beyond `max_history`
+    pub fn _record_user(
+        &self,
+        storage: &mut dyn Storage,
+        token_id: &str,
+        record: UserRecord,
+    ) -> StdResult<()> {
+        let max_history = self.max_history(storage)? as usize;
+        if max_history == 0 {
+            return Ok(());
+        }
+        let ids = self
+            .user_history
+            .prefix(token_id)
+            .keys(storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+        let next_id = ids.last().map_or(0, |id| id + 1);
+        let excess = (ids.len() + 1).saturating_sub(max_history);
+        for id in &ids[..excess] {
+            self.user_history.remove(storage, (token_id, *id));
+        }
+        self.user_history.save(storage, (token_id, next_id), &record)
+    }
+
+    /// Ends the history entry of a user removed before it expired at the current block
+    pub fn _end_user_record(
+        &self,
+        storage: &mut dyn Storage,
+        block: &BlockInfo,
+        token_id: &str,
+        user: &Addr,
+    ) -> StdResult<()> {
+        self._update_open_record(storage, token_id, user, |record| {
+            record.end_height = Some(block.height);
+            record.end_time = Some(block.time);
+        })
+    }
+
+    /// Updates the latest history entry of the token if it is the open entry of `user`.
+    /// An active user is always the latest entry, reservations cannot overlap it.
+    pub fn _update_open_record(
+        &self,
+        storage: &mut dyn Storage,
+        token_id: &str,
+        user: &Addr,
+        update: impl FnOnce(&mut UserRecord),
+    ) -> StdResult<()> {
+        let latest = self
+            .user_history
+            .prefix(token_id)
+            .range(storage, None, None, Order::Descending)
+            .next()
+            .transpose()?;
+        if let Some((id, mut record)) = latest {
+            if record.user == *user && record.end_time.is_none() {
+                update(&mut record);
+                self.user_history.save(storage, (token_id, id), &record)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn _clear_reservations(&self, storage: &mut dyn Storage, token_id: &str) -> StdResult<()> {
+        let starts = self
+            .reservations
+            .prefix(token_id)
+            .keys(storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+        for start in starts {
+            self.reservations.remove(storage, (token_id, start))?;
+        }
+        Ok(())
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -442,62 +801,104 @@ where
         Ok(token)
     }
 
-     #[allow(clippy::too_many_arguments)]
-    pub fn _update_user( //self._update_user(deps, &env, &info, &user, &token_id, true, expires)?;
+    #[allow(clippy::too_many_arguments)]
+    pub fn _update_user(
         &self,
         deps: DepsMut,
         env: &Env,
         info: &MessageInfo,
         user: &str,
         token_id: &str,
+        // if add == false, remove. if add == true, replace the user with this expiration
         add: bool,
         expires: Option<Expiration>,
     ) -> Result<TokenInfo<T>, ContractError> {
         let mut token = self.tokens.load(deps.storage, token_id)?;
-        // ensure we have permissions
-        self.check_can_approve(deps.as_ref(), env, info, &token)?;
+        self._activate_reservation(deps.storage, &env.block, token_id, &mut token)?;
 
-        // update the approval list (remove any for the same spender before adding)
-        let user_addr = deps.api.addr_validate(user)?;
-        //token.user.retain(|apr| apr.spender != user_addr);
+        if add {
+            // ensure we have permissions
+            self.can_set_new_user(deps.as_ref(), env, info, &token)?;
 
-        //check if the expire is true, if not no new user
-        self.can_set_new_user(deps.as_ref(), env, info, &token)?;
-        
-        //CHECK IF WE NEED THIS OR NOT ??????????????????????????????????????????????????????????????????????????
-        //most likely do not need it, as it is not flexibe, but users have to ensure they are given the right by contracts
-        //hence are sure they wont have the role change wrongfully by owners
-        
-        /* if token.user.expires.is_expired(&env.block){
-            return Err(ContractError::ExistingUserNotExpired {});
-        }
-        */
-        //token.user = User::default(); //Default::default(); User::default()
-        //We need to reset it fisrt to empty 
-        //println!("Resetted User from old state:  {:?}", token.user);
+            let user_addr = deps.api.addr_validate(user)?;
 
-        // only difference between approve and revoke
-        if add { //we are using this same function for remove user, thats why we have this 'add' which if tru means setUser, if false mean removeUser
             // reject expired data as invalid
             let expires = expires.unwrap_or_default();
-            println!("PASSED TIMESTAMP:  {:?}", expires);
             if expires.is_expired(&env.block) {
-                println!("is expired ???:  {:?} and block time is: {:?}", expires.is_expired(&env.block), &env.block.time);
                 return Err(ContractError::ExpiredUser {});
             }
-            
+
+            // replacing an active user or cutting its rental short evicts it
+            let active = token.user.is_active(&env.block);
+            let same_user = token.user.user == user_addr;
+            let extends = same_user && expires >= token.user.expires;
+            if active && !extends {
+                self.check_can_remove_user(deps.as_ref(), env, info, &token)?;
+            }
+
+            // the new user must be gone before the next reservation starts
+            let next = self
+                .reservations
+                .prefix(token_id)
+                .range(
+                    deps.storage,
+                    Some(Bound::exclusive(env.block.time.nanos())),
+                    None,
+                    Order::Ascending,
+                )
+                .next()
+                .transpose()?;
+            if let Some((_, reservation)) = next {
+                match expires {
+                    Expiration::AtTime(time) if time <= reservation.start => {}
+                    _ => return Err(ContractError::ReservationConflict {}),
+                }
+            }
+
+            if active && same_user {
+                // the rental goes on with the new expiration, it is still the same entry
+                self._update_open_record(deps.storage, token_id, &user_addr, |record| {
+                    record.expires = expires
+                })?;
+            } else {
+                if active {
+                    self._end_user_record(deps.storage, &env.block, token_id, &token.user.user)?;
+                }
+                let record = UserRecord {
+                    user: user_addr.clone(),
+                    set_by: info.sender.clone(),
+                    start_height: Some(env.block.height),
+                    start_time: env.block.time,
+                    expires,
+                    end_height: None,
+                    end_time: None,
+                };
+                self._record_user(deps.storage, token_id, record)?;
+            }
+
             token.user = User {
-            user: user_addr,
-            expires: expires //Expiration::Never {} Expiration::AtTime(time) Expiration::AtHeight(height)
+                user: user_addr,
+                expires, //Expiration::Never {} Expiration::AtTime(time) Expiration::AtHeight(height)
             };
-            
-        } 
+        } else {
+            if user.is_empty() || token.user.user != user {
+                return Err(ContractError::UserNotFound {
+                    user: user.to_string(),
+                });
+            }
+            self.check_can_remove_user(deps.as_ref(), env, info, &token)?;
+
+            if token.user.is_active(&env.block) {
+                self._end_user_record(deps.storage, &env.block, token_id, &token.user.user)?;
+            }
+            token.user = User::default();
+        }
 
         self.tokens.save(deps.storage, token_id, &token)?;
 
         Ok(token)
     }
-    
+
     /// returns true if the sender can execute approve or reject on the contract
     pub fn check_can_approve(
         &self,
@@ -564,6 +965,32 @@ where
         }
     }
 
+    /// returns true if the sender can remove the current user of the token
+    pub fn check_can_remove_user(
+        &self,
+        deps: Deps,
+        env: &Env,
+        info: &MessageInfo,
+        token: &TokenInfo<T>,
+    ) -> Result<(), ContractError> {
+        let is_user = token.user.user == info.sender;
+
+        // an expired user can be cleared by the user or whoever could set a new one
+        if token.user.is_expired(&env.block) {
+            if is_user {
+                return Ok(());
+            }
+            return self.check_can_approve(deps, env, info, token);
+        }
+
+        match self.eviction_policy(deps.storage)? {
+            EvictionPolicy::Owner => self.check_can_approve(deps, env, info, token),
+            EvictionPolicy::User if is_user => Ok(()),
+            EvictionPolicy::User => Err(ContractError::Unauthorized {}),
+            EvictionPolicy::Nobody => Err(ContractError::UserNotExpired {}),
+        }
+    }
+
     /// returns true if the sender can transfer ownership of the token
     pub fn check_can_send(
         &self,
@@ -602,3 +1029,10 @@ where
         }
     }
 }
+
+fn dropped_user_attributes(dropped_user: Option<Addr>) -> Vec<Attribute> {
+    dropped_user
+        .map(|user| Attribute::new("removed_user", user))
+        .into_iter()
+        .collect()
+}
@@ -7,10 +7,12 @@ use cosmwasm_std::{
 use cw721::{
     AllNftInfoResponse, Approval, ApprovalResponse, ApprovalsResponse, ContractInfoResponse,
     NftInfoResponse, NumTokensResponse, OperatorsResponse, OwnerOfResponse, TokensResponse,
+    UserOfResponse,
 };
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
+use crate::msg::CanUseResponse;
 use crate::{ExecuteMsg, QueryMsg};
 
 #[cw_serde]
@@ -68,13 +70,36 @@ impl<Q: CustomMsg, E: CustomMsg> Cw721Contract<Q, E> {
         &self,
         querier: &QuerierWrapper,
         token_id: T,
-    ) -> StdResult<OwnerOfResponse> {
+    ) -> StdResult<UserOfResponse> {
         let req = QueryMsg::UserOf {
             token_id: token_id.into(),
         };
         self.query(querier, req)
     }
 
+    pub fn can_use<T: Into<String>>(
+        &self,
+        querier: &QuerierWrapper,
+        token_id: T,
+        address: T,
+    ) -> StdResult<CanUseResponse> {
+        let req = QueryMsg::CanUse {
+            token_id: token_id.into(),
+            address: address.into(),
+        };
+        self.query(querier, req)
+    }
+
+    /// returns true if the address is the active user, or the owner while there is no active user
+    pub fn is_usable_by<T: Into<String>>(
+        &self,
+        querier: &QuerierWrapper,
+        token_id: T,
+        address: T,
+    ) -> StdResult<bool> {
+        Ok(self.can_use(querier, token_id, address)?.can_use())
+    }
+
     pub fn approval<T: Into<String>>(
         &self,
         querier: &QuerierWrapper,
@@ -177,6 +202,24 @@ impl<Q: CustomMsg, E: CustomMsg> Cw721Contract<Q, E> {
         self.query(querier, req)
     }
 
+    /// With enumerable extension
+    pub fn tokens_used_by<T: Into<String>>(
+        &self,
+        querier: &QuerierWrapper,
+        user: T,
+        include_expired: bool,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<TokensResponse> {
+        let req = QueryMsg::TokensUsedBy {
+            user: user.into(),
+            include_expired: Some(include_expired),
+            start_after,
+            limit,
+        };
+        self.query(querier, req)
+    }
+
     /// With enumerable extension
     pub fn all_tokens(
         &self,
@@ -12,7 +12,9 @@ mod contract_tests;
 mod multi_tests;
 
 pub use crate::error::ContractError;
-pub use crate::msg::{ExecuteMsg, InstantiateMsg, MinterResponse, QueryMsg};
+pub use crate::msg::{
+    EvictionPolicy, ExecuteMsg, InstantiateMsg, MinterResponse, QueryMsg, RentalConfig,
+};
 pub use crate::state::Cw721Contract;
 
 // These types are re-exported so that contracts interacting with this
@@ -111,6 +113,10 @@ mod tests {
                 symbol: "".into(),
                 minter: Some("other".into()),
                 withdraw_address: None,
+                eviction_policy: None,
+                rental_config: None,
+                max_history: None,
+                base_uri: None,
             },
         )
         .unwrap();
@@ -144,6 +150,10 @@ mod tests {
                 symbol: "".into(),
                 minter: None,
                 withdraw_address: None,
+                eviction_policy: None,
+                rental_config: None,
+                max_history: None,
+                base_uri: None,
             },
         )
         .unwrap();
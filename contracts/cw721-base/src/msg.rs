@@ -1,5 +1,5 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Binary, Coin};
+use cosmwasm_std::{Binary, Coin, Timestamp};
 use cw721::Expiration;
 use cw_ownable::{cw_ownable_execute, cw_ownable_query};
 use schemars::JsonSchema;
@@ -17,6 +17,46 @@ pub struct InstantiateMsg {
     pub minter: Option<String>,
 
     pub withdraw_address: Option<String>,
+
+    /// Who may remove an active user before it expires. Defaults to the owner.
+    pub eviction_policy: Option<EvictionPolicy>,
+
+    /// What happens to the user when the token is transferred or burned.
+    /// Defaults to clearing it, as ERC-4907 does.
+    pub rental_config: Option<RentalConfig>,
+
+    /// How many past users are kept per token, the oldest are dropped first.
+    /// Defaults to 10, 0 disables the history.
+    pub max_history: Option<u32>,
+
+    /// Prefix of the token uri of tokens minted without one, followed by the token_id
+    pub base_uri: Option<String>,
+}
+
+/// Decides who may remove a user from a token before the rental expires.
+/// Once a user has expired, the owner or the user can always clear it.
+#[cw_serde]
+#[derive(Default)]
+pub enum EvictionPolicy {
+    /// The token owner or an approved operator may evict the user
+    #[default]
+    Owner,
+    /// Only the user may give up the token before expiry
+    User,
+    /// Nobody may remove an active user, the rental runs until it expires
+    Nobody,
+}
+
+/// Decides whether the user keeps the token when it changes hands
+#[cw_serde]
+#[derive(Default)]
+pub enum RentalConfig {
+    /// The user is cleared whenever the token is transferred, sent or burned
+    #[default]
+    ClearOnTransfer,
+    /// An active user keeps the token until it expires. Transfers go through
+    /// with the user in place, burning is rejected until the user expires
+    PersistUntilExpiry,
 }
 
 /// This is like Cw721ExecuteMsg but we add a Mint command for an owner
@@ -39,6 +79,21 @@ pub enum ExecuteMsg<T, E> {
         user: String,
         expires: Option<Expiration>,
             },
+    /// Remove the user of the given token. Who may do this before the user
+    /// expires is decided by the `EvictionPolicy` set at instantiation
+    RemoveUser { token_id: String },
+    /// Book the token for `user` from `start` until `end`. Once the reservation starts
+    /// the user is set, expiring at `end`. Can be called by whoever may set the user,
+    /// and must not overlap the current user or another reservation.
+    Reserve {
+        token_id: String,
+        user: String,
+        start: Timestamp,
+        end: Timestamp,
+    },
+    /// Cancel a reservation that has not started yet. Can be called by the
+    /// reserved user or whoever may set the user.
+    CancelReservation { token_id: String, start: Timestamp },
     /// Allows operator to transfer / send the token from the owner's account.
     /// If expiration is set, then this allowance has a time/height limit
     Approve {
@@ -84,6 +139,13 @@ pub enum ExecuteMsg<T, E> {
     /// Withdraw from the contract to the given address. Anyone can call this,
     /// which is okay since withdraw address has been set by owner.
     WithdrawFunds { amount: Coin },
+
+    /// Sets or, with `None`, removes the base uri of the collection, until it is frozen.
+    /// Only owner can call this.
+    UpdateBaseUri { base_uri: Option<String> },
+    /// Freezes the collection metadata for good, the base uri cannot change anymore.
+    /// Only owner can call this.
+    Freeze {},
 }
 
 #[cw_ownable_query]
@@ -104,6 +166,10 @@ pub enum QueryMsg<Q: JsonSchema> {
         token_id: String,
         
     },
+    /// Return whether the given address may use the token right now.
+    /// An active user has exclusive use, the owner may use the token only when there is none.
+    #[returns(CanUseResponse)]
+    CanUse { token_id: String, address: String },
     /// Return operator that can access all of the owner's tokens.
     #[returns(cw721::ApprovalResponse)]
     Approval {
@@ -165,6 +231,40 @@ pub enum QueryMsg<Q: JsonSchema> {
         limit: Option<u32>,
     },
     /// With Enumerable extension.
+    /// Returns all tokens currently rented by the given address, including reservations that
+    /// have started, [] if unset.
+    #[returns(cw721::TokensResponse)]
+    TokensUsedBy {
+        user: String,
+        /// unset or false will filter out expired users, you must set to true to see them
+        include_expired: Option<bool>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Returns the reservations of the given token that have not ended yet, ordered by start
+    #[returns(ReservationsResponse)]
+    Reservations {
+        token_id: String,
+        start_after: Option<Timestamp>,
+        limit: Option<u32>,
+    },
+    /// Returns the reservations of the given user that have not ended yet,
+    /// ordered by (token_id, start)
+    #[returns(ReservationsResponse)]
+    UserReservations {
+        user: String,
+        start_after: Option<(String, Timestamp)>,
+        limit: Option<u32>,
+    },
+    /// Returns the users the given token had, oldest first. At most `max_history`
+    /// entries are kept per token.
+    #[returns(UserHistoryResponse)]
+    UserHistory {
+        token_id: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// With Enumerable extension.
     /// Requires pagination. Lists all token_ids controlled by the contract.
     #[returns(cw721::TokensResponse)]
     AllTokens {
@@ -182,6 +282,10 @@ pub enum QueryMsg<Q: JsonSchema> {
 
     #[returns(Option<String>)]
     GetWithdrawAddress {},
+
+    /// Whether the collection metadata is frozen
+    #[returns(bool)]
+    Frozen {},
 }
 
 /// Shows who can mint these tokens
@@ -190,6 +294,56 @@ pub struct MinterResponse {
     pub minter: Option<String>,
 }
 
+/// Why an address may use a token, see `QueryMsg::CanUse`
+#[cw_serde]
+pub enum CanUseResponse {
+    /// The address owns the token and there is no active user
+    Owner,
+    /// The address is the active user of the token until `expires`
+    ActiveUser { expires: Expiration },
+    /// The address may not use the token
+    None,
+}
+
+impl CanUseResponse {
+    pub fn can_use(&self) -> bool {
+        !matches!(self, CanUseResponse::None)
+    }
+}
+
+#[cw_serde]
+pub struct ReservationResponse {
+    pub token_id: String,
+    pub user: String,
+    pub start: Timestamp,
+    pub end: Timestamp,
+}
+
+#[cw_serde]
+pub struct ReservationsResponse {
+    pub reservations: Vec<ReservationResponse>,
+}
+
+#[cw_serde]
+pub struct UserRecordResponse {
+    /// Use as `start_after` to page through the history
+    pub id: u64,
+    pub user: String,
+    pub set_by: String,
+    /// Not known for reservations
+    pub start_height: Option<u64>,
+    pub start_time: Timestamp,
+    pub expires: Expiration,
+    /// Set if the user was removed before it expired
+    pub end_height: Option<u64>,
+    pub end_time: Option<Timestamp>,
+}
+
+#[cw_serde]
+pub struct UserHistoryResponse {
+    pub history: Vec<UserRecordResponse>,
+}
+
 
 // #[cw_serde]
 // pub struct UserOfResponse {
@@ -1,7 +1,10 @@
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
-use cosmwasm_std::{to_binary, to_json_binary, Addr, Binary, BlockInfo, CustomMsg, Deps, Env, Order, StdError, StdResult,};
+use cosmwasm_std::{
+    to_json_binary, Addr, Binary, BlockInfo, CustomMsg, Deps, Env, Order, StdError, StdResult,
+    Timestamp,
+};
 
 use cw721::{
     AllNftInfoResponse, ApprovalResponse, ApprovalsResponse, ContractInfoResponse, Cw721Query,
@@ -11,7 +14,11 @@ use cw721::{
 use cw_storage_plus::Bound;
 use cw_utils::maybe_addr;
 
-use crate::msg::{MinterResponse, QueryMsg};
+use crate::msg::{
+    CanUseResponse, MinterResponse, QueryMsg, ReservationResponse, ReservationsResponse,
+    UserHistoryResponse, UserRecordResponse,
+};
+use crate::state::Reservation;
 use crate::state::{Approval, Cw721Contract, TokenInfo};
 
 const DEFAULT_LIMIT: u32 = 10;
@@ -36,7 +43,7 @@ where
     fn nft_info(&self, deps: Deps, token_id: String) -> StdResult<NftInfoResponse<T>> {
         let info = self.tokens.load(deps.storage, &token_id)?;
         Ok(NftInfoResponse {
-            token_uri: info.token_uri,
+            token_uri: self.token_uri(deps.storage, &token_id, info.token_uri)?,
             extension: info.extension,
         })
     }
@@ -122,12 +129,11 @@ where
         token_id: String,
     ) -> StdResult<UserOfResponse> {
         let info = self.tokens.load(deps.storage, &token_id)?;
+        let user = self.current_user(deps.storage, &env.block, &token_id, &info)?;
         //We could check if it has expired we return the default state
         //but that would require considering the other variants of Expiration, 
         //so we allow Ui and cotracts to handle that
-        let check = info.user.is_expired(&env.block);
-        println!("Inside contract, is_expired: {:?}", check);
-        if info.user.is_expired(&env.block) {
+        if user.is_expired(&env.block) {
         // If expired, return the default state
         return Ok(UserOfResponse {
             user: "".to_string(), // Update with the appropriate default value
@@ -135,8 +141,8 @@ where
         });
         }
         Ok(UserOfResponse {
-            user: info.user.user.to_string(),
-            expires: info.user.expires,
+            user: user.user.to_string(),
+            expires: user.expires,
         })
     }
 
@@ -251,18 +257,19 @@ where
         include_expired: bool,
     ) -> StdResult<AllNftInfoResponse<T>> {
         let info = self.tokens.load(deps.storage, &token_id)?;
+        let user = self.current_user(deps.storage, &env.block, &token_id, &info)?;
         Ok(AllNftInfoResponse {
             access: OwnerOfResponse {
                 owner: info.owner.to_string(),
                 approvals: humanize_approvals(&env.block, &info, include_expired),
             },
             info: NftInfoResponse {
-                token_uri: info.token_uri,
+                token_uri: self.token_uri(deps.storage, &token_id, info.token_uri)?,
                 extension: info.extension,
             },
             user: UserOfResponse{
-                user: info.user.user.to_string(),
-                expires: info.user.expires,
+                user: user.user.to_string(),
+                expires: user.expires,
             }
         })
     }
@@ -292,8 +299,11 @@ where
             QueryMsg::UserOf {
                 token_id,
             } => {
-                to_binary(&self.user_of(deps, env, token_id)?)
+                to_json_binary(&self.user_of(deps, env, token_id)?)
             },
+            QueryMsg::CanUse { token_id, address } => {
+                to_json_binary(&self.can_use(deps, env, token_id, address)?)
+            }
             QueryMsg::AllNftInfo {
                 token_id,
                 include_expired,
@@ -333,6 +343,34 @@ where
                 start_after,
                 limit,
             } => to_json_binary(&self.tokens(deps, owner, start_after, limit)?),
+            QueryMsg::TokensUsedBy {
+                user,
+                include_expired,
+                start_after,
+                limit,
+            } => to_json_binary(&self.tokens_used_by(
+                deps,
+                env,
+                user,
+                include_expired.unwrap_or(false),
+                start_after,
+                limit,
+            )?),
+            QueryMsg::Reservations {
+                token_id,
+                start_after,
+                limit,
+            } => to_json_binary(&self.reservations(deps, env, token_id, start_after, limit)?),
+            QueryMsg::UserReservations {
+                user,
+                start_after,
+                limit,
+            } => to_json_binary(&self.user_reservations(deps, env, user, start_after, limit)?),
+            QueryMsg::UserHistory {
+                token_id,
+                start_after,
+                limit,
+            } => to_json_binary(&self.user_history(deps, token_id, start_after, limit)?),
             QueryMsg::AllTokens { start_after, limit } => {
                 to_json_binary(&self.all_tokens(deps, start_after, limit)?)
             }
@@ -361,6 +399,7 @@ where
             QueryMsg::GetWithdrawAddress {} => {
                 to_json_binary(&self.withdraw_address.may_load(deps.storage)?)
             }
+            QueryMsg::Frozen {} => to_json_binary(&self.is_frozen(deps.storage)?),
         }
     }
 
@@ -375,6 +414,180 @@ where
     pub fn ownership(deps: Deps) -> StdResult<cw_ownable::Ownership<Addr>> {
         cw_ownable::get_ownership(deps.storage)
     }
+
+    /// can_use tells whether an address may use a token. An active user, including a
+    /// reservation that has started, has exclusive use of the token. The owner may
+    /// use it only when there is no active user.
+    pub fn can_use(
+        &self,
+        deps: Deps,
+        env: Env,
+        token_id: String,
+        address: String,
+    ) -> StdResult<CanUseResponse> {
+        let token = self.tokens.load(deps.storage, &token_id)?;
+        let user = self.current_user(deps.storage, &env.block, &token_id, &token)?;
+        let res = if user.is_active(&env.block) {
+            if user.user == address {
+                CanUseResponse::ActiveUser {
+                    expires: user.expires,
+                }
+            } else {
+                CanUseResponse::None
+            }
+        } else if token.owner == address {
+            CanUseResponse::Owner
+        } else {
+            CanUseResponse::None
+        };
+        Ok(res)
+    }
+
+    /// tokens_used_by returns all tokens the given address is set as user of
+    pub fn tokens_used_by(
+        &self,
+        deps: Deps,
+        env: Env,
+        user: String,
+        include_expired: bool,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<TokensResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.clone().map(|s| Bound::ExclusiveRaw(s.into()));
+
+        let user_addr = deps.api.addr_validate(&user)?;
+        let stored = self
+            .tokens
+            .idx
+            .user
+            .prefix(user_addr.clone())
+            .range(deps.storage, start, None, Order::Ascending)
+            .filter(|r| {
+                include_expired || r.is_err() || !r.as_ref().unwrap().1.user.is_expired(&env.block)
+            })
+            .take(limit)
+            .map(|item| item.map(|(k, _)| k));
+
+        // reservations that have started are the user even before they are stored
+        let reservation_start = start_after.map(|s| Bound::exclusive((s, u64::MAX)));
+        let started = self
+            .reservations
+            .idx
+            .user
+            .prefix(user_addr)
+            .range(deps.storage, reservation_start, None, Order::Ascending)
+            .filter(|r| match r {
+                Ok((_, reservation)) => {
+                    reservation.start <= env.block.time && !reservation.is_ended(&env.block)
+                }
+                Err(_) => true,
+            })
+            .take(limit)
+            .map(|item| item.map(|((token_id, _), _)| token_id));
+
+        let mut tokens = stored.chain(started).collect::<StdResult<Vec<_>>>()?;
+        tokens.sort();
+        tokens.dedup();
+        tokens.truncate(limit);
+
+        Ok(TokensResponse { tokens })
+    }
+
+    /// reservations returns the reservations of a token that have not ended, ordered by start
+    pub fn reservations(
+        &self,
+        deps: Deps,
+        env: Env,
+        token_id: String,
+        start_after: Option<Timestamp>,
+        limit: Option<u32>,
+    ) -> StdResult<ReservationsResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.map(|s| Bound::exclusive(s.nanos()));
+
+        let reservations = self
+            .reservations
+            .prefix(&token_id)
+            .range(deps.storage, start, None, Order::Ascending)
+            .filter(|r| r.is_err() || !r.as_ref().unwrap().1.is_ended(&env.block))
+            .take(limit)
+            .map(|item| item.map(|(_, r)| humanize_reservation(token_id.clone(), r)))
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok(ReservationsResponse { reservations })
+    }
+
+    /// user_reservations returns the reservations of an address that have not ended,
+    /// ordered by token_id and start
+    pub fn user_reservations(
+        &self,
+        deps: Deps,
+        env: Env,
+        user: String,
+        start_after: Option<(String, Timestamp)>,
+        limit: Option<u32>,
+    ) -> StdResult<ReservationsResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start =
+            start_after.map(|(token_id, start)| Bound::exclusive((token_id, start.nanos())));
+
+        let user_addr = deps.api.addr_validate(&user)?;
+        let reservations = self
+            .reservations
+            .idx
+            .user
+            .prefix(user_addr)
+            .range(deps.storage, start, None, Order::Ascending)
+            .filter(|r| r.is_err() || !r.as_ref().unwrap().1.is_ended(&env.block))
+            .take(limit)
+            .map(|item| item.map(|((token_id, _), r)| humanize_reservation(token_id, r)))
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok(ReservationsResponse { reservations })
+    }
+
+    /// user_history returns the past and current users of a token, oldest first
+    pub fn user_history(
+        &self,
+        deps: Deps,
+        token_id: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<UserHistoryResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.map(Bound::exclusive);
+
+        let history = self
+            .user_history
+            .prefix(&token_id)
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| {
+                item.map(|(id, record)| UserRecordResponse {
+                    id,
+                    user: record.user.into_string(),
+                    set_by: record.set_by.into_string(),
+                    start_height: record.start_height,
+                    start_time: record.start_time,
+                    expires: record.expires,
+                    end_height: record.end_height,
+                    end_time: record.end_time,
+                })
+            })
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok(UserHistoryResponse { history })
+    }
+}
+
+fn humanize_reservation(token_id: String, reservation: Reservation) -> ReservationResponse {
+    ReservationResponse {
+        token_id,
+        user: reservation.user.into_string(),
+        start: reservation.start,
+        end: reservation.end,
+    }
 }
 
 fn parse_approval(item: StdResult<(Addr, Expiration)>) -> StdResult<cw721::Approval> {
@@ -3,10 +3,15 @@ use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::marker::PhantomData;
 
-use cosmwasm_std::{Addr, BlockInfo, CustomMsg, StdResult, Storage};
+use cosmwasm_std::{Addr, BlockInfo, CustomMsg, Order, StdResult, Storage, Timestamp};
 
 use cw721::{ContractInfoResponse, Cw721, Expiration};
-use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
+use cw_storage_plus::{Bound, Index, IndexList, IndexedMap, Item, Map, MultiIndex};
+
+use crate::msg::{EvictionPolicy, RentalConfig};
+
+/// How many past users are kept per token unless set at instantiation
+pub const DEFAULT_MAX_HISTORY: u32 = 10;
 
 pub struct Cw721Contract<'a, T, C, E, Q>
 where
@@ -20,6 +25,15 @@ where
     pub operators: Map<'a, (&'a Addr, &'a Addr), Expiration>,
     pub tokens: IndexedMap<'a, &'a str, TokenInfo<T>, TokenIndexes<'a, T>>,
     pub withdraw_address: Item<'a, String>,
+    pub eviction_policy: Item<'a, EvictionPolicy>,
+    pub rental_config: Item<'a, RentalConfig>,
+    /// Stored as (token_id, start in nanoseconds), ordering the reservations of a token by start
+    pub reservations: IndexedMap<'a, (&'a str, u64), Reservation, ReservationIndexes<'a>>,
+    /// Stored as (token_id, sequence), the users a token had, oldest first
+    pub user_history: Map<'a, (&'a str, u64), UserRecord>,
+    pub max_history: Item<'a, u32>,
+    /// Set once the collection metadata can no longer change
+    pub frozen: Item<'a, bool>,
 
     pub(crate) _custom_response: PhantomData<C>,
     pub(crate) _custom_query: PhantomData<Q>,
@@ -49,7 +63,15 @@ where
             "operators",
             "tokens",
             "tokens__owner",
+            "tokens__user",
             "withdraw_address",
+            "eviction_policy",
+            "rental_config",
+            "reservations",
+            "reservations__user",
+            "user_history",
+            "max_history",
+            "frozen",
         )
     }
 }
@@ -60,16 +82,29 @@ where
     E: CustomMsg,
     Q: CustomMsg,
 {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         contract_key: &'a str,
         token_count_key: &'a str,
         operator_key: &'a str,
         tokens_key: &'a str,
         tokens_owner_key: &'a str,
+        tokens_user_key: &'a str,
         withdraw_address_key: &'a str,
+        eviction_policy_key: &'a str,
+        rental_config_key: &'a str,
+        reservations_key: &'a str,
+        reservations_user_key: &'a str,
+        user_history_key: &'a str,
+        max_history_key: &'a str,
+        frozen_key: &'a str,
     ) -> Self {
         let indexes = TokenIndexes {
             owner: MultiIndex::new(token_owner_idx, tokens_key, tokens_owner_key),
+            user: MultiIndex::new(token_user_idx, tokens_key, tokens_user_key),
+        };
+        let reservation_indexes = ReservationIndexes {
+            user: MultiIndex::new(reservation_user_idx, reservations_key, reservations_user_key),
         };
         Self {
             contract_info: Item::new(contract_key),
@@ -77,6 +112,12 @@ where
             operators: Map::new(operator_key),
             tokens: IndexedMap::new(tokens_key, indexes),
             withdraw_address: Item::new(withdraw_address_key),
+            eviction_policy: Item::new(eviction_policy_key),
+            rental_config: Item::new(rental_config_key),
+            reservations: IndexedMap::new(reservations_key, reservation_indexes),
+            user_history: Map::new(user_history_key),
+            max_history: Item::new(max_history_key),
+            frozen: Item::new(frozen_key),
             _custom_response: PhantomData,
             _custom_execute: PhantomData,
             _custom_query: PhantomData,
@@ -93,6 +134,78 @@ where
         Ok(val)
     }
 
+    pub fn eviction_policy(&self, storage: &dyn Storage) -> StdResult<EvictionPolicy> {
+        Ok(self.eviction_policy.may_load(storage)?.unwrap_or_default())
+    }
+
+    pub fn rental_config(&self, storage: &dyn Storage) -> StdResult<RentalConfig> {
+        Ok(self.rental_config.may_load(storage)?.unwrap_or_default())
+    }
+
+    pub fn is_frozen(&self, storage: &dyn Storage) -> StdResult<bool> {
+        Ok(self.frozen.may_load(storage)?.unwrap_or_default())
+    }
+
+    /// The stored token uri, or `base_uri + token_id` without one
+    pub fn token_uri(
+        &self,
+        storage: &dyn Storage,
+        token_id: &str,
+        token_uri: Option<String>,
+    ) -> StdResult<Option<String>> {
+        if token_uri.is_some() {
+            return Ok(token_uri);
+        }
+        let base_uri = self.contract_info.load(storage)?.base_uri;
+        Ok(base_uri.map(|base_uri| format!("{}{}", base_uri, token_id)))
+    }
+
+    pub fn max_history(&self, storage: &dyn Storage) -> StdResult<u32> {
+        Ok(self
+            .max_history
+            .may_load(storage)?
+            .unwrap_or(DEFAULT_MAX_HISTORY))
+    }
+
+    /// Returns the reservation of the token that is in progress at the given block, if any
+    pub fn active_reservation(
+        &self,
+        storage: &dyn Storage,
+        block: &BlockInfo,
+        token_id: &str,
+    ) -> StdResult<Option<Reservation>> {
+        let latest = self
+            .reservations
+            .prefix(token_id)
+            .range(
+                storage,
+                None,
+                Some(Bound::inclusive(block.time.nanos())),
+                Order::Descending,
+            )
+            .next()
+            .transpose()?;
+        Ok(latest
+            .map(|(_, reservation)| reservation)
+            .filter(|reservation| !reservation.is_ended(block)))
+    }
+
+    /// Returns the user of the token at the given block. Once a reservation starts it is
+    /// the user, even before the next write to the token stores it in `TokenInfo`.
+    pub fn current_user(
+        &self,
+        storage: &dyn Storage,
+        block: &BlockInfo,
+        token_id: &str,
+        token: &TokenInfo<T>,
+    ) -> StdResult<User> {
+        if token.user.is_active(block) {
+            return Ok(token.user.clone());
+        }
+        let reservation = self.active_reservation(storage, block, token_id)?;
+        Ok(reservation.map_or_else(|| token.user.clone(), Reservation::into_user))
+    }
+
     pub fn decrement_tokens(&self, storage: &mut dyn Storage) -> StdResult<u64> {
         let val = self.token_count(storage)? - 1;
         self.token_count.save(storage, &val)?;
@@ -147,19 +260,71 @@ impl User {
         self.expires.is_expired(block)
     }
 
-    pub fn default() -> Self {
+    /// true if a user is set and has not expired yet
+    pub fn is_active(&self, block: &BlockInfo) -> bool {
+        !self.user.as_str().is_empty() && !self.is_expired(block)
+    }
+}
+
+impl Default for User {
+    fn default() -> Self {
         User {
             user: Addr::unchecked(""), // Provide a default value for Addr
             expires: Expiration::Never {},
         }
     }
-} 
+}
+
+/// A booking of a token for a future time window. When the window starts the
+/// reserved account becomes the user of the token, expiring at the end of it.
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct Reservation {
+    /// Account that becomes the user of the token
+    pub user: Addr,
+    /// Account that booked the reservation
+    pub reserved_by: Addr,
+    pub start: Timestamp,
+    pub end: Timestamp,
+}
+
+impl Reservation {
+    pub fn is_ended(&self, block: &BlockInfo) -> bool {
+        self.end <= block.time
+    }
+
+    pub fn into_user(self) -> User {
+        User {
+            user: self.user,
+            expires: Expiration::AtTime(self.end),
+        }
+    }
+}
+
+/// An entry in the rental history of a token
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct UserRecord {
+    pub user: Addr,
+    /// Account that set the user, or booked the reservation it came from
+    pub set_by: Addr,
+    /// Block in which the user was set. A reservation starts at a time rather than in a block
+    /// of this contract, so it is not known for reservations.
+    pub start_height: Option<u64>,
+    /// When the user started, for a reservation this is its start
+    pub start_time: Timestamp,
+    pub expires: Expiration,
+    /// Block in which the user was removed, if that was before it expired
+    pub end_height: Option<u64>,
+    /// When the user was removed, if that was before it expired
+    pub end_time: Option<Timestamp>,
+}
 
 pub struct TokenIndexes<'a, T>
 where
     T: Serialize + DeserializeOwned + Clone,
 {
     pub owner: MultiIndex<'a, Addr, TokenInfo<T>, String>,
+    /// Indexes tokens by the address currently set as their user (renter)
+    pub user: MultiIndex<'a, Addr, TokenInfo<T>, String>,
 }
 
 impl<'a, T> IndexList<TokenInfo<T>> for TokenIndexes<'a, T>
@@ -167,7 +332,7 @@ where
     T: Serialize + DeserializeOwned + Clone,
 {
     fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<TokenInfo<T>>> + '_> {
-        let v: Vec<&dyn Index<TokenInfo<T>>> = vec![&self.owner];
+        let v: Vec<&dyn Index<TokenInfo<T>>> = vec![&self.owner, &self.user];
         Box::new(v.into_iter())
     }
 }
@@ -175,3 +340,22 @@ where
 pub fn token_owner_idx<T>(_pk: &[u8], d: &TokenInfo<T>) -> Addr {
     d.owner.clone()
 }
+
+pub fn token_user_idx<T>(_pk: &[u8], d: &TokenInfo<T>) -> Addr {
+    d.user.user.clone()
+}
+
+pub struct ReservationIndexes<'a> {
+    pub user: MultiIndex<'a, Addr, Reservation, (String, u64)>,
+}
+
+impl<'a> IndexList<Reservation> for ReservationIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Reservation>> + '_> {
+        let v: Vec<&dyn Index<Reservation>> = vec![&self.user];
+        Box::new(v.into_iter())
+    }
+}
+
+pub fn reservation_user_idx(_pk: &[u8], d: &Reservation) -> Addr {
+    d.user.clone()
+}
@@ -1,7 +1,7 @@
 use cosmwasm_schema::write_api;
 use cosmwasm_std::Empty;
 
-use cw721_base::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use cw721_minter::{ExecuteMsg, InstantiateMsg, QueryMsg};
 
 fn main() {
     write_api! {
@@ -2,8 +2,10 @@
 use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
 
 use cosmwasm_std::{
-    from_json, to_json_binary, Addr, Coin, CosmosMsg, DepsMut, Empty, Response, StdError, WasmMsg,
+    coin, coins, from_json, to_json_binary, Addr, BankMsg, Coin, CosmosMsg, DepsMut, Empty,
+    HexBinary, Response, StdError, SubMsg, WasmMsg,
 };
+use sha2::{Digest, Sha256};
 
 use cw721::{
     Approval, ApprovalResponse, ContractInfoResponse, Cw721Query, Cw721ReceiveMsg, Expiration,
@@ -11,6 +13,8 @@ use cw721::{
 };
 use cw_ownable::OwnershipError;
 
+use crate::msg::{ActivePhaseResponse, RandomMintResponse};
+use crate::state::{Phase, Sale};
 use crate::{
     ContractError, Cw721Contract, ExecuteMsg, Extension, InstantiateMsg, MinterResponse, QueryMsg,
 };
@@ -26,6 +30,9 @@ fn setup_contract(deps: DepsMut<'_>) -> Cw721Contract<'static, Extension, Empty,
         symbol: SYMBOL.to_string(),
         minter: Some(String::from(MINTER)),
         withdraw_address: None,
+        placeholder_uri: None,
+        uri_suffix: None,
+        sale: None,
     };
     let info = mock_info("creator", &[]);
     let res = contract.instantiate(deps, mock_env(), info, msg).unwrap();
@@ -43,6 +50,9 @@ fn proper_instantiation() {
         symbol: SYMBOL.to_string(),
         minter: Some(String::from(MINTER)),
         withdraw_address: Some(String::from(MINTER)),
+        placeholder_uri: None,
+        uri_suffix: None,
+        sale: None,
     };
     let info = mock_info("creator", &[]);
 
@@ -61,6 +71,7 @@ fn proper_instantiation() {
         ContractInfoResponse {
             name: CONTRACT_NAME.to_string(),
             symbol: SYMBOL.to_string(),
+            base_uri: None,
         }
     );
 
@@ -1001,3 +1012,922 @@ fn query_tokens_by_owner() {
         .unwrap();
     assert_eq!(&by_demeter[1..], &tokens.tokens[..]);
 }
+
+fn setup_sale(deps: DepsMut<'_>) -> Cw721Contract<'static, Extension, Empty, Empty, Empty> {
+    let contract = Cw721Contract::default();
+    let msg = InstantiateMsg {
+        name: CONTRACT_NAME.to_string(),
+        symbol: SYMBOL.to_string(),
+        minter: Some(String::from(MINTER)),
+        withdraw_address: Some(String::from("treasury")),
+        placeholder_uri: None,
+        uri_suffix: None,
+        sale: Some(Sale {
+            max_supply: 3,
+            phases: vec![Phase {
+                id: 0,
+                start: Expiration::AtHeight(0),
+                end: Expiration::Never {},
+                price: coin(100, "ustars"),
+                per_address_limit: 2,
+                merkle_root: None,
+            }],
+        }),
+    };
+    let info = mock_info("creator", &[]);
+    contract.instantiate(deps, mock_env(), info, msg).unwrap();
+    contract
+}
+
+fn mint_next_msg(owner: &str) -> ExecuteMsg<Extension, Empty> {
+    ExecuteMsg::MintNext {
+        owner: owner.to_string(),
+        proof: None,
+    }
+}
+
+fn hash_pair(a: &[u8], b: &[u8]) -> HexBinary {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    HexBinary::from(
+        Sha256::new()
+            .chain_update(first)
+            .chain_update(second)
+            .finalize()
+            .as_slice(),
+    )
+}
+
+#[test]
+fn sale_minting() {
+    let mut deps = mock_dependencies();
+    let contract = setup_sale(deps.as_mut());
+    let minted_token_id = |res: &Response| {
+        res.attributes
+            .iter()
+            .find(|attr| attr.key == "token_id")
+            .unwrap()
+            .value
+            .clone()
+    };
+
+    // anyone can mint paying exactly the price
+    for funds in [vec![], coins(99, "ustars"), coins(100, "uatom")] {
+        let err = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &funds),
+                mint_next_msg("alice"),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::IncorrectPayment {
+                price: coin(100, "ustars")
+            }
+        );
+    }
+    let res = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &coins(100, "ustars")),
+            mint_next_msg("alice"),
+        )
+        .unwrap();
+
+    // proceeds go to the withdraw address
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "treasury".to_string(),
+            amount: coins(100, "ustars"),
+        })]
+    );
+
+    // token ids count up from 1, buyers set no metadata of their own
+    assert_eq!(minted_token_id(&res), "1");
+    let info = contract.nft_info(deps.as_ref(), "1".to_string()).unwrap();
+    assert_eq!(
+        info,
+        NftInfoResponse::<Extension> {
+            token_uri: None,
+            extension: None,
+        }
+    );
+
+    // each address can mint 2 tokens, whoever they are minted for
+    let res = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &coins(100, "ustars")),
+            mint_next_msg("bob"),
+        )
+        .unwrap();
+    assert_eq!(minted_token_id(&res), "2");
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &coins(100, "ustars")),
+            mint_next_msg("alice"),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::WalletLimitReached { limit: 2 });
+    assert_eq!(
+        contract.minted(deps.as_ref(), "alice".to_string()).unwrap(),
+        2
+    );
+    assert_eq!(
+        contract.minted(deps.as_ref(), "bob".to_string()).unwrap(),
+        0
+    );
+
+    // no more than 3 tokens can be minted, burning one does not make room
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bob", &coins(100, "ustars")),
+            mint_next_msg("bob"),
+        )
+        .unwrap();
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bob", &[]),
+            ExecuteMsg::Burn {
+                token_id: "2".to_string(),
+            },
+        )
+        .unwrap();
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("carol", &coins(100, "ustars")),
+            mint_next_msg("carol"),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::SoldOut {});
+
+    // not even by the minter
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            ExecuteMsg::Mint {
+                token_id: "reserved".to_string(),
+                owner: MINTER.to_string(),
+                token_uri: None,
+                extension: None,
+            },
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::SoldOut {});
+
+    // buyers cannot pick token ids
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("carol", &coins(100, "ustars")),
+            ExecuteMsg::Mint {
+                token_id: "rare".to_string(),
+                owner: "carol".to_string(),
+                token_uri: None,
+                extension: None,
+            },
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::Ownership(OwnershipError::NotOwner));
+
+    // only the owner can close the sale, after which only the minter can mint
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("random", &[]),
+            ExecuteMsg::UpdateSale { sale: None },
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::Ownership(OwnershipError::NotOwner));
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            ExecuteMsg::UpdateSale { sale: None },
+        )
+        .unwrap();
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("carol", &coins(100, "ustars")),
+            mint_next_msg("carol"),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::Ownership(OwnershipError::NotOwner));
+    let res = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            mint_next_msg("carol"),
+        )
+        .unwrap();
+    assert_eq!(minted_token_id(&res), "4");
+}
+
+#[test]
+fn sale_phases() {
+    let mut deps = mock_dependencies();
+    let contract = setup_sale(deps.as_mut());
+
+    // allowlist of alice, bob and carol
+    let leaves: Vec<HexBinary> = ["alice", "bob", "carol"]
+        .iter()
+        .map(|address| HexBinary::from(Sha256::digest(address.as_bytes()).as_slice()))
+        .collect();
+    let alice_bob = hash_pair(&leaves[0], &leaves[1]);
+    let root = hash_pair(&alice_bob, &leaves[2]);
+    let alice_proof = vec![leaves[1].clone(), leaves[2].clone()];
+    let carol_proof = vec![alice_bob];
+
+    let presale = Phase {
+        id: 1,
+        start: Expiration::AtHeight(12_350),
+        end: Expiration::AtHeight(12_360),
+        price: coin(50, "ustars"),
+        per_address_limit: 1,
+        merkle_root: Some(root),
+    };
+    let public = Phase {
+        id: 2,
+        start: Expiration::AtHeight(12_360),
+        end: Expiration::AtHeight(12_370),
+        price: coin(100, "ustars"),
+        per_address_limit: 1,
+        merkle_root: None,
+    };
+
+    // phases must end after they start
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            ExecuteMsg::UpdateSale {
+                sale: Some(Sale {
+                    max_supply: 10,
+                    phases: vec![Phase {
+                        end: Expiration::AtTime(mock_env().block.time),
+                        ..presale.clone()
+                    }],
+                }),
+            },
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::InvalidPhase {});
+
+    // and have an id of their own
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            ExecuteMsg::UpdateSale {
+                sale: Some(Sale {
+                    max_supply: 10,
+                    phases: vec![presale.clone(), presale.clone()],
+                }),
+            },
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::DuplicatePhaseId { id: 1 });
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            ExecuteMsg::UpdateSale {
+                sale: Some(Sale {
+                    max_supply: 10,
+                    phases: vec![presale.clone(), public.clone()],
+                }),
+            },
+        )
+        .unwrap();
+
+    let mint = |deps: DepsMut, height: u64, sender: &str, price: u128, proof: &[HexBinary]| {
+        let mut env = mock_env();
+        env.block.height = height;
+        let msg = ExecuteMsg::MintNext {
+            owner: sender.to_string(),
+            proof: Some(proof.to_vec()),
+        };
+        contract.execute(deps, env, mock_info(sender, &coins(price, "ustars")), msg)
+    };
+
+    // nothing can be minted before the first phase
+    let err = mint(deps.as_mut(), 12_345, "alice", 50, &alice_proof).unwrap_err();
+    assert_eq!(err, ContractError::NoActivePhase {});
+    let mut env = mock_env();
+    assert_eq!(
+        contract
+            .active_phase(deps.as_ref(), env.clone(), "alice".to_string())
+            .unwrap(),
+        None
+    );
+
+    // allowlisted addresses mint in the presale with a valid proof
+    env.block.height = 12_350;
+    assert_eq!(
+        contract
+            .active_phase(deps.as_ref(), env, "alice".to_string())
+            .unwrap(),
+        Some(ActivePhaseResponse {
+            id: 1,
+            phase: presale.clone(),
+            remaining: 1,
+        })
+    );
+    mint(deps.as_mut(), 12_350, "alice", 50, &alice_proof).unwrap();
+    mint(deps.as_mut(), 12_351, "carol", 50, &carol_proof).unwrap();
+    let err = mint(deps.as_mut(), 12_352, "alice", 50, &alice_proof).unwrap_err();
+    assert_eq!(err, ContractError::WalletLimitReached { limit: 1 });
+    let err = mint(deps.as_mut(), 12_352, "dave", 50, &alice_proof).unwrap_err();
+    assert_eq!(err, ContractError::NotAllowlisted {});
+    let err = mint(deps.as_mut(), 12_352, "bob", 50, &carol_proof).unwrap_err();
+    assert_eq!(err, ContractError::NotAllowlisted {});
+
+    // minted counts stay with their phase when the phases are reordered
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            ExecuteMsg::UpdateSale {
+                sale: Some(Sale {
+                    max_supply: 10,
+                    phases: vec![public.clone(), presale],
+                }),
+            },
+        )
+        .unwrap();
+    let err = mint(deps.as_mut(), 12_352, "alice", 50, &alice_proof).unwrap_err();
+    assert_eq!(err, ContractError::WalletLimitReached { limit: 1 });
+
+    // the public phase is open to everyone, with its own price and limit
+    let mut env = mock_env();
+    env.block.height = 12_360;
+    assert_eq!(
+        contract
+            .active_phase(deps.as_ref(), env, "alice".to_string())
+            .unwrap(),
+        Some(ActivePhaseResponse {
+            id: 2,
+            phase: public,
+            remaining: 1,
+        })
+    );
+    let err = mint(deps.as_mut(), 12_360, "dave", 50, &[]).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::IncorrectPayment {
+            price: coin(100, "ustars")
+        }
+    );
+    mint(deps.as_mut(), 12_360, "dave", 100, &[]).unwrap();
+    mint(deps.as_mut(), 12_361, "alice", 100, &[]).unwrap();
+    assert_eq!(
+        contract.minted(deps.as_ref(), "alice".to_string()).unwrap(),
+        2
+    );
+
+    // and minting closes after the last phase
+    let err = mint(deps.as_mut(), 12_370, "bob", 100, &[]).unwrap_err();
+    assert_eq!(err, ContractError::NoActivePhase {});
+}
+
+#[test]
+fn random_assignment() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+    let seed = HexBinary::from(b"the secret seed".as_slice());
+    let commitment = HexBinary::from(Sha256::digest(seed.as_slice()).as_slice());
+    let pool: Vec<String> = (0..10).map(|id| id.to_string()).collect();
+
+    let execute =
+        |deps: DepsMut, env, msg| contract.execute(deps, env, mock_info(MINTER, &[]), msg);
+    let load_pool = |token_ids: &[String]| ExecuteMsg::LoadTokenPool {
+        token_ids: token_ids.to_vec(),
+    };
+
+    // the pool is loaded before committing to the seed, each id once
+    execute(deps.as_mut(), mock_env(), load_pool(&pool[..6])).unwrap();
+    let err = execute(deps.as_mut(), mock_env(), load_pool(&pool[5..])).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::AlreadyPooled {
+            token_id: "5".to_string()
+        }
+    );
+    execute(deps.as_mut(), mock_env(), load_pool(&pool[6..])).unwrap();
+    let err = execute(deps.as_mut(), mock_env(), mint_next_msg("alice")).unwrap_err();
+    assert_eq!(err, ContractError::NoSeedCommitted {});
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        ExecuteMsg::CommitSeed {
+            commitment: commitment.clone(),
+            reveal_deadline: Expiration::AtHeight(20_000),
+        },
+    )
+    .unwrap();
+    let err = execute(deps.as_mut(), mock_env(), load_pool(&["10".to_string()])).unwrap_err();
+    assert_eq!(err, ContractError::SeedAlreadyCommitted {});
+
+    // token ids cannot be picked anymore
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        ExecuteMsg::Mint {
+            token_id: "0".to_string(),
+            owner: "alice".to_string(),
+            token_uri: None,
+            extension: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::RandomAssignment {});
+
+    // every mint takes a ticket, until there is one for each id of the pool
+    let mut tickets = vec![];
+    for i in 0..10 {
+        let mut env = mock_env();
+        env.block.height += i / 3;
+        let owner = ["alice", "bob"][i as usize % 2];
+        let res = execute(deps.as_mut(), env.clone(), mint_next_msg(owner)).unwrap();
+        assert!(res
+            .attributes
+            .iter()
+            .any(|attr| attr.key == "ticket" && attr.value == i.to_string()));
+        tickets.push((env, owner));
+    }
+    let err = execute(deps.as_mut(), mock_env(), mint_next_msg("alice")).unwrap_err();
+    assert_eq!(err, ContractError::SoldOut {});
+
+    // no token id is known before the seed is revealed
+    assert_eq!(contract.num_tokens(deps.as_ref()).unwrap().count, 0);
+    let assign = |limit| ExecuteMsg::AssignTokens { limit };
+    let err = execute(deps.as_mut(), mock_env(), assign(None)).unwrap_err();
+    assert_eq!(err, ContractError::SeedNotRevealed {});
+
+    // only the committed seed can be revealed, which closes the tickets
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        ExecuteMsg::RevealSeed {
+            seed: HexBinary::from(b"another seed".as_slice()),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::InvalidSeed {});
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        ExecuteMsg::RevealSeed { seed: seed.clone() },
+    )
+    .unwrap();
+    let err = execute(deps.as_mut(), mock_env(), mint_next_msg("alice")).unwrap_err();
+    assert_eq!(err, ContractError::SeedAlreadyRevealed {});
+
+    // then anyone can assign the token ids, in batches
+    let mut assigned = vec![];
+    for limit in [Some(4), None] {
+        let res = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("random", &[]),
+                assign(limit),
+            )
+            .unwrap();
+        assigned.extend(
+            res.attributes
+                .iter()
+                .filter(|attr| attr.key == "token_id")
+                .map(|attr| attr.value.clone()),
+        );
+    }
+    let err = execute(deps.as_mut(), mock_env(), assign(None)).unwrap_err();
+    assert_eq!(err, ContractError::NothingToAssign {});
+    assert_eq!(
+        contract.random_mint_info(deps.as_ref()).unwrap(),
+        RandomMintResponse {
+            commitment: Some(commitment),
+            seed: Some(seed.clone()),
+            reveal_deadline: Some(Expiration::AtHeight(20_000)),
+            pool_size: 0,
+            tickets: 10,
+            assigned: 10,
+            refunded: 0,
+        }
+    );
+
+    // each id of the pool is handed out once, to the owner of its ticket
+    let mut sorted = assigned.clone();
+    sorted.sort_by_key(|id| id.parse::<u32>().unwrap());
+    assert_eq!(sorted, pool);
+    assert_ne!(assigned, pool);
+    for (token_id, (_, owner)) in assigned.iter().zip(&tickets) {
+        let res = contract
+            .owner_of(deps.as_ref(), mock_env(), token_id.clone(), false)
+            .unwrap();
+        assert_eq!(res.owner, *owner);
+    }
+
+    // the revealed seed reproduces the assignment from the tickets' block data
+    let mut replay = pool;
+    let reproduced: Vec<String> = tickets
+        .iter()
+        .enumerate()
+        .map(|(id, (env, owner))| {
+            let transaction_index = env.transaction.as_ref().map_or(0, |tx| tx.index);
+            let hash = Sha256::new()
+                .chain_update(seed.as_slice())
+                .chain_update((id as u32).to_be_bytes())
+                .chain_update(env.block.height.to_be_bytes())
+                .chain_update(env.block.time.nanos().to_be_bytes())
+                .chain_update(transaction_index.to_be_bytes())
+                .chain_update(owner.as_bytes())
+                .finalize();
+            let random = u64::from_be_bytes(hash[..8].try_into().unwrap());
+            replay.swap_remove((random % replay.len() as u64) as usize)
+        })
+        .collect();
+    assert_eq!(reproduced, assigned);
+}
+
+/// Loads a pool of `size` token ids into a sale contract and commits to `seed`, to be revealed
+/// before height `deadline`
+fn setup_random_sale(
+    mut deps: DepsMut<'_>,
+    size: u32,
+    seed: &HexBinary,
+    deadline: u64,
+) -> Cw721Contract<'static, Extension, Empty, Empty, Empty> {
+    let contract = setup_sale(deps.branch());
+    let token_ids = (0..size).map(|id| id.to_string()).collect();
+    let commitment = HexBinary::from(Sha256::digest(seed.as_slice()).as_slice());
+    contract
+        .execute(
+            deps.branch(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            ExecuteMsg::LoadTokenPool { token_ids },
+        )
+        .unwrap();
+    contract
+        .execute(
+            deps,
+            mock_env(),
+            mock_info(MINTER, &[]),
+            ExecuteMsg::CommitSeed {
+                commitment,
+                reveal_deadline: Expiration::AtHeight(deadline),
+            },
+        )
+        .unwrap();
+    contract
+}
+
+#[test]
+fn ticket_refunds() {
+    let mut deps = mock_dependencies();
+    let seed = HexBinary::from(b"the secret seed".as_slice());
+    let deadline = mock_env().block.height + 100;
+    let contract = setup_random_sale(deps.as_mut(), 5, &seed, deadline);
+    let price = coins(100, "ustars");
+
+    // ticket payments are held by the contract
+    for owner in ["alice", "bob"] {
+        let res = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(owner, &price),
+                mint_next_msg(owner),
+            )
+            .unwrap();
+        assert!(res.messages.is_empty());
+    }
+    let withdraw = ExecuteMsg::WithdrawFunds {
+        amount: coin(100, "ustars"),
+    };
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("anyone", &[]),
+            withdraw.clone(),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::TicketPaymentsHeld {});
+
+    // until the deadline the seed can still be revealed
+    let refund = |ticket| ExecuteMsg::RefundTicket { ticket };
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            refund(0),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::RevealDeadlineNotPassed {});
+
+    // after it the seed cannot be revealed anymore
+    let mut env = mock_env();
+    env.block.height = deadline;
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(MINTER, &[]),
+            ExecuteMsg::RevealSeed { seed },
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::RevealDeadlinePassed {});
+
+    // and ticket owners get their payment back, once
+    let err = contract
+        .execute(deps.as_mut(), env.clone(), mock_info("bob", &[]), refund(0))
+        .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let res = contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("alice", &[]),
+            refund(0),
+        )
+        .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "alice".to_string(),
+            amount: price.clone(),
+        })]
+    );
+    contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("alice", &[]),
+            refund(0),
+        )
+        .unwrap_err();
+
+    // the other funds can be withdrawn once every ticket is refunded
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("anyone", &[]),
+            withdraw.clone(),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::TicketPaymentsHeld {});
+    contract
+        .execute(deps.as_mut(), env.clone(), mock_info("bob", &[]), refund(1))
+        .unwrap();
+    contract
+        .execute(deps.as_mut(), env, mock_info("anyone", &[]), withdraw)
+        .unwrap();
+    let random_mint = contract.random_mint_info(deps.as_ref()).unwrap();
+    assert_eq!((random_mint.tickets, random_mint.refunded), (2, 2));
+}
+
+#[test]
+fn minting_token_pool_leftovers() {
+    let mut deps = mock_dependencies();
+    let seed = HexBinary::from(b"the secret seed".as_slice());
+    let contract = setup_random_sale(deps.as_mut(), 5, &seed, mock_env().block.height + 100);
+    let mint_pool = |limit| ExecuteMsg::MintTokenPool {
+        owner: "treasury".to_string(),
+        limit,
+    };
+
+    // two tickets are taken, the seed is revealed before the deadline
+    for owner in ["alice", "bob"] {
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(owner, &coins(100, "ustars")),
+                mint_next_msg(owner),
+            )
+            .unwrap();
+    }
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            mint_pool(None),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::SeedNotRevealed {});
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            ExecuteMsg::RevealSeed { seed },
+        )
+        .unwrap();
+
+    // the leftovers wait for every ticket to be assigned
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            mint_pool(None),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::TicketsNotAssigned {});
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("anyone", &[]),
+            ExecuteMsg::AssignTokens { limit: None },
+        )
+        .unwrap();
+
+    // then the owner mints them, up to the supply of the sale
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            mint_pool(None),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::Ownership(OwnershipError::NotOwner));
+    let res = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            mint_pool(None),
+        )
+        .unwrap();
+    let minted: Vec<_> = res
+        .attributes
+        .iter()
+        .filter(|attr| attr.key == "token_id")
+        .map(|attr| attr.value.clone())
+        .collect();
+    assert_eq!(minted.len(), 1);
+    let owner = contract
+        .owner_of(deps.as_ref(), mock_env(), minted[0].clone(), false)
+        .unwrap();
+    assert_eq!(owner.owner, "treasury");
+    assert_eq!(contract.num_tokens(deps.as_ref()).unwrap().count, 3);
+    assert_eq!(
+        contract.random_mint_info(deps.as_ref()).unwrap().pool_size,
+        2
+    );
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            mint_pool(None),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::SoldOut {});
+
+    // without the sale the rest of the pool can be minted
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            ExecuteMsg::UpdateSale { sale: None },
+        )
+        .unwrap();
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            mint_pool(Some(5)),
+        )
+        .unwrap();
+    assert_eq!(contract.num_tokens(deps.as_ref()).unwrap().count, 5);
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            mint_pool(None),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::EmptyPool {});
+}
+
+#[test]
+fn delayed_reveal() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+
+    // nothing to reveal without a placeholder
+    let reveal = ExecuteMsg::Reveal {
+        base_uri: "ipfs://QmRevealed/".to_string(),
+    };
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            reveal.clone(),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::NoPlaceholder {});
+
+    let mut deps = mock_dependencies();
+    let contract = Cw721Contract::<Extension, Empty, Empty, Empty>::default();
+    let msg = InstantiateMsg {
+        name: CONTRACT_NAME.to_string(),
+        symbol: SYMBOL.to_string(),
+        minter: Some(String::from(MINTER)),
+        withdraw_address: None,
+        placeholder_uri: Some("ipfs://QmPlaceholder".to_string()),
+        uri_suffix: Some(".json".to_string()),
+        sale: None,
+    };
+    contract
+        .instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg)
+        .unwrap();
+    let mint_msg = ExecuteMsg::Mint {
+        token_id: "1".to_string(),
+        owner: "alice".to_string(),
+        token_uri: Some("ipfs://QmOwnUri".to_string()),
+        extension: None,
+    };
+    contract
+        .execute(deps.as_mut(), mock_env(), mock_info(MINTER, &[]), mint_msg)
+        .unwrap();
+
+    // the placeholder is returned until the reveal
+    let info = contract.nft_info(deps.as_ref(), "1".to_string()).unwrap();
+    assert_eq!(info.token_uri, Some("ipfs://QmPlaceholder".to_string()));
+    assert!(!contract.revealed(deps.as_ref()).unwrap());
+
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("random", &[]),
+            reveal.clone(),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::Ownership(OwnershipError::NotOwner));
+    contract
+        .execute(deps.as_mut(), mock_env(), mock_info(MINTER, &[]), reveal)
+        .unwrap();
+
+    // then token uris are derived from the base uri
+    assert!(contract.revealed(deps.as_ref()).unwrap());
+    let info = contract.nft_info(deps.as_ref(), "1".to_string()).unwrap();
+    assert_eq!(info.token_uri, Some("ipfs://QmRevealed/1.json".to_string()));
+    let info = contract
+        .all_nft_info(deps.as_ref(), mock_env(), "1".to_string(), false)
+        .unwrap();
+    assert_eq!(
+        info.info.token_uri,
+        Some("ipfs://QmRevealed/1.json".to_string())
+    );
+
+    // and the reveal cannot be changed
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            ExecuteMsg::Reveal {
+                base_uri: "ipfs://QmOther/".to_string(),
+            },
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::AlreadyRevealed {});
+}
@@ -1,4 +1,4 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{Coin, StdError};
 use cw_ownable::OwnershipError;
 use thiserror::Error;
 
@@ -15,7 +15,7 @@ pub enum ContractError {
 
     #[error("Unauthorized")]
     Unauthorized {},
-    
+
     #[error("token_id already claimed")]
     Claimed {},
 
@@ -30,4 +30,70 @@ pub enum ContractError {
 
     #[error("No withdraw address set")]
     NoWithdrawAddress {},
+
+    #[error("Minting costs exactly {price}")]
+    IncorrectPayment { price: Coin },
+
+    #[error("All tokens have been minted")]
+    SoldOut {},
+
+    #[error("This address already minted {limit} tokens, the most allowed in this phase")]
+    WalletLimitReached { limit: u32 },
+
+    #[error("No sale phase is active")]
+    NoActivePhase {},
+
+    #[error("This address is not on the allowlist of the active phase")]
+    NotAllowlisted {},
+
+    #[error("No placeholder uri to reveal")]
+    NoPlaceholder {},
+
+    #[error("The metadata is already revealed")]
+    AlreadyRevealed {},
+
+    #[error("Token ids are assigned randomly, mint with MintNext")]
+    RandomAssignment {},
+
+    #[error("A seed is already committed")]
+    SeedAlreadyCommitted {},
+
+    #[error("No seed is committed")]
+    NoSeedCommitted {},
+
+    #[error("The seed is already revealed")]
+    SeedAlreadyRevealed {},
+
+    #[error("The seed does not match the commitment")]
+    InvalidSeed {},
+
+    #[error("The seed is not revealed yet")]
+    SeedNotRevealed {},
+
+    #[error("Token id {token_id} is already in the pool")]
+    AlreadyPooled { token_id: String },
+
+    #[error("Every ticket has been assigned a token id")]
+    NothingToAssign {},
+
+    #[error("The reveal deadline is already over")]
+    RevealDeadlinePassed {},
+
+    #[error("Tickets can only be refunded after the reveal deadline")]
+    RevealDeadlineNotPassed {},
+
+    #[error("Ticket payments are held until the seed is revealed or they are refunded")]
+    TicketPaymentsHeld {},
+
+    #[error("Every ticket must be assigned a token id first")]
+    TicketsNotAssigned {},
+
+    #[error("The token pool is empty")]
+    EmptyPool {},
+
+    #[error("Phases must start and end later than they start, both by height or by time")]
+    InvalidPhase {},
+
+    #[error("Phase id {id} is used more than once")]
+    DuplicatePhaseId { id: u32 },
 }
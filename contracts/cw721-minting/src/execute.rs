@@ -3,18 +3,22 @@ use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 use cosmwasm_std::{
-    Addr, BankMsg, Binary, Coin, CustomMsg, Deps, DepsMut, Env, MessageInfo, Response, Storage,
+    Addr, BankMsg, Binary, BlockInfo, Coin, CustomMsg, Deps, DepsMut, Empty, Env, HexBinary,
+    MessageInfo, Response, StdResult, Storage,
 };
 
 use cw721::{ContractInfoResponse, Cw721Execute, Cw721ReceiveMsg, Expiration};
 
 use crate::error::ContractError;
 use crate::msg::{ExecuteMsg, InstantiateMsg};
-use crate::state::{Approval, Cw721Contract, TokenInfo, User};
+use crate::state::{Approval, Cw721Contract, RandomMint, Reveal, Sale, Ticket, TokenInfo, User};
+
+/// Most tickets `AssignTokens` assigns a token id at once
+const MAX_ASSIGN_LIMIT: u32 = 100;
 
 impl<'a, T, C, E, Q> Cw721Contract<'a, T, C, E, Q>
 where
-    T: Serialize + DeserializeOwned + Clone,
+    T: Serialize + DeserializeOwned + Clone + Default,
     C: CustomMsg,
     E: CustomMsg,
     Q: CustomMsg,
@@ -29,15 +33,30 @@ where
         let contract_info = ContractInfoResponse {
             name: msg.name,
             symbol: msg.symbol,
+            base_uri: None,
         };
         self.contract_info.save(deps.storage, &contract_info)?;
 
-        let owner = match msg.minter { 
+        let owner = match msg.minter {
             Some(owner) => deps.api.addr_validate(&owner)?,
             None => info.sender,
         };
         cw_ownable::initialize_owner(deps.storage, deps.api, Some(owner.as_ref()))?;
 
+        if let Some(sale) = msg.sale {
+            sale.validate()?;
+            self.sale.save(deps.storage, &sale)?;
+        }
+
+        if let Some(placeholder_uri) = msg.placeholder_uri {
+            let reveal = Reveal {
+                placeholder_uri,
+                suffix: msg.uri_suffix.unwrap_or_default(),
+                base_uri: None,
+            };
+            self.reveal.save(deps.storage, &reveal)?;
+        }
+
         if let Some(address) = msg.withdraw_address {
             self.set_withdraw_address(deps, &owner, address)?;
         }
@@ -59,6 +78,19 @@ where
                 token_uri,
                 extension,
             } => self.mint(deps, info, token_id, owner, token_uri, extension),
+            ExecuteMsg::MintNext { owner, proof } => self.mint_next(deps, env, info, owner, proof),
+            ExecuteMsg::LoadTokenPool { token_ids } => self.load_token_pool(deps, info, token_ids),
+            ExecuteMsg::Reveal { base_uri } => self.reveal(deps, info, base_uri),
+            ExecuteMsg::CommitSeed {
+                commitment,
+                reveal_deadline,
+            } => self.commit_seed(deps, env, info, commitment, reveal_deadline),
+            ExecuteMsg::RevealSeed { seed } => self.reveal_seed(deps, env, info, seed),
+            ExecuteMsg::AssignTokens { limit } => self.assign_tokens(deps, limit),
+            ExecuteMsg::RefundTicket { ticket } => self.refund_ticket(deps, env, info, ticket),
+            ExecuteMsg::MintTokenPool { owner, limit } => {
+                self.mint_token_pool(deps, info, owner, limit)
+            }
             ExecuteMsg::Approve {
                 spender,
                 token_id,
@@ -83,8 +115,9 @@ where
             ExecuteMsg::SetUser {
                 token_id,
                 user,
-                expires
+                expires,
             } => self.set_user(deps, env, info, token_id, user, expires),
+            ExecuteMsg::RemoveUser { token_id } => self.remove_user(deps, env, info, token_id),
             ExecuteMsg::Burn { token_id } => self.burn(deps, env, info, token_id),
             ExecuteMsg::UpdateOwnership(action) => Self::update_ownership(deps, env, info, action),
             ExecuteMsg::Extension { msg: _ } => Ok(Response::default()),
@@ -95,6 +128,7 @@ where
                 self.remove_withdraw_address(deps.storage, &info.sender)
             }
             ExecuteMsg::WithdrawFunds { amount } => self.withdraw_funds(deps.storage, &amount),
+            ExecuteMsg::UpdateSale { sale } => self.update_sale(deps, info, sale),
         }
     }
 }
@@ -102,7 +136,7 @@ where
 // TODO pull this into some sort of trait extension??
 impl<'a, T, C, E, Q> Cw721Contract<'a, T, C, E, Q>
 where
-    T: Serialize + DeserializeOwned + Clone,
+    T: Serialize + DeserializeOwned + Clone + Default,
     C: CustomMsg,
     E: CustomMsg,
     Q: CustomMsg,
@@ -117,28 +151,492 @@ where
         extension: T,
     ) -> Result<Response<C>, ContractError> {
         cw_ownable::assert_owner(deps.storage, &info.sender)?;
+        if self.assigns_randomly(deps.storage)? {
+            return Err(ContractError::RandomAssignment {});
+        }
+        // tokens of the minter count against the supply of the sale as well
+        if let Some(sale) = self.sale.may_load(deps.storage)? {
+            if self.total_minted(deps.storage)? >= sale.max_supply {
+                return Err(ContractError::SoldOut {});
+            }
+        }
+        let owner_addr = deps.api.addr_validate(&owner)?;
+        self.mint_token(deps.storage, &token_id, owner_addr, token_uri, extension)?;
+        self.increment_total_minted(deps.storage)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "mint")
+            .add_attribute("minter", info.sender)
+            .add_attribute("owner", owner)
+            .add_attribute("token_id", token_id))
+    }
+
+    /// Mints the next token id, the lowest one above the number of tokens minted so far that
+    /// is not taken. Once a seed is committed it takes a ticket for a token id of the pool
+    /// instead.
+    pub fn mint_next(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        owner: String,
+        proof: Option<Vec<HexBinary>>,
+    ) -> Result<Response<C>, ContractError> {
+        let payment = match self.sale.may_load(deps.storage)? {
+            Some(sale) => self.pay_for_mint(
+                deps.storage,
+                &env.block,
+                &info,
+                &sale,
+                &proof.unwrap_or_default(),
+            )?,
+            None => {
+                cw_ownable::assert_owner(deps.storage, &info.sender)?;
+                None
+            }
+        };
+        let owner_addr = deps.api.addr_validate(&owner)?;
+        let total_minted = self.increment_total_minted(deps.storage)?;
+
+        if self.assigns_randomly(deps.storage)? {
+            // the payment stays here, the ticket is refunded if the seed is not revealed
+            let ticket = self.take_ticket(deps.storage, &env, owner_addr, payment)?;
+            return Ok(Response::new()
+                .add_attribute("action", "take_ticket")
+                .add_attribute("minter", info.sender)
+                .add_attribute("owner", owner)
+                .add_attribute("ticket", ticket.to_string()));
+        }
+
+        let mut id = total_minted;
+        while self.tokens.has(deps.storage, &id.to_string()) {
+            id += 1;
+        }
+        let token_id = id.to_string();
+        self.mint_token(deps.storage, &token_id, owner_addr, None, T::default())?;
+
+        // without a withdraw address proceeds stay here until one is set
+        let withdraw_address = self.withdraw_address.may_load(deps.storage)?;
+        let proceeds = payment
+            .zip(withdraw_address)
+            .map(|(payment, address)| BankMsg::Send {
+                to_address: address,
+                amount: vec![payment],
+            });
+
+        Ok(Response::new()
+            .add_messages(proceeds)
+            .add_attribute("action", "mint")
+            .add_attribute("minter", info.sender)
+            .add_attribute("owner", owner)
+            .add_attribute("token_id", token_id))
+    }
+
+    /// Records a random mint with the block data it is taken in, returning its ticket id. The
+    /// token id is drawn once the seed is revealed, so nobody can predict it when minting.
+    fn take_ticket(
+        &self,
+        storage: &mut dyn Storage,
+        env: &Env,
+        owner: Addr,
+        price: Option<Coin>,
+    ) -> Result<u32, ContractError> {
+        let mut random_mint = self
+            .random_mint
+            .may_load(storage)?
+            .ok_or(ContractError::NoSeedCommitted {})?;
+        if random_mint.seed.is_some() {
+            return Err(ContractError::SeedAlreadyRevealed {});
+        }
+        if random_mint.tickets >= self.pool_size(storage)? {
+            return Err(ContractError::SoldOut {});
+        }
+
+        let id = random_mint.tickets;
+        self.tickets
+            .save(storage, id, &Ticket::new(env, owner, price))?;
+        random_mint.tickets += 1;
+        self.random_mint.save(storage, &random_mint)?;
+        Ok(id)
+    }
 
+    /// Mints the token ids the next tickets draw with the revealed seed, in ticket order
+    pub fn assign_tokens(
+        &self,
+        deps: DepsMut,
+        limit: Option<u32>,
+    ) -> Result<Response<C>, ContractError> {
+        let mut random_mint = self
+            .random_mint
+            .may_load(deps.storage)?
+            .ok_or(ContractError::NoSeedCommitted {})?;
+        let seed = random_mint
+            .seed
+            .clone()
+            .ok_or(ContractError::SeedNotRevealed {})?;
+        if random_mint.assigned == random_mint.tickets {
+            return Err(ContractError::NothingToAssign {});
+        }
+
+        let limit = limit.unwrap_or(MAX_ASSIGN_LIMIT).min(MAX_ASSIGN_LIMIT);
+        let start = random_mint.assigned;
+        let end = random_mint.tickets.min(start.saturating_add(limit));
+        let mut res = Response::new().add_attribute("action", "assign_tokens");
+        for id in start..end {
+            let ticket = self.tickets.load(deps.storage, id)?;
+            let token_id = self.draw_token_id(deps.storage, &seed, id, &ticket)?;
+            self.mint_token(
+                deps.storage,
+                &token_id,
+                ticket.owner.clone(),
+                None,
+                T::default(),
+            )?;
+            self.tickets.remove(deps.storage, id);
+            res = res
+                .add_attribute("ticket", id.to_string())
+                .add_attribute("owner", ticket.owner)
+                .add_attribute("token_id", token_id);
+        }
+        random_mint.assigned = end;
+        self.random_mint.save(deps.storage, &random_mint)?;
+
+        Ok(res.add_attribute("assigned", (end - start).to_string()))
+    }
+
+    /// Takes the token id at the pool slot the ticket draws, moving the last token id of the
+    /// pool into the slot
+    fn draw_token_id(
+        &self,
+        storage: &mut dyn Storage,
+        seed: &HexBinary,
+        id: u32,
+        ticket: &Ticket,
+    ) -> Result<String, ContractError> {
+        let pool_size = self.pool_size(storage)?;
+        let slot = ticket.draw(seed, id, pool_size);
+        let last = pool_size - 1;
+        let token_id = self.token_pool.load(storage, slot)?;
+        if slot != last {
+            let moved = self.token_pool.load(storage, last)?;
+            self.token_pool.save(storage, slot, &moved)?;
+        }
+        self.token_pool.remove(storage, last);
+        self.token_pool_size.save(storage, &last)?;
+        Ok(token_id)
+    }
+
+    fn mint_token(
+        &self,
+        storage: &mut dyn Storage,
+        token_id: &str,
+        owner: Addr,
+        token_uri: Option<String>,
+        extension: T,
+    ) -> Result<(), ContractError> {
         // create the token
         let token = TokenInfo {
-            owner: deps.api.addr_validate(&owner)?,
+            owner,
             approvals: vec![],
             user: User::default(),
             token_uri,
             extension,
         };
-        self.tokens
-            .update(deps.storage, &token_id, |old| match old {
-                Some(_) => Err(ContractError::Claimed {}),
-                None => Ok(token),
-            })?;
+        self.tokens.update(storage, token_id, |old| match old {
+            Some(_) => Err(ContractError::Claimed {}),
+            None => Ok(token),
+        })?;
+
+        self.increment_tokens(storage)?;
+        Ok(())
+    }
 
-        self.increment_tokens(deps.storage)?;
+    fn increment_total_minted(&self, storage: &mut dyn Storage) -> StdResult<u64> {
+        let val = self.total_minted(storage)? + 1;
+        self.total_minted.save(storage, &val)?;
+        Ok(val)
+    }
+
+    /// Checks the active phase of the sale allows `info.sender` to mint and takes the payment,
+    /// returning it unless the phase is free
+    fn pay_for_mint(
+        &self,
+        storage: &mut dyn Storage,
+        block: &BlockInfo,
+        info: &MessageInfo,
+        sale: &Sale,
+        proof: &[HexBinary],
+    ) -> Result<Option<Coin>, ContractError> {
+        let phase = sale
+            .active_phase(block)
+            .ok_or(ContractError::NoActivePhase {})?;
+        let id = phase.id;
+        if self.total_minted(storage)? >= sale.max_supply {
+            return Err(ContractError::SoldOut {});
+        }
+        if !phase.allows(&info.sender, proof) {
+            return Err(ContractError::NotAllowlisted {});
+        }
+
+        let minted = self
+            .minted
+            .may_load(storage, (&info.sender, id))?
+            .unwrap_or_default();
+        if minted >= phase.per_address_limit {
+            return Err(ContractError::WalletLimitReached {
+                limit: phase.per_address_limit,
+            });
+        }
+
+        let paid = match info.funds.as_slice() {
+            [] => phase.price.amount.is_zero(),
+            [coin] => *coin == phase.price,
+            _ => false,
+        };
+        if !paid {
+            return Err(ContractError::IncorrectPayment {
+                price: phase.price.clone(),
+            });
+        }
+        self.minted
+            .save(storage, (&info.sender, id), &(minted + 1))?;
+
+        Ok((!phase.price.amount.is_zero()).then(|| phase.price.clone()))
+    }
+
+    pub fn update_sale(
+        &self,
+        deps: DepsMut,
+        info: MessageInfo,
+        sale: Option<Sale>,
+    ) -> Result<Response<C>, ContractError> {
+        cw_ownable::assert_owner(deps.storage, &info.sender)?;
+        match &sale {
+            Some(sale) => {
+                sale.validate()?;
+                self.sale.save(deps.storage, sale)?
+            }
+            None => self.sale.remove(deps.storage),
+        }
 
         Ok(Response::new()
-            .add_attribute("action", "mint")
-            .add_attribute("minter", info.sender)
-            .add_attribute("owner", owner)
-            .add_attribute("token_id", token_id))
+            .add_attribute("action", "update_sale")
+            .add_attribute("open", sale.is_some().to_string()))
+    }
+
+    pub fn reveal(
+        &self,
+        deps: DepsMut,
+        info: MessageInfo,
+        base_uri: String,
+    ) -> Result<Response<C>, ContractError> {
+        cw_ownable::assert_owner(deps.storage, &info.sender)?;
+        let mut reveal = self
+            .reveal
+            .may_load(deps.storage)?
+            .ok_or(ContractError::NoPlaceholder {})?;
+        if reveal.base_uri.is_some() {
+            return Err(ContractError::AlreadyRevealed {});
+        }
+        reveal.base_uri = Some(base_uri.clone());
+        self.reveal.save(deps.storage, &reveal)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "reveal")
+            .add_attribute("base_uri", base_uri))
+    }
+
+    pub fn load_token_pool(
+        &self,
+        deps: DepsMut,
+        info: MessageInfo,
+        token_ids: Vec<String>,
+    ) -> Result<Response<C>, ContractError> {
+        cw_ownable::assert_owner(deps.storage, &info.sender)?;
+        if self.random_mint.may_load(deps.storage)?.is_some() {
+            return Err(ContractError::SeedAlreadyCommitted {});
+        }
+
+        let mut pool_size = self.pool_size(deps.storage)?;
+        for token_id in &token_ids {
+            if self.tokens.has(deps.storage, token_id) {
+                return Err(ContractError::Claimed {});
+            }
+            if self.pooled.has(deps.storage, token_id) {
+                return Err(ContractError::AlreadyPooled {
+                    token_id: token_id.clone(),
+                });
+            }
+            self.pooled.save(deps.storage, token_id, &Empty {})?;
+            self.token_pool.save(deps.storage, pool_size, token_id)?;
+            pool_size += 1;
+        }
+        self.token_pool_size.save(deps.storage, &pool_size)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "load_token_pool")
+            .add_attribute("loaded", token_ids.len().to_string())
+            .add_attribute("pool_size", pool_size.to_string()))
+    }
+
+    pub fn commit_seed(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        commitment: HexBinary,
+        reveal_deadline: Expiration,
+    ) -> Result<Response<C>, ContractError> {
+        cw_ownable::assert_owner(deps.storage, &info.sender)?;
+        if self.random_mint.may_load(deps.storage)?.is_some() {
+            return Err(ContractError::SeedAlreadyCommitted {});
+        }
+        if reveal_deadline.is_expired(&env.block) {
+            return Err(ContractError::RevealDeadlinePassed {});
+        }
+        self.random_mint.save(
+            deps.storage,
+            &RandomMint {
+                commitment: commitment.clone(),
+                seed: None,
+                reveal_deadline,
+                tickets: 0,
+                assigned: 0,
+                refunded: 0,
+            },
+        )?;
+
+        Ok(Response::new()
+            .add_attribute("action", "commit_seed")
+            .add_attribute("commitment", commitment.to_hex())
+            .add_attribute("reveal_deadline", reveal_deadline.to_string()))
+    }
+
+    pub fn reveal_seed(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        seed: HexBinary,
+    ) -> Result<Response<C>, ContractError> {
+        cw_ownable::assert_owner(deps.storage, &info.sender)?;
+        let mut random_mint = self
+            .random_mint
+            .may_load(deps.storage)?
+            .ok_or(ContractError::NoSeedCommitted {})?;
+        if random_mint.seed.is_some() {
+            return Err(ContractError::SeedAlreadyRevealed {});
+        }
+        if random_mint.reveal_deadline.is_expired(&env.block) {
+            return Err(ContractError::RevealDeadlinePassed {});
+        }
+        if !random_mint.matches(&seed) {
+            return Err(ContractError::InvalidSeed {});
+        }
+        random_mint.seed = Some(seed.clone());
+        self.random_mint.save(deps.storage, &random_mint)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "reveal_seed")
+            .add_attribute("seed", seed.to_hex()))
+    }
+
+    /// Returns the payment of a ticket whose token id can no longer be drawn, as the seed was
+    /// not revealed before the deadline
+    pub fn refund_ticket(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        id: u32,
+    ) -> Result<Response<C>, ContractError> {
+        let mut random_mint = self
+            .random_mint
+            .may_load(deps.storage)?
+            .ok_or(ContractError::NoSeedCommitted {})?;
+        if random_mint.seed.is_some() {
+            return Err(ContractError::SeedAlreadyRevealed {});
+        }
+        if !random_mint.reveal_deadline.is_expired(&env.block) {
+            return Err(ContractError::RevealDeadlineNotPassed {});
+        }
+        let ticket = self.tickets.load(deps.storage, id)?;
+        if ticket.owner != info.sender {
+            return Err(ContractError::Unauthorized {});
+        }
+        self.tickets.remove(deps.storage, id);
+        random_mint.refunded += 1;
+        self.random_mint.save(deps.storage, &random_mint)?;
+
+        let refund = ticket.price.map(|price| BankMsg::Send {
+            to_address: ticket.owner.to_string(),
+            amount: vec![price],
+        });
+        Ok(Response::new()
+            .add_messages(refund)
+            .add_attribute("action", "refund_ticket")
+            .add_attribute("ticket", id.to_string())
+            .add_attribute("owner", ticket.owner))
+    }
+
+    /// Mints the token ids no ticket drew, from the end of the pool
+    pub fn mint_token_pool(
+        &self,
+        deps: DepsMut,
+        info: MessageInfo,
+        owner: String,
+        limit: Option<u32>,
+    ) -> Result<Response<C>, ContractError> {
+        cw_ownable::assert_owner(deps.storage, &info.sender)?;
+        let random_mint = self
+            .random_mint
+            .may_load(deps.storage)?
+            .ok_or(ContractError::NoSeedCommitted {})?;
+        if random_mint.seed.is_none() {
+            return Err(ContractError::SeedNotRevealed {});
+        }
+        if random_mint.assigned < random_mint.tickets {
+            return Err(ContractError::TicketsNotAssigned {});
+        }
+        let pool_size = self.pool_size(deps.storage)?;
+        if pool_size == 0 {
+            return Err(ContractError::EmptyPool {});
+        }
+
+        let mut count = limit
+            .unwrap_or(MAX_ASSIGN_LIMIT)
+            .min(MAX_ASSIGN_LIMIT)
+            .min(pool_size);
+        if let Some(sale) = self.sale.may_load(deps.storage)? {
+            let left = sale
+                .max_supply
+                .saturating_sub(self.total_minted(deps.storage)?);
+            if left == 0 {
+                return Err(ContractError::SoldOut {});
+            }
+            count = count.min(left.try_into().unwrap_or(u32::MAX));
+        }
+        let owner_addr = deps.api.addr_validate(&owner)?;
+        let mut res = Response::new()
+            .add_attribute("action", "mint_token_pool")
+            .add_attribute("owner", owner);
+        for slot in (pool_size - count..pool_size).rev() {
+            let token_id = self.token_pool.load(deps.storage, slot)?;
+            self.mint_token(
+                deps.storage,
+                &token_id,
+                owner_addr.clone(),
+                None,
+                T::default(),
+            )?;
+            self.increment_total_minted(deps.storage)?;
+            self.token_pool.remove(deps.storage, slot);
+            res = res.add_attribute("token_id", token_id);
+        }
+        self.token_pool_size
+            .save(deps.storage, &(pool_size - count))?;
+
+        Ok(res.add_attribute("pool_size", (pool_size - count).to_string()))
     }
 
     pub fn update_ownership(
@@ -188,6 +686,11 @@ where
         storage: &mut dyn Storage,
         amount: &Coin,
     ) -> Result<Response<C>, ContractError> {
+        if let Some(random_mint) = self.random_mint.may_load(storage)? {
+            if random_mint.holds_payments() {
+                return Err(ContractError::TicketPaymentsHeld {});
+            }
+        }
         let address = self.withdraw_address.may_load(storage)?;
         match address {
             Some(address) => {
@@ -268,7 +771,6 @@ where
         user: String,
         expires: Option<Expiration>,
     ) -> Result<Response<C>, ContractError> {
-        
         self._update_user(deps, &env, &info, &user, &token_id, true, expires)?;
 
         Ok(Response::new()
@@ -279,6 +781,24 @@ where
             .add_attribute("expires", expires.expect("REASON").to_string()))
     }
 
+    fn remove_user(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+    ) -> Result<Response<C>, ContractError> {
+        let mut token = self.tokens.load(deps.storage, &token_id)?;
+        self.check_can_approve(deps.as_ref(), &env, &info, &token)?;
+        token.user = User::default();
+        self.tokens.save(deps.storage, &token_id, &token)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "remove_user")
+            .add_attribute("sender", info.sender)
+            .add_attribute("token_id", token_id))
+    }
+
     fn approve(
         &self,
         deps: DepsMut,
@@ -441,8 +961,9 @@ where
         Ok(token)
     }
 
-     #[allow(clippy::too_many_arguments)]
-    pub fn _update_user( //self._update_user(deps, &env, &info, &user, &token_id, true, expires)?;
+    #[allow(clippy::too_many_arguments)]
+    pub fn _update_user(
+        //self._update_user(deps, &env, &info, &user, &token_id, true, expires)?;
         &self,
         deps: DepsMut,
         env: &Env,
@@ -462,17 +983,16 @@ where
 
         //check if the expire is true, if not no new user
         self.can_set_new_user(deps.as_ref(), env, info, &token)?;
-        
+
         //CHECK IF WE NEED THIS OR NOT ??????????????????????????????????????????????????????????????????????????
         //most likely do not need it, as it is not flexibe, but users have to ensure they are given the right by contracts
         //hence are sure they wont have the role change wrongfully by owners
-        
+
         /* if token.user.expires.is_expired(&env.block){
             return Err(ContractError::ExistingUserNotExpired {});
         }
         */
         token.user = User::default(); //Default::default(); User::default()
-        println!("{:?}", token.user);
 
         // only difference between approve and revoke
         if add {
@@ -481,19 +1001,18 @@ where
             if expires.is_expired(&env.block) {
                 return Err(ContractError::ExpiredUser {});
             }
-            
+
             token.user = User {
-            user: user_addr,
-            expires: expires //Expiration::Never {} Expiration::AtTime(time) Expiration::AtHeight(height)
+                user: user_addr,
+                expires, //Expiration::Never {} Expiration::AtTime(time) Expiration::AtHeight(height)
             };
-            
-        } 
+        }
 
         self.tokens.save(deps.storage, token_id, &token)?;
 
         Ok(token)
     }
-    
+
     /// returns true if the sender can execute approve or reject on the contract
     pub fn check_can_approve(
         &self,
@@ -529,7 +1048,6 @@ where
         info: &MessageInfo,
         token: &TokenInfo<T>,
     ) -> Result<(), ContractError> {
-
         // owner can set new user
         if token.owner == info.sender {
             return Ok(());
@@ -111,6 +111,9 @@ mod tests {
                 symbol: "".into(),
                 minter: Some("other".into()),
                 withdraw_address: None,
+                placeholder_uri: None,
+                uri_suffix: None,
+                sale: None,
             },
         )
         .unwrap();
@@ -144,6 +147,9 @@ mod tests {
                 symbol: "".into(),
                 minter: None,
                 withdraw_address: None,
+                placeholder_uri: None,
+                uri_suffix: None,
+                sale: None,
             },
         )
         .unwrap();
@@ -1,9 +1,11 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Binary, Coin};
+use cosmwasm_std::{Binary, Coin, HexBinary};
 use cw721::Expiration;
 use cw_ownable::{cw_ownable_execute, cw_ownable_query};
 use schemars::JsonSchema;
 
+use crate::state::{Phase, Sale};
+
 #[cw_serde]
 pub struct InstantiateMsg {
     /// Name of the NFT contract
@@ -17,6 +19,14 @@ pub struct InstantiateMsg {
     pub minter: Option<String>,
 
     pub withdraw_address: Option<String>,
+
+    /// Open a public sale, otherwise only the minter can mint
+    pub sale: Option<Sale>,
+
+    /// Token uri of every NFT until the owner reveals the metadata
+    pub placeholder_uri: Option<String>,
+    /// Appended to the token uris derived on reveal, e.g. ".json"
+    pub uri_suffix: Option<String>,
 }
 
 /// This is like Cw721ExecuteMsg but we add a Mint command for an owner
@@ -38,7 +48,9 @@ pub enum ExecuteMsg<T, E> {
         token_id: String,
         user: String,
         expires: Option<Expiration>,
-            },
+    },
+    /// Remove the user of the given token
+    RemoveUser { token_id: String },
     /// Allows operator to transfer / send the token from the owner's account.
     /// If expiration is set, then this allowance has a time/height limit
     Approve {
@@ -57,7 +69,8 @@ pub enum ExecuteMsg<T, E> {
     /// Remove previously granted ApproveAll permission
     RevokeAll { operator: String },
 
-    /// Mint a new NFT, can only be called by the contract minter
+    /// Mint a new NFT, can only be called by the contract minter. Closed once token ids are
+    /// assigned randomly.
     Mint {
         /// Unique ID of the NFT
         token_id: String,
@@ -71,12 +84,52 @@ pub enum ExecuteMsg<T, E> {
         extension: T,
     },
 
+    /// Mint the next NFT, its token id is assigned by the contract and it has no token uri or
+    /// extension of its own. Once a seed is committed this takes a ticket instead, which
+    /// `AssignTokens` mints a token id of the pool for after the seed reveal. With a sale anyone
+    /// can call this in the active phase paying its price, sale proceeds are sent to the
+    /// withdraw address if one is set. Otherwise it can only be called by the contract minter.
+    MintNext {
+        /// The owner of the newly minter NFT
+        owner: String,
+        /// Merkle proof that the sender is on the allowlist of the active phase, if it has one
+        proof: Option<Vec<HexBinary>>,
+    },
+    /// Adds token ids to the pool of `MintNext`, until a seed is committed. Only owner can
+    /// call this.
+    LoadTokenPool { token_ids: Vec<String> },
+    /// Commits to `sha256(seed)` of a secret seed before the sale, to be revealed before
+    /// `reveal_deadline`. Only owner can call this.
+    CommitSeed {
+        commitment: HexBinary,
+        reveal_deadline: Expiration,
+    },
+    /// Reveals the committed seed after the sale, no more tickets can be taken from then on.
+    /// Only owner can call this, before the reveal deadline.
+    RevealSeed { seed: HexBinary },
+    /// Mints the token ids of up to `limit` tickets, in ticket order, once the seed is
+    /// revealed. Anyone can call this.
+    AssignTokens { limit: Option<u32> },
+    /// Refunds the price of a ticket to its owner once the reveal deadline passed without the
+    /// seed being revealed. Only the ticket owner can call this.
+    RefundTicket { ticket: u32 },
+    /// Mints up to `limit` token ids left in the pool to `owner` once every ticket is assigned,
+    /// as far as the supply of the sale allows. Only owner can call this.
+    MintTokenPool { owner: String, limit: Option<u32> },
+
+    /// Reveals the metadata, the token uri of every NFT becomes `base_uri + token_id + suffix`
+    /// instead of the placeholder. Can only be done once. Only owner can call this.
+    Reveal { base_uri: String },
+
     /// Burn an NFT the sender has access to
     Burn { token_id: String },
 
     /// Extension msg
     Extension { msg: E },
 
+    /// Opens, changes or, with `None`, closes the sale. Only owner can call this.
+    UpdateSale { sale: Option<Sale> },
+
     /// Sets address to send withdrawn fees to. Only owner can call this.
     SetWithdrawAddress { address: String },
     /// Removes the withdraw address, so fees are sent to the contract. Only owner can call this.
@@ -100,10 +153,7 @@ pub enum QueryMsg<Q: JsonSchema> {
     /// Return user that can be considered the renter of the token at the given ID.
     /// Return type: `UserOfResponse`
     #[returns(cw721::UserOfResponse)]
-    UserOf {
-        token_id: String,
-        
-    },
+    UserOf { token_id: String },
     /// Return operator that can access all of the owner's tokens.
     #[returns(cw721::ApprovalResponse)]
     Approval {
@@ -182,6 +232,24 @@ pub enum QueryMsg<Q: JsonSchema> {
 
     #[returns(Option<String>)]
     GetWithdrawAddress {},
+
+    /// Terms of the sale, if one is open
+    #[returns(Option<Sale>)]
+    Sale {},
+    /// Number of tokens an address has minted in the sale, across phases
+    #[returns(u32)]
+    Minted { address: String },
+    /// The phase active at the current block, if any, and how many tokens an address can
+    /// still mint in it
+    #[returns(Option<ActivePhaseResponse>)]
+    ActivePhase { address: String },
+    /// Whether the metadata has been revealed
+    #[returns(bool)]
+    Revealed {},
+    /// The seed commitment of the random assignment, the number of token ids left in its pool
+    /// and how many tickets have been taken, assigned and refunded
+    #[returns(RandomMintResponse)]
+    RandomMint {},
 }
 
 /// Shows who can mint these tokens
@@ -190,6 +258,29 @@ pub struct MinterResponse {
     pub minter: Option<String>,
 }
 
+#[cw_serde]
+pub struct ActivePhaseResponse {
+    /// Id of the phase
+    pub id: u32,
+    pub phase: Phase,
+    /// Tokens the address can still mint in this phase, allowlist aside
+    pub remaining: u32,
+}
+
+#[cw_serde]
+pub struct RandomMintResponse {
+    pub commitment: Option<HexBinary>,
+    /// Set once revealed
+    pub seed: Option<HexBinary>,
+    pub reveal_deadline: Option<Expiration>,
+    pub pool_size: u32,
+    /// Number of tickets taken
+    pub tickets: u32,
+    /// Number of tickets assigned a token id
+    pub assigned: u32,
+    /// Number of tickets refunded after the reveal deadline
+    pub refunded: u32,
+}
 
 // #[cw_serde]
 // pub struct UserOfResponse {
@@ -1,17 +1,19 @@
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
-use cosmwasm_std::{to_binary, to_json_binary, Addr, Binary, BlockInfo, CustomMsg, Deps, Env, Order, StdError, StdResult,};
+use cosmwasm_std::{
+    to_json_binary, Addr, Binary, BlockInfo, CustomMsg, Deps, Env, Order, StdError, StdResult,
+};
 
 use cw721::{
     AllNftInfoResponse, ApprovalResponse, ApprovalsResponse, ContractInfoResponse, Cw721Query,
     Expiration, NftInfoResponse, NumTokensResponse, OperatorResponse, OperatorsResponse,
-    OwnerOfResponse, TokensResponse, UserOfResponse
+    OwnerOfResponse, TokensResponse, UserOfResponse,
 };
 use cw_storage_plus::Bound;
 use cw_utils::maybe_addr;
 
-use crate::msg::{MinterResponse, QueryMsg};
+use crate::msg::{ActivePhaseResponse, MinterResponse, QueryMsg, RandomMintResponse};
 use crate::state::{Approval, Cw721Contract, TokenInfo};
 
 const DEFAULT_LIMIT: u32 = 10;
@@ -36,7 +38,7 @@ where
     fn nft_info(&self, deps: Deps, token_id: String) -> StdResult<NftInfoResponse<T>> {
         let info = self.tokens.load(deps.storage, &token_id)?;
         Ok(NftInfoResponse {
-            token_uri: info.token_uri,
+            token_uri: self.token_uri(deps.storage, &token_id, info.token_uri)?,
             extension: info.extension,
         })
     }
@@ -115,24 +117,17 @@ where
         Ok(OperatorsResponse { operators: res? })
     }
 
-    fn user_of(
-        &self,
-        deps: Deps,
-        env: Env,
-        token_id: String,
-    ) -> StdResult<UserOfResponse> {
+    fn user_of(&self, deps: Deps, env: Env, token_id: String) -> StdResult<UserOfResponse> {
         let info = self.tokens.load(deps.storage, &token_id)?;
         //We could check if it has expired we return the default state
-        //but that would require considering the other variants of Expiration, 
+        //but that would require considering the other variants of Expiration,
         //so we allow Ui and cotracts to handle that
-        let check = info.user.is_expired(&env.block);
-        println!("Inside contract, is_expired: {:?}", check);
         if info.user.is_expired(&env.block) {
-        // If expired, return the default state
-        return Ok(UserOfResponse {
-            user: "".to_string(), // Update with the appropriate default value
-            expires: Expiration::Never {},
-        });
+            // If expired, return the default state
+            return Ok(UserOfResponse {
+                user: "".to_string(), // Update with the appropriate default value
+                expires: Expiration::Never {},
+            });
         }
         Ok(UserOfResponse {
             user: info.user.user.to_string(),
@@ -257,13 +252,13 @@ where
                 approvals: humanize_approvals(&env.block, &info, include_expired),
             },
             info: NftInfoResponse {
-                token_uri: info.token_uri,
+                token_uri: self.token_uri(deps.storage, &token_id, info.token_uri)?,
                 extension: info.extension,
             },
-            user: UserOfResponse{
+            user: UserOfResponse {
                 user: info.user.user.to_string(),
                 expires: info.user.expires,
-            }
+            },
         })
     }
 }
@@ -289,11 +284,7 @@ where
                 token_id,
                 include_expired.unwrap_or(false),
             )?),
-            QueryMsg::UserOf {
-                token_id,
-            } => {
-                to_binary(&self.user_of(deps, env, token_id)?)
-            },
+            QueryMsg::UserOf { token_id } => to_json_binary(&self.user_of(deps, env, token_id)?),
             QueryMsg::AllNftInfo {
                 token_id,
                 include_expired,
@@ -361,9 +352,79 @@ where
             QueryMsg::GetWithdrawAddress {} => {
                 to_json_binary(&self.withdraw_address.may_load(deps.storage)?)
             }
+            QueryMsg::Sale {} => to_json_binary(&self.sale.may_load(deps.storage)?),
+            QueryMsg::Minted { address } => to_json_binary(&self.minted(deps, address)?),
+            QueryMsg::Revealed {} => to_json_binary(&self.revealed(deps)?),
+            QueryMsg::RandomMint {} => to_json_binary(&self.random_mint_info(deps)?),
+            QueryMsg::ActivePhase { address } => {
+                to_json_binary(&self.active_phase(deps, env, address)?)
+            }
         }
     }
 
+    pub fn minted(&self, deps: Deps, address: String) -> StdResult<u32> {
+        let address = deps.api.addr_validate(&address)?;
+        self.minted
+            .prefix(&address)
+            .range(deps.storage, None, None, Order::Ascending)
+            .map(|item| item.map(|(_, minted)| minted))
+            .sum()
+    }
+
+    pub fn active_phase(
+        &self,
+        deps: Deps,
+        env: Env,
+        address: String,
+    ) -> StdResult<Option<ActivePhaseResponse>> {
+        let address = deps.api.addr_validate(&address)?;
+        let sale = match self.sale.may_load(deps.storage)? {
+            Some(sale) => sale,
+            None => return Ok(None),
+        };
+        let phase = match sale.active_phase(&env.block) {
+            Some(active) => active,
+            None => return Ok(None),
+        };
+
+        let minted = self
+            .minted
+            .may_load(deps.storage, (&address, phase.id))?
+            .unwrap_or_default();
+        let supply_left = sale
+            .max_supply
+            .saturating_sub(self.total_minted(deps.storage)?);
+        let remaining = phase
+            .per_address_limit
+            .saturating_sub(minted)
+            .min(supply_left.try_into().unwrap_or(u32::MAX));
+        Ok(Some(ActivePhaseResponse {
+            id: phase.id,
+            phase: phase.clone(),
+            remaining,
+        }))
+    }
+
+    pub fn revealed(&self, deps: Deps) -> StdResult<bool> {
+        Ok(self
+            .reveal
+            .may_load(deps.storage)?
+            .map_or(false, |reveal| reveal.base_uri.is_some()))
+    }
+
+    pub fn random_mint_info(&self, deps: Deps) -> StdResult<RandomMintResponse> {
+        let random_mint = self.random_mint.may_load(deps.storage)?;
+        Ok(RandomMintResponse {
+            commitment: random_mint.as_ref().map(|r| r.commitment.clone()),
+            seed: random_mint.as_ref().and_then(|r| r.seed.clone()),
+            reveal_deadline: random_mint.as_ref().map(|r| r.reveal_deadline),
+            pool_size: self.pool_size(deps.storage)?,
+            tickets: random_mint.as_ref().map_or(0, |r| r.tickets),
+            assigned: random_mint.as_ref().map_or(0, |r| r.assigned),
+            refunded: random_mint.map_or(0, |r| r.refunded),
+        })
+    }
+
     pub fn minter(&self, deps: Deps) -> StdResult<MinterResponse> {
         let minter = cw_ownable::get_ownership(deps.storage)?
             .owner
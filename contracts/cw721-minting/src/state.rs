@@ -3,11 +3,16 @@ use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::marker::PhantomData;
 
-use cosmwasm_std::{Addr, BlockInfo, CustomMsg, StdResult, Storage};
+use cosmwasm_std::{
+    Addr, BlockInfo, Coin, CustomMsg, Empty, Env, HexBinary, StdResult, Storage, Timestamp,
+};
+use sha2::{Digest, Sha256};
 
 use cw721::{ContractInfoResponse, Cw721, Expiration};
 use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
 
+use crate::error::ContractError;
+
 pub struct Cw721Contract<'a, T, C, E, Q>
 where
     T: Serialize + DeserializeOwned + Clone,
@@ -16,10 +21,27 @@ where
 {
     pub contract_info: Item<'a, ContractInfoResponse>,
     pub token_count: Item<'a, u64>,
+    /// Number of tokens ever minted, unlike `token_count` it does not go down on burns
+    pub total_minted: Item<'a, u64>,
     /// Stored as (granter, operator) giving operator full control over granter's account
     pub operators: Map<'a, (&'a Addr, &'a Addr), Expiration>,
     pub tokens: IndexedMap<'a, &'a str, TokenInfo<T>, TokenIndexes<'a, T>>,
     pub withdraw_address: Item<'a, String>,
+    /// Phases of the public sale, without a sale only the minter can mint
+    pub sale: Item<'a, Sale>,
+    /// Number of tokens each address has minted in each phase of the sale, by phase id
+    pub minted: Map<'a, (&'a Addr, u32), u32>,
+    /// Placeholder metadata until the reveal
+    pub reveal: Item<'a, Reveal>,
+    /// Seed commitment of the random assignment, see `MintNext`
+    pub random_mint: Item<'a, RandomMint>,
+    /// Token ids not yet assigned, in slots `0..token_pool_size`
+    pub token_pool: Map<'a, u32, String>,
+    pub token_pool_size: Item<'a, u32>,
+    /// Every token id loaded into the pool, so none is loaded twice
+    pub pooled: Map<'a, &'a str, Empty>,
+    /// Random mints waiting for the seed reveal to be assigned a token id, by ticket id
+    pub tickets: Map<'a, u32, Ticket>,
 
     pub(crate) _custom_response: PhantomData<C>,
     pub(crate) _custom_query: PhantomData<Q>,
@@ -46,10 +68,19 @@ where
         Self::new(
             "nft_info",
             "num_tokens",
+            "total_minted",
             "operators",
             "tokens",
             "tokens__owner",
             "withdraw_address",
+            "sale",
+            "minted",
+            "reveal",
+            "random_mint",
+            "token_pool",
+            "token_pool_size",
+            "pooled",
+            "tickets",
         )
     }
 }
@@ -60,13 +91,23 @@ where
     E: CustomMsg,
     Q: CustomMsg,
 {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         contract_key: &'a str,
         token_count_key: &'a str,
+        total_minted_key: &'a str,
         operator_key: &'a str,
         tokens_key: &'a str,
         tokens_owner_key: &'a str,
         withdraw_address_key: &'a str,
+        sale_key: &'a str,
+        minted_key: &'a str,
+        reveal_key: &'a str,
+        random_mint_key: &'a str,
+        token_pool_key: &'a str,
+        token_pool_size_key: &'a str,
+        pooled_key: &'a str,
+        tickets_key: &'a str,
     ) -> Self {
         let indexes = TokenIndexes {
             owner: MultiIndex::new(token_owner_idx, tokens_key, tokens_owner_key),
@@ -74,9 +115,18 @@ where
         Self {
             contract_info: Item::new(contract_key),
             token_count: Item::new(token_count_key),
+            total_minted: Item::new(total_minted_key),
             operators: Map::new(operator_key),
             tokens: IndexedMap::new(tokens_key, indexes),
             withdraw_address: Item::new(withdraw_address_key),
+            sale: Item::new(sale_key),
+            minted: Map::new(minted_key),
+            reveal: Item::new(reveal_key),
+            random_mint: Item::new(random_mint_key),
+            token_pool: Map::new(token_pool_key),
+            token_pool_size: Item::new(token_pool_size_key),
+            pooled: Map::new(pooled_key),
+            tickets: Map::new(tickets_key),
             _custom_response: PhantomData,
             _custom_execute: PhantomData,
             _custom_query: PhantomData,
@@ -93,6 +143,32 @@ where
         Ok(val)
     }
 
+    pub fn total_minted(&self, storage: &dyn Storage) -> StdResult<u64> {
+        Ok(self.total_minted.may_load(storage)?.unwrap_or_default())
+    }
+
+    /// The token uri queries return, the placeholder or derived one if the collection has them
+    pub fn token_uri(
+        &self,
+        storage: &dyn Storage,
+        token_id: &str,
+        token_uri: Option<String>,
+    ) -> StdResult<Option<String>> {
+        Ok(match self.reveal.may_load(storage)? {
+            Some(reveal) => Some(reveal.token_uri(token_id)),
+            None => token_uri,
+        })
+    }
+
+    pub fn pool_size(&self, storage: &dyn Storage) -> StdResult<u32> {
+        Ok(self.token_pool_size.may_load(storage)?.unwrap_or_default())
+    }
+
+    /// Token ids are assigned randomly once a pool is loaded or a seed is committed
+    pub fn assigns_randomly(&self, storage: &dyn Storage) -> StdResult<bool> {
+        Ok(self.pool_size(storage)? > 0 || self.random_mint.may_load(storage)?.is_some())
+    }
+
     pub fn decrement_tokens(&self, storage: &mut dyn Storage) -> StdResult<u64> {
         let val = self.token_count(storage)? - 1;
         self.token_count.save(storage, &val)?;
@@ -117,6 +193,163 @@ pub struct TokenInfo<T> {
     pub extension: T,
 }
 
+/// Anyone can mint in the active phase, until `max_supply` tokens have been minted
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct Sale {
+    pub max_supply: u64,
+    pub phases: Vec<Phase>,
+}
+
+impl Sale {
+    pub fn validate(&self) -> Result<(), ContractError> {
+        // expirations by height and by time are not comparable, so neither is greater
+        let valid = self
+            .phases
+            .iter()
+            .all(|phase| phase.start != Expiration::Never {} && phase.end > phase.start);
+        if !valid {
+            return Err(ContractError::InvalidPhase {});
+        }
+        for (i, phase) in self.phases.iter().enumerate() {
+            if self.phases[..i].iter().any(|other| other.id == phase.id) {
+                return Err(ContractError::DuplicatePhaseId { id: phase.id });
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the first phase active at `block`
+    pub fn active_phase(&self, block: &BlockInfo) -> Option<&Phase> {
+        self.phases.iter().find(|phase| phase.is_active(block))
+    }
+}
+
+/// Between `start` and `end` each address can mint up to `per_address_limit` tokens paying
+/// `price`. With a `merkle_root`, only addresses on its allowlist can.
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct Phase {
+    /// Minted counts are kept by this id, so phases can be reordered when updating the sale
+    pub id: u32,
+    pub start: Expiration,
+    pub end: Expiration,
+    pub price: Coin,
+    pub per_address_limit: u32,
+    /// Root of a merkle tree whose leaves are the sha256 hashes of the allowlisted addresses
+    pub merkle_root: Option<HexBinary>,
+}
+
+impl Phase {
+    pub fn is_active(&self, block: &BlockInfo) -> bool {
+        self.start.is_expired(block) && !self.end.is_expired(block)
+    }
+
+    /// Checks `address` is on the allowlist, hashing the leaf up with the `proof` siblings in
+    /// sorted pairs. Phases without a merkle root allow everyone.
+    pub fn allows(&self, address: &Addr, proof: &[HexBinary]) -> bool {
+        let root = match &self.merkle_root {
+            Some(root) => root,
+            None => return true,
+        };
+        let leaf: [u8; 32] = Sha256::digest(address.as_bytes()).into();
+        let computed = proof.iter().fold(leaf, |hash, sibling| {
+            let (first, second) = if hash.as_slice() <= sibling.as_slice() {
+                (hash.as_slice(), sibling.as_slice())
+            } else {
+                (sibling.as_slice(), hash.as_slice())
+            };
+            Sha256::new()
+                .chain_update(first)
+                .chain_update(second)
+                .finalize()
+                .into()
+        });
+        computed.as_slice() == root.as_slice()
+    }
+}
+
+/// Every NFT has `placeholder_uri` as token uri until `base_uri` is revealed
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct Reveal {
+    pub placeholder_uri: String,
+    pub suffix: String,
+    pub base_uri: Option<String>,
+}
+
+impl Reveal {
+    pub fn token_uri(&self, token_id: &str) -> String {
+        match &self.base_uri {
+            Some(base_uri) => format!("{}{}{}", base_uri, token_id, self.suffix),
+            None => self.placeholder_uri.clone(),
+        }
+    }
+}
+
+/// The owner commits to `sha256(seed)` before the sale and reveals `seed` after it
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct RandomMint {
+    pub commitment: HexBinary,
+    pub seed: Option<HexBinary>,
+    /// The seed can only be revealed before this, after it tickets are refunded instead
+    pub reveal_deadline: Expiration,
+    /// Number of tickets taken, ticket ids count up from 0
+    pub tickets: u32,
+    /// Number of tickets assigned a token id, they are assigned in order
+    pub assigned: u32,
+    /// Number of tickets refunded after the reveal deadline
+    pub refunded: u32,
+}
+
+impl RandomMint {
+    pub fn matches(&self, seed: &HexBinary) -> bool {
+        Sha256::digest(seed.as_slice()).as_slice() == self.commitment.as_slice()
+    }
+
+    /// Ticket payments stay in the contract until the seed is revealed or they are refunded
+    pub fn holds_payments(&self) -> bool {
+        self.seed.is_none() && self.refunded < self.tickets
+    }
+}
+
+/// A random mint, with the block data it was taken in
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct Ticket {
+    pub owner: Addr,
+    pub height: u64,
+    pub time: Timestamp,
+    pub transaction_index: Option<u32>,
+    /// What was paid for the ticket, refunded if the seed is not revealed in time
+    pub price: Option<Coin>,
+}
+
+impl Ticket {
+    pub fn new(env: &Env, owner: Addr, price: Option<Coin>) -> Self {
+        Ticket {
+            owner,
+            height: env.block.height,
+            time: env.block.time,
+            transaction_index: env.transaction.as_ref().map(|tx| tx.index),
+            price,
+        }
+    }
+
+    /// Picks the pool slot of ticket `id` by hashing the revealed seed with the ticket id, its
+    /// block data and owner. Nobody knows the seed when the ticket is taken, and anyone can
+    /// reproduce the assignment once it is revealed.
+    pub fn draw(&self, seed: &HexBinary, id: u32, pool_size: u32) -> u32 {
+        let hash = Sha256::new()
+            .chain_update(seed.as_slice())
+            .chain_update(id.to_be_bytes())
+            .chain_update(self.height.to_be_bytes())
+            .chain_update(self.time.nanos().to_be_bytes())
+            .chain_update(self.transaction_index.unwrap_or_default().to_be_bytes())
+            .chain_update(self.owner.as_bytes())
+            .finalize();
+        let mut random = [0u8; 8];
+        random.copy_from_slice(&hash[..8]);
+        (u64::from_be_bytes(random) % u64::from(pool_size)) as u32
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 pub struct Approval {
     /// Account that can transfer/send the token
@@ -131,7 +364,6 @@ impl Approval {
     }
 }
 
-
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 pub struct User {
     /// Account that can transfer/send the token
@@ -144,14 +376,16 @@ impl User {
     pub fn is_expired(&self, block: &BlockInfo) -> bool {
         self.expires.is_expired(block)
     }
+}
 
-    pub fn default() -> Self {
+impl Default for User {
+    fn default() -> Self {
         User {
             user: Addr::unchecked(""), // Provide a default value for Addr
             expires: Expiration::Never {},
         }
     }
-} 
+}
 
 pub struct TokenIndexes<'a, T>
 where
@@ -0,0 +1,42 @@
+use cosmwasm_std::{Coin, OverflowError, StdError};
+use cw_utils::PaymentError;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ContractError {
+    #[error(transparent)]
+    Std(#[from] StdError),
+
+    #[error(transparent)]
+    Payment(#[from] PaymentError),
+
+    #[error(transparent)]
+    Overflow(#[from] OverflowError),
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("The rentals contract is not approved to set the user of token {token_id}")]
+    NotApproved { token_id: String },
+
+    #[error("Price must be greater than zero")]
+    InvalidPrice {},
+
+    #[error("Invalid duration range: min {min} must be non-zero and not exceed max {max}")]
+    InvalidDurationRange { min: u64, max: u64 },
+
+    #[error("Duration must be between {min} and {max}")]
+    InvalidDuration { min: u64, max: u64 },
+
+    #[error("Wrong payment, expected {expected}")]
+    WrongPayment { expected: Coin },
+
+    #[error("Token {token_id} is already rented")]
+    AlreadyRented { token_id: String },
+
+    #[error("Listing is stale, token {token_id} changed owner")]
+    StaleListing { token_id: String },
+
+    #[error("Nothing to claim")]
+    NothingToClaim {},
+}
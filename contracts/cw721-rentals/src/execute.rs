@@ -0,0 +1,198 @@
+use std::marker::PhantomData;
+
+use cosmwasm_std::{
+    coin, BankMsg, Deps, DepsMut, Empty, Env, MessageInfo, Order, Response, StdResult, Uint128,
+};
+use cw721::OperatorResponse;
+use cw721_base::helpers::Cw721Contract;
+use cw_utils::must_pay;
+
+use crate::error::ContractError;
+use crate::state::{Listing, RentalUnit, COLLECTION, LISTINGS, PROCEEDS};
+
+pub type Cw721Collection = Cw721Contract<Empty, Empty>;
+pub type Cw721ExecuteMsg = cw721_base::ExecuteMsg<Empty, Empty>;
+pub type Cw721QueryMsg = cw721_base::QueryMsg<Empty>;
+
+#[allow(clippy::too_many_arguments)]
+pub fn list(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_id: String,
+    denom: String,
+    price: Uint128,
+    unit: RentalUnit,
+    min_duration: u64,
+    max_duration: u64,
+) -> Result<Response, ContractError> {
+    if price.is_zero() {
+        return Err(ContractError::InvalidPrice {});
+    }
+    if min_duration == 0 || min_duration > max_duration {
+        return Err(ContractError::InvalidDurationRange {
+            min: min_duration,
+            max: max_duration,
+        });
+    }
+
+    let collection = collection(deps.as_ref())?;
+    let owner = collection.owner_of(&deps.querier, &token_id, false)?.owner;
+    if owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    if !can_set_user(deps.as_ref(), &env, &collection, &token_id, &owner) {
+        return Err(ContractError::NotApproved { token_id });
+    }
+
+    let listing = Listing {
+        owner: info.sender,
+        denom,
+        price,
+        unit,
+        min_duration,
+        max_duration,
+    };
+    LISTINGS.save(deps.storage, &token_id, &listing)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "list")
+        .add_attribute("owner", listing.owner)
+        .add_attribute("token_id", token_id)
+        .add_attribute("price", coin(price.u128(), listing.denom).to_string()))
+}
+
+pub fn delist(
+    deps: DepsMut,
+    info: MessageInfo,
+    token_id: String,
+) -> Result<Response, ContractError> {
+    let listing = LISTINGS.load(deps.storage, &token_id)?;
+    if listing.owner != info.sender {
+        // whoever owns the token now can clean up a stale listing
+        let owner = collection(deps.as_ref())?
+            .owner_of(&deps.querier, &token_id, false)?
+            .owner;
+        if owner != info.sender {
+            return Err(ContractError::Unauthorized {});
+        }
+    }
+    LISTINGS.remove(deps.storage, &token_id);
+
+    Ok(Response::new()
+        .add_attribute("action", "delist")
+        .add_attribute("sender", info.sender)
+        .add_attribute("token_id", token_id))
+}
+
+pub fn rent(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_id: String,
+    duration: u64,
+) -> Result<Response, ContractError> {
+    let listing = LISTINGS.load(deps.storage, &token_id)?;
+    if duration < listing.min_duration || duration > listing.max_duration {
+        return Err(ContractError::InvalidDuration {
+            min: listing.min_duration,
+            max: listing.max_duration,
+        });
+    }
+
+    let collection = collection(deps.as_ref())?;
+    let owner = collection.owner_of(&deps.querier, &token_id, false)?.owner;
+    if owner != listing.owner {
+        return Err(ContractError::StaleListing { token_id });
+    }
+    let user = collection.user_of(&deps.querier, &token_id)?;
+    if !user.user.is_empty() {
+        return Err(ContractError::AlreadyRented { token_id });
+    }
+
+    let cost = listing.price.checked_mul(Uint128::from(duration))?;
+    let paid = must_pay(&info, &listing.denom)?;
+    if paid != cost {
+        return Err(ContractError::WrongPayment {
+            expected: coin(cost.u128(), listing.denom),
+        });
+    }
+
+    // hold the rent until the owner claims it
+    PROCEEDS.update(
+        deps.storage,
+        (&listing.owner, &listing.denom),
+        |proceeds| -> StdResult<_> { Ok(proceeds.unwrap_or_default().checked_add(cost)?) },
+    )?;
+
+    let expires = listing.unit.expiration(&env.block, duration);
+    let set_user = collection.call(Cw721ExecuteMsg::SetUser {
+        token_id: token_id.clone(),
+        user: info.sender.to_string(),
+        expires: Some(expires),
+    })?;
+
+    Ok(Response::new()
+        .add_message(set_user)
+        .add_attribute("action", "rent")
+        .add_attribute("renter", info.sender)
+        .add_attribute("token_id", token_id)
+        .add_attribute("expires", expires.to_string())
+        .add_attribute("cost", coin(cost.u128(), listing.denom).to_string()))
+}
+
+pub fn claim(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let proceeds = PROCEEDS
+        .prefix(&info.sender)
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    if proceeds.is_empty() {
+        return Err(ContractError::NothingToClaim {});
+    }
+
+    let mut amount = vec![];
+    for (denom, proceed) in proceeds {
+        PROCEEDS.remove(deps.storage, (&info.sender, &denom));
+        amount.push(coin(proceed.u128(), denom));
+    }
+
+    Ok(Response::new()
+        .add_message(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount,
+        })
+        .add_attribute("action", "claim")
+        .add_attribute("owner", info.sender))
+}
+
+pub fn collection(deps: Deps) -> StdResult<Cw721Collection> {
+    let addr = COLLECTION.load(deps.storage)?;
+    Ok(Cw721Contract(addr, PhantomData, PhantomData))
+}
+
+/// returns true if this contract holds a token approval or is an operator of the owner
+fn can_set_user(
+    deps: Deps,
+    env: &Env,
+    collection: &Cw721Collection,
+    token_id: &str,
+    owner: &str,
+) -> bool {
+    let contract = env.contract.address.to_string();
+    if collection
+        .approval(&deps.querier, token_id, contract.as_str(), Some(false))
+        .is_ok()
+    {
+        return true;
+    }
+    collection
+        .query::<OperatorResponse>(
+            &deps.querier,
+            Cw721QueryMsg::Operator {
+                owner: owner.to_string(),
+                operator: contract,
+                include_expired: Some(false),
+            },
+        )
+        .is_ok()
+}
@@ -0,0 +1,90 @@
+pub mod error;
+pub mod execute;
+pub mod msg;
+pub mod query;
+pub mod state;
+
+#[cfg(test)]
+mod multi_tests;
+
+pub use crate::error::ContractError;
+
+// Version info for migration
+pub const CONTRACT_NAME: &str = "crates.io:cw721-rentals";
+pub const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+pub mod entry {
+    use super::*;
+
+    #[cfg(not(feature = "library"))]
+    use cosmwasm_std::entry_point;
+    use cosmwasm_std::{
+        to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
+    };
+
+    use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+    use crate::state::COLLECTION;
+
+    #[cfg_attr(not(feature = "library"), entry_point)]
+    pub fn instantiate(
+        deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        msg: InstantiateMsg,
+    ) -> Result<Response, ContractError> {
+        cw2::set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+        let collection = deps.api.addr_validate(&msg.collection)?;
+        COLLECTION.save(deps.storage, &collection)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "instantiate")
+            .add_attribute("collection", collection))
+    }
+
+    #[cfg_attr(not(feature = "library"), entry_point)]
+    pub fn execute(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        msg: ExecuteMsg,
+    ) -> Result<Response, ContractError> {
+        match msg {
+            ExecuteMsg::List {
+                token_id,
+                denom,
+                price,
+                unit,
+                min_duration,
+                max_duration,
+            } => execute::list(
+                deps,
+                env,
+                info,
+                token_id,
+                denom,
+                price,
+                unit,
+                min_duration,
+                max_duration,
+            ),
+            ExecuteMsg::Delist { token_id } => execute::delist(deps, info, token_id),
+            ExecuteMsg::Rent { token_id, duration } => {
+                execute::rent(deps, env, info, token_id, duration)
+            }
+            ExecuteMsg::Claim {} => execute::claim(deps, info),
+        }
+    }
+
+    #[cfg_attr(not(feature = "library"), entry_point)]
+    pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+        match msg {
+            QueryMsg::Config {} => to_json_binary(&query::config(deps)?),
+            QueryMsg::Listing { token_id } => to_json_binary(&query::listing(deps, token_id)?),
+            QueryMsg::Listings { start_after, limit } => {
+                to_json_binary(&query::listings(deps, start_after, limit)?)
+            }
+            QueryMsg::Proceeds { owner } => to_json_binary(&query::proceeds(deps, owner)?),
+        }
+    }
+}
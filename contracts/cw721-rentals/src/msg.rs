@@ -0,0 +1,78 @@
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{Coin, Uint128};
+
+use crate::state::{Listing, RentalUnit};
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    /// The cw721-base collection whose tokens are rented out through this contract
+    pub collection: String,
+}
+
+#[cw_serde]
+pub enum ExecuteMsg {
+    /// List a token for rent. Only the token owner can call this, and this contract
+    /// must hold a token approval or be an operator of the owner, so it can call `SetUser`.
+    /// Listing an already listed token replaces the previous terms.
+    List {
+        token_id: String,
+        /// Denom the rent has to be paid in
+        denom: String,
+        /// Price per `unit` of rental time
+        price: Uint128,
+        /// Whether durations are counted in seconds or blocks
+        unit: RentalUnit,
+        /// Shortest rental accepted, in `unit`s
+        min_duration: u64,
+        /// Longest rental accepted, in `unit`s
+        max_duration: u64,
+    },
+    /// Remove a listing. Can be called by the lister or the current token owner
+    Delist { token_id: String },
+    /// Rent a listed token for `duration` units, paying `price * duration` in the listed denom.
+    /// Makes the sender the user of the token until the rental expires
+    Rent { token_id: String, duration: u64 },
+    /// Send all rent collected for the sender's listings to the sender
+    Claim {},
+}
+
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum QueryMsg {
+    /// Returns the collection this contract rents out tokens for
+    #[returns(ConfigResponse)]
+    Config {},
+    /// Returns the listing of a token, error if it is not listed
+    #[returns(Listing)]
+    Listing { token_id: String },
+    /// Lists all listings, ordered by token_id
+    #[returns(ListingsResponse)]
+    Listings {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Returns the rent held in escrow for the given owner
+    #[returns(ProceedsResponse)]
+    Proceeds { owner: String },
+}
+
+#[cw_serde]
+pub struct ConfigResponse {
+    pub collection: String,
+}
+
+#[cw_serde]
+pub struct ListingsResponse {
+    pub listings: Vec<ListingResponse>,
+}
+
+#[cw_serde]
+pub struct ListingResponse {
+    pub token_id: String,
+    pub listing: Listing,
+}
+
+#[cw_serde]
+pub struct ProceedsResponse {
+    pub proceeds: Vec<Coin>,
+}
@@ -0,0 +1,399 @@
+use cosmwasm_std::{coin, coins, Addr, Empty, Uint128};
+use cw721::{Expiration, UserOfResponse};
+use cw_multi_test::{App, AppResponse, Contract, ContractWrapper, Executor};
+use cw_utils::PaymentError;
+
+use crate::msg::{ExecuteMsg, InstantiateMsg, ListingsResponse, ProceedsResponse, QueryMsg};
+use crate::state::{Listing, RentalUnit};
+use crate::ContractError;
+
+const OWNER: &str = "owner";
+const RENTER: &str = "renter";
+const DENOM: &str = "uark";
+
+type Cw721ExecuteMsg = cw721_base::ExecuteMsg<Empty, Empty>;
+type Cw721QueryMsg = cw721_base::QueryMsg<Empty>;
+
+fn cw721_base_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        cw721_base::entry::execute,
+        cw721_base::entry::instantiate,
+        cw721_base::entry::query,
+    );
+    Box::new(contract)
+}
+
+fn rentals_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        crate::entry::execute,
+        crate::entry::instantiate,
+        crate::entry::query,
+    );
+    Box::new(contract)
+}
+
+/// Sets up a collection with token "1" owned by OWNER and a rentals contract for it
+fn setup() -> (App, Addr, Addr) {
+    let mut app = App::new(|router, _, storage| {
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked(RENTER), coins(10_000, DENOM))
+            .unwrap()
+    });
+
+    let cw721_id = app.store_code(cw721_base_contract());
+    let cw721 = app
+        .instantiate_contract(
+            cw721_id,
+            Addr::unchecked(OWNER),
+            &cw721_base::InstantiateMsg {
+                name: "collection".to_string(),
+                symbol: "symbol".to_string(),
+                minter: None,
+                withdraw_address: None,
+                eviction_policy: None,
+                rental_config: None,
+                max_history: None,
+                base_uri: None,
+            },
+            &[],
+            "cw721-base",
+            None,
+        )
+        .unwrap();
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        cw721.clone(),
+        &Cw721ExecuteMsg::Mint {
+            token_id: "1".to_string(),
+            owner: OWNER.to_string(),
+            token_uri: None,
+            extension: Empty::default(),
+        },
+        &[],
+    )
+    .unwrap();
+
+    let rentals_id = app.store_code(rentals_contract());
+    let rentals = app
+        .instantiate_contract(
+            rentals_id,
+            Addr::unchecked(OWNER),
+            &InstantiateMsg {
+                collection: cw721.to_string(),
+            },
+            &[],
+            "cw721-rentals",
+            None,
+        )
+        .unwrap();
+
+    (app, cw721, rentals)
+}
+
+fn list_msg() -> ExecuteMsg {
+    ExecuteMsg::List {
+        token_id: "1".to_string(),
+        denom: DENOM.to_string(),
+        price: Uint128::new(10),
+        unit: RentalUnit::Second,
+        min_duration: 60,
+        max_duration: 600,
+    }
+}
+
+fn rent(
+    app: &mut App,
+    rentals: &Addr,
+    duration: u64,
+    amount: u128,
+) -> Result<AppResponse, ContractError> {
+    app.execute_contract(
+        Addr::unchecked(RENTER),
+        rentals.clone(),
+        &ExecuteMsg::Rent {
+            token_id: "1".to_string(),
+            duration,
+        },
+        &coins(amount, DENOM),
+    )
+    .map_err(|err| err.downcast().unwrap())
+}
+
+fn query_user(app: &App, cw721: &Addr) -> UserOfResponse {
+    app.wrap()
+        .query_wasm_smart(
+            cw721,
+            &Cw721QueryMsg::UserOf {
+                token_id: "1".to_string(),
+            },
+        )
+        .unwrap()
+}
+
+#[test]
+fn list_requires_owner_and_approval() {
+    let (mut app, cw721, rentals) = setup();
+
+    // random cannot list someone else's token
+    let err = app
+        .execute_contract(Addr::unchecked("random"), rentals.clone(), &list_msg(), &[])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    );
+
+    // owner cannot list before approving the rentals contract
+    let err = app
+        .execute_contract(Addr::unchecked(OWNER), rentals.clone(), &list_msg(), &[])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::NotApproved {
+            token_id: "1".to_string()
+        }
+    );
+
+    // a token approval is enough
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        cw721.clone(),
+        &Cw721ExecuteMsg::Approve {
+            spender: rentals.to_string(),
+            token_id: "1".to_string(),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(Addr::unchecked(OWNER), rentals.clone(), &list_msg(), &[])
+        .unwrap();
+
+    // and so is being an operator
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        cw721.clone(),
+        &Cw721ExecuteMsg::Revoke {
+            spender: rentals.to_string(),
+            token_id: "1".to_string(),
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        cw721,
+        &Cw721ExecuteMsg::ApproveAll {
+            operator: rentals.to_string(),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(Addr::unchecked(OWNER), rentals.clone(), &list_msg(), &[])
+        .unwrap();
+
+    let listing: Listing = app
+        .wrap()
+        .query_wasm_smart(
+            &rentals,
+            &QueryMsg::Listing {
+                token_id: "1".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        listing,
+        Listing {
+            owner: Addr::unchecked(OWNER),
+            denom: DENOM.to_string(),
+            price: Uint128::new(10),
+            unit: RentalUnit::Second,
+            min_duration: 60,
+            max_duration: 600,
+        }
+    );
+    let listings: ListingsResponse = app
+        .wrap()
+        .query_wasm_smart(
+            &rentals,
+            &QueryMsg::Listings {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(listings.listings.len(), 1);
+}
+
+#[test]
+fn rent_and_claim() {
+    let (mut app, cw721, rentals) = setup();
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        cw721.clone(),
+        &Cw721ExecuteMsg::ApproveAll {
+            operator: rentals.to_string(),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(Addr::unchecked(OWNER), rentals.clone(), &list_msg(), &[])
+        .unwrap();
+
+    // duration outside of the listed range
+    let err = rent(&mut app, &rentals, 30, 300).unwrap_err();
+    assert_eq!(err, ContractError::InvalidDuration { min: 60, max: 600 });
+
+    // wrong payment
+    let err = rent(&mut app, &rentals, 100, 999).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::WrongPayment {
+            expected: coin(1000, DENOM)
+        }
+    );
+    let err = app
+        .execute_contract(
+            Addr::unchecked(RENTER),
+            rentals.clone(),
+            &ExecuteMsg::Rent {
+                token_id: "1".to_string(),
+                duration: 100,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Payment(PaymentError::NoFunds {})
+    );
+
+    // rent for 100 seconds
+    let expires = app.block_info().time.plus_seconds(100);
+    rent(&mut app, &rentals, 100, 1000).unwrap();
+    assert_eq!(
+        query_user(&app, &cw721),
+        UserOfResponse {
+            user: RENTER.to_string(),
+            expires: Expiration::AtTime(expires),
+        }
+    );
+
+    // cannot rent it again while the rental is active
+    let err = rent(&mut app, &rentals, 100, 1000).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::AlreadyRented {
+            token_id: "1".to_string()
+        }
+    );
+
+    // once expired it can be rented again
+    app.update_block(|block| block.time = block.time.plus_seconds(100));
+    assert_eq!(query_user(&app, &cw721).user, "");
+    rent(&mut app, &rentals, 60, 600).unwrap();
+    assert_eq!(query_user(&app, &cw721).user, RENTER);
+
+    // rent is escrowed until the owner claims it
+    let proceeds: ProceedsResponse = app
+        .wrap()
+        .query_wasm_smart(
+            &rentals,
+            &QueryMsg::Proceeds {
+                owner: OWNER.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(proceeds.proceeds, coins(1600, DENOM));
+    assert_eq!(
+        app.wrap().query_balance(&rentals, DENOM).unwrap(),
+        coin(1600, DENOM)
+    );
+
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        rentals.clone(),
+        &ExecuteMsg::Claim {},
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap().query_balance(OWNER, DENOM).unwrap(),
+        coin(1600, DENOM)
+    );
+
+    // nothing left to claim
+    let err = app
+        .execute_contract(Addr::unchecked(OWNER), rentals, &ExecuteMsg::Claim {}, &[])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::NothingToClaim {}
+    );
+}
+
+#[test]
+fn stale_listing_and_delist() {
+    let (mut app, cw721, rentals) = setup();
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        cw721.clone(),
+        &Cw721ExecuteMsg::Approve {
+            spender: rentals.to_string(),
+            token_id: "1".to_string(),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(Addr::unchecked(OWNER), rentals.clone(), &list_msg(), &[])
+        .unwrap();
+
+    // the token is sold, the listing no longer applies
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        cw721,
+        &Cw721ExecuteMsg::TransferNft {
+            recipient: "buyer".to_string(),
+            token_id: "1".to_string(),
+        },
+        &[],
+    )
+    .unwrap();
+    let err = rent(&mut app, &rentals, 100, 1000).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::StaleListing {
+            token_id: "1".to_string()
+        }
+    );
+
+    // random cannot delist, the new owner can
+    let delist_msg = ExecuteMsg::Delist {
+        token_id: "1".to_string(),
+    };
+    let err = app
+        .execute_contract(Addr::unchecked("random"), rentals.clone(), &delist_msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    );
+    app.execute_contract(Addr::unchecked("buyer"), rentals.clone(), &delist_msg, &[])
+        .unwrap();
+
+    let listings: ListingsResponse = app
+        .wrap()
+        .query_wasm_smart(
+            &rentals,
+            &QueryMsg::Listings {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert!(listings.listings.is_empty());
+}
@@ -0,0 +1,47 @@
+use cosmwasm_std::{coin, Deps, Order, StdResult};
+use cw_storage_plus::Bound;
+
+use crate::msg::{ConfigResponse, ListingResponse, ListingsResponse, ProceedsResponse};
+use crate::state::{Listing, COLLECTION, LISTINGS, PROCEEDS};
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 100;
+
+pub fn config(deps: Deps) -> StdResult<ConfigResponse> {
+    let collection = COLLECTION.load(deps.storage)?;
+    Ok(ConfigResponse {
+        collection: collection.into_string(),
+    })
+}
+
+pub fn listing(deps: Deps, token_id: String) -> StdResult<Listing> {
+    LISTINGS.load(deps.storage, &token_id)
+}
+
+pub fn listings(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ListingsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_deref().map(Bound::exclusive);
+
+    let listings = LISTINGS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(token_id, listing)| ListingResponse { token_id, listing }))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(ListingsResponse { listings })
+}
+
+pub fn proceeds(deps: Deps, owner: String) -> StdResult<ProceedsResponse> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let proceeds = PROCEEDS
+        .prefix(&owner)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(denom, amount)| coin(amount.u128(), denom)))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(ProceedsResponse { proceeds })
+}
@@ -0,0 +1,42 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, BlockInfo, Uint128};
+use cw721::Expiration;
+use cw_storage_plus::{Item, Map};
+
+/// The collection whose tokens are rented out
+pub const COLLECTION: Item<Addr> = Item::new("collection");
+
+/// Listed tokens, keyed by token_id
+pub const LISTINGS: Map<&str, Listing> = Map::new("listings");
+
+/// Rent held in escrow until the owner claims it, keyed by (owner, denom)
+pub const PROCEEDS: Map<(&Addr, &str), Uint128> = Map::new("proceeds");
+
+#[cw_serde]
+pub struct Listing {
+    /// The token owner that listed the token and receives the rent
+    pub owner: Addr,
+    pub denom: String,
+    /// Price per unit of rental time
+    pub price: Uint128,
+    pub unit: RentalUnit,
+    pub min_duration: u64,
+    pub max_duration: u64,
+}
+
+/// How rental durations are measured
+#[cw_serde]
+pub enum RentalUnit {
+    Second,
+    Block,
+}
+
+impl RentalUnit {
+    /// Returns when a rental of `duration` units starting at the current block expires
+    pub fn expiration(&self, block: &BlockInfo, duration: u64) -> Expiration {
+        match self {
+            RentalUnit::Second => Expiration::AtTime(block.time.plus_seconds(duration)),
+            RentalUnit::Block => Expiration::AtHeight(block.height + duration),
+        }
+    }
+}
@@ -1,5 +1,5 @@
-use cosmwasm_std::StdError;
-use cw_ownable::OwnershipError;
+use cosmwasm_std::{Coin, StdError, Uint128};
+use cw_utils::PaymentError;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -8,16 +8,10 @@ pub enum ContractError {
     Std(#[from] StdError),
 
     #[error(transparent)]
-    Ownership(#[from] OwnershipError),
-
-    #[error(transparent)]
-    Version(#[from] cw2::VersionError),
+    Payment(#[from] PaymentError),
 
     #[error("Unauthorized")]
     Unauthorized {},
-    
-    #[error("token_id already claimed")]
-    Claimed {},
 
     #[error("The token_id is already staked in this collection")]
     AlreadyStakedTokenIDInCollection {},
@@ -30,4 +24,58 @@ pub enum ContractError {
 
     #[error("The collection is not supported")]
     InvalidCollection {},
+
+    #[error("The reward pool only accepts {reward_asset}")]
+    InvalidRewardAsset { reward_asset: String },
+
+    #[error("The reward asset cannot change while the reward pool holds funds")]
+    RewardPoolNotEmpty {},
+
+    #[error("Nothing to claim")]
+    NothingToClaim {},
+
+    #[error("Reward pool is underfunded: {rewards} rewards due, {funded_tokens} left")]
+    InsufficientRewardFunds {
+        rewards: Uint128,
+        funded_tokens: Uint128,
+    },
+
+    #[error("The NFT is locked until {unlock_time}")]
+    UnstakeTooEarly { unlock_time: u64 },
+
+    #[error("The NFT is unbonding until {withdrawable_at}")]
+    UnbondingNotOver { withdrawable_at: u64 },
+
+    #[error("Forfeit ratio must be at most 1 and fees must be non-zero")]
+    InvalidEarlyExitPolicy {},
+
+    #[error("The emission must end after it starts")]
+    InvalidEmissionSchedule {},
+
+    #[error("Only available with an emission reward mode")]
+    InvalidRewardMode {},
+
+    #[error("Multipliers must be non-zero")]
+    InvalidMultiplier {},
+
+    #[error("This contract must be the minter of the badge collection")]
+    NotBadgeMinter {},
+
+    #[error("Milestones must need at least 1 day or 1 NFT")]
+    InvalidMilestone {},
+
+    #[error("Not available in emergency mode")]
+    EmergencyMode {},
+
+    #[error("Only available in emergency mode")]
+    NotEmergencyMode {},
+
+    #[error("The reward pool can be withdrawn from {withdraw_after}")]
+    WithdrawLocked { withdraw_after: u64 },
+
+    #[error("Unknown reply id {id}")]
+    UnknownReplyId { id: u64 },
+
+    #[error("Unstaking early requires a fee of {fee}")]
+    IncorrectEarlyExitFee { fee: Coin },
 }
@@ -1,215 +1,1226 @@
-use cw_ownable::OwnershipError;
-use serde::de::DeserializeOwned;
-use serde::Serialize;
+use std::marker::PhantomData;
 
 use cosmwasm_std::{
-    Addr, BankMsg, Binary, Coin, CustomMsg, Deps, DepsMut, Env, MessageInfo, Response, Storage,
+    from_json, Addr, BankMsg, BlockInfo, Coin, CosmosMsg, Decimal, Deps, DepsMut, Empty, Env,
+    Event, MessageInfo, Order, Reply, Response, StdResult, Storage, SubMsg, SubMsgResult,
+    Timestamp, Uint128,
 };
-
-use cw721::{ContractInfoResponse, Cw721Execute, Cw721ReceiveMsg, Expiration};
+use cw20::Cw20ReceiveMsg;
+use cw721::{Cw721QueryMsg, Cw721ReceiveMsg, NftInfoResponse};
+use cw721_base::helpers::Cw721Contract;
+use cw721_base::MinterResponse;
+use cw_storage_plus::Bound;
+use cw_utils::{must_pay, nonpayable, Expiration, PaymentError};
+use serde::Deserialize;
 
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, InstantiateMsg};
-use crate::state::{Approval, Cw721Contract, TokenInfo, User};
+use crate::msg::{Cw20HookMsg, ReceiveMsg, UncheckedRewardAsset};
+use crate::state::{
+    nft_stakes, update_voting_power, Config, EarlyExitPolicy, Milestone, MilestoneCondition, Mode,
+    RewardAsset, RewardMode, StakeInfo, State, Unbonding, BADGES, BADGE_COLLECTION, COLLECTIONS,
+    COLLECTION_MULTIPLIER, CONFIG, DAY_IN_SECONDS, EARLY_EXIT_POLICY, EMERGENCY_WITHDRAW_DELAY,
+    MILESTONES, MODE, PENDING_BADGES, REWARD_RATE, STAKED_NFTS, STATE, TRAIT_MULTIPLIER, UNBONDING,
+    UNPAID_REWARDS,
+};
+
+pub type Cw721Collection = Cw721Contract<Empty, Empty>;
+pub type Cw721BaseExecuteMsg = cw721_base::ExecuteMsg<Empty, Empty>;
+
+/// Replies of the `SetUser` and `RemoveUser` calls, which may be rejected by the collection
+pub const SET_USER_REPLY_ID: u64 = 1;
+pub const REMOVE_USER_REPLY_ID: u64 = 2;
+/// Reply of a badge `Mint`, which is recorded once it succeeded and skipped otherwise
+pub const BADGE_REPLY_ID: u64 = 3;
+
+/// Most stakes `ClaimAll` settles at once
+const MAX_CLAIM_LIMIT: u32 = 100;
+/// Most stakes `ClaimAll` looks at, including those without rewards
+const MAX_CLAIM_SCAN: usize = 300;
 
-const CONTRACT_NAME: &str = "crates.io:staking";
-const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// The part of a collection's `Metadata` extension multipliers are read from. Other
+/// fields are ignored, so this reads any extension with OpenSea style attributes.
+#[derive(Deserialize)]
+struct TraitsExtension {
+    attributes: Option<Vec<Trait>>,
+}
+
+#[derive(Deserialize)]
+struct Trait {
+    trait_type: String,
+    value: String,
+}
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn instantiate(
+pub fn try_update_config(
     deps: DepsMut,
-    _env: Env,
     info: MessageInfo,
-    msg: InstantiateMsg,
+    owner: String,
+    reward_asset: UncheckedRewardAsset,
+    is_staking_allowed: bool,
+    unbonding_period: u64,
 ) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
 
-    let owner_addr = deps.api.addr_validate(&msg.owner)?;
-    let token_address = deps.api.addr_validate(&msg.token_address)?;
-
-    let config = Config {
-        owner: owner_addr,
-        token_address: token_address,
-        is_staking_allowed: msg.boolean,
-    
-};
-    let state = State {
-        total_sales: Uint128::new(0),
-        total_stakes: Uint128::new(0),
-        total_claims: Uint128::new(0),
-        funded_tokens: Uint128::new(0),
-    };
+    // the pool is accounted in a single asset, switching would strand what is left in it
+    let reward_asset = reward_asset.check(deps.api)?;
+    if reward_asset != config.reward_asset && !STATE.load(deps.storage)?.funded_tokens.is_zero() {
+        return Err(ContractError::RewardPoolNotEmpty {});
+    }
 
-    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    config.owner = deps.api.addr_validate(&owner)?;
+    config.reward_asset = reward_asset;
+    config.is_staking_allowed = is_staking_allowed;
+    config.unbonding_period = unbonding_period;
     CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new().add_attribute("method", "update_config"))
+}
+
+pub fn try_whitelisting_collection(
+    deps: DepsMut,
+    info: MessageInfo,
+    collection_addr: String,
+    boolean: bool,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let collection_addr = deps.api.addr_validate(&collection_addr)?;
+    COLLECTIONS.save(deps.storage, &collection_addr, &boolean)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "whitelist_collection")
+        .add_attribute("collection", collection_addr)
+        .add_attribute("whitelist", boolean.to_string()))
+}
+
+pub fn try_adding_reward_rate(
+    deps: DepsMut,
+    info: MessageInfo,
+    number_days: u64,
+    rate: u64,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    REWARD_RATE.save(deps.storage, number_days, &rate)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "add_reward_rate")
+        .add_attribute("number_days", number_days.to_string())
+        .add_attribute("rate", rate.to_string()))
+}
+
+pub fn try_setting_emission(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    rate: Uint128,
+    start: u64,
+    end: u64,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    if !matches!(config.reward_mode, RewardMode::Emission { .. }) {
+        return Err(ContractError::InvalidRewardMode {});
+    }
+    let reward_mode = RewardMode::Emission { rate, start, end };
+    reward_mode.validate()?;
+
+    // what was emitted under the old schedule is kept
+    let mut state = STATE.load(deps.storage)?;
+    state.accrue(&config.reward_mode, env.block.time.seconds());
     STATE.save(deps.storage, &state)?;
+    config.reward_mode = reward_mode;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_emission")
+        .add_attribute("rate", rate)
+        .add_attribute("start", start.to_string())
+        .add_attribute("end", end.to_string()))
+}
+
+pub fn try_setting_collection_multiplier(
+    deps: DepsMut,
+    info: MessageInfo,
+    collection: String,
+    multiplier: Option<Decimal>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let collection_addr = deps.api.addr_validate(&collection)?;
+    match multiplier {
+        Some(multiplier) if multiplier.is_zero() => {
+            return Err(ContractError::InvalidMultiplier {})
+        }
+        Some(multiplier) => {
+            COLLECTION_MULTIPLIER.save(deps.storage, &collection_addr, &multiplier)?
+        }
+        None => COLLECTION_MULTIPLIER.remove(deps.storage, &collection_addr),
+    }
+
+    Ok(Response::new()
+        .add_attribute("method", "set_collection_multiplier")
+        .add_attribute("collection", collection_addr)
+        .add_attribute(
+            "multiplier",
+            multiplier.unwrap_or(Decimal::one()).to_string(),
+        ))
+}
+
+pub fn try_setting_trait_multiplier(
+    deps: DepsMut,
+    info: MessageInfo,
+    collection: String,
+    trait_type: String,
+    value: String,
+    multiplier: Option<Decimal>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let collection_addr = deps.api.addr_validate(&collection)?;
+    let key = (&collection_addr, trait_type.as_str(), value.as_str());
+    match multiplier {
+        Some(multiplier) if multiplier.is_zero() => {
+            return Err(ContractError::InvalidMultiplier {})
+        }
+        Some(multiplier) => TRAIT_MULTIPLIER.save(deps.storage, key, &multiplier)?,
+        None => TRAIT_MULTIPLIER.remove(deps.storage, key),
+    }
 
     Ok(Response::new()
-        .add_attribute("method", "instantiate")
-        .add_attribute("sender", info.sender))
+        .add_attribute("method", "set_trait_multiplier")
+        .add_attribute("collection", collection_addr)
+        .add_attribute("trait_type", trait_type)
+        .add_attribute("value", value)
+        .add_attribute(
+            "multiplier",
+            multiplier.unwrap_or(Decimal::one()).to_string(),
+        ))
+}
+
+pub fn try_setting_early_exit_policy(
+    deps: DepsMut,
+    info: MessageInfo,
+    number_days: u64,
+    policy: Option<EarlyExitPolicy>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let res = Response::new()
+        .add_attribute("method", "set_early_exit_policy")
+        .add_attribute("number_days", number_days.to_string());
+    let policy = match policy {
+        Some(policy) => policy,
+        None => {
+            EARLY_EXIT_POLICY.remove(deps.storage, number_days);
+            return Ok(res.add_attribute("policy", "none"));
+        }
+    };
+
+    let valid = match &policy {
+        EarlyExitPolicy::ForfeitRewards { ratio } => *ratio <= Decimal::one(),
+        EarlyExitPolicy::Fee { fee } => !fee.amount.is_zero(),
+    };
+    if !valid {
+        return Err(ContractError::InvalidEarlyExitPolicy {});
+    }
+    EARLY_EXIT_POLICY.save(deps.storage, number_days, &policy)?;
+
+    let res = match policy {
+        EarlyExitPolicy::ForfeitRewards { ratio } => res
+            .add_attribute("policy", "forfeit_rewards")
+            .add_attribute("ratio", ratio.to_string()),
+        EarlyExitPolicy::Fee { fee } => res
+            .add_attribute("policy", "fee")
+            .add_attribute("fee", fee.to_string()),
+    };
+    Ok(res)
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn execute(
+/// Entry point for `SendNft`, the sender is the collection and the NFT is already ours
+pub fn try_receive_nft(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    msg: ExecuteMsg,
+    receive_msg: Cw721ReceiveMsg,
 ) -> Result<Response, ContractError> {
-    match msg {
-        ExecuteMsg::WhitelistCollection {collection_addr, boolean} => try_whitelisting_collection(deps, env, info, collection_addr, boolean),
-        ExecuteMsg::AddRwardRate{days_number, rate} => try_adding_reward_rate(deps, env, info, days_number, rate),
-        ExecuteMsg::Stake {token_id, number_days} => try_staking(deps, env, info, token_id, number_days),
-        ExecuteMsg::Claim {token_id} => try_claim(deps, env, info, token_id),
-        ExecuteMsg::Unstake {token_id} => try_unstake(deps, env, info, token_id),
-        //ExecuteMsg::Withdraw {token_id} => try_withdraw(deps, env, info, token_id),
-        ExecuteMsg::UpdateConfig { 
-            owner,
-            token_address,
-            sales_wallet,
-            is_staking_allowed,  
+    match from_json(&receive_msg.msg)? {
+        ReceiveMsg::Stake { number_days } => {
+            let owner = deps.api.addr_validate(&receive_msg.sender)?;
+            try_staking(deps, env, info, owner, receive_msg.token_id, number_days)
+        }
+    }
+}
 
-        } => try_update_config(deps, env, info,
-            owner,
-            token_address,
-            is_staking_allowed,
-        ),
+/// Entry point for cw20 `Send`, only the reward token can fund the pool
+pub fn try_receive_cw20(
+    deps: DepsMut,
+    info: MessageInfo,
+    receive_msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    match &config.reward_asset {
+        RewardAsset::Cw20 { address } if *address == info.sender => {}
+        reward_asset => {
+            return Err(ContractError::InvalidRewardAsset {
+                reward_asset: reward_asset.to_string(),
+            })
+        }
+    }
+
+    match from_json(&receive_msg.msg)? {
+        Cw20HookMsg::Fund {} => {
+            let sender = deps.api.addr_validate(&receive_msg.sender)?;
+            fund_pool(deps.storage, sender, receive_msg.amount)
+        }
     }
 }
 
-pub fn try_update_config(
-    deps: DepsMut, 
+/// Funds a native reward pool with the reward denom sent along
+pub fn try_fund(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let amount = match &config.reward_asset {
+        RewardAsset::Native { denom } => must_pay(&info, denom)?,
+        reward_asset => {
+            return Err(ContractError::InvalidRewardAsset {
+                reward_asset: reward_asset.to_string(),
+            })
+        }
+    };
+
+    fund_pool(deps.storage, info.sender, amount)
+}
+
+fn fund_pool(
+    storage: &mut dyn Storage,
+    sender: Addr,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    Mode::ensure_normal(storage)?;
+    let state = STATE.update(storage, |mut state| -> Result<_, ContractError> {
+        state.funded_tokens += amount;
+        Ok(state)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("method", "fund")
+        .add_attribute("sender", sender)
+        .add_attribute("amount", amount)
+        .add_attribute("funded_tokens", state.funded_tokens))
+}
+
+pub fn try_staking(
+    deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    owner: String,
-    token_address: String,
-    is_staking_allowed: bool
+    owner: Addr,
+    token_id: String,
+    number_days: u64,
+) -> Result<Response, ContractError> {
+    Mode::ensure_normal(deps.storage)?;
+    let config = CONFIG.load(deps.storage)?;
+    if !config.is_staking_allowed {
+        return Err(ContractError::StakingNotAllowed {});
+    }
 
-    ) -> Result<Response, ContractError> {
+    // the collection contract is the one sending us the NFT
+    let collection_addr = info.sender;
+    let whitelisted = COLLECTIONS
+        .may_load(deps.storage, &collection_addr)?
+        .unwrap_or_default();
+    if !whitelisted {
+        return Err(ContractError::InvalidCollection {});
+    }
 
-        let mut config = CONFIG.load(deps.storage)?;
+    if nft_stakes().has(deps.storage, (&token_id, &collection_addr)) {
+        return Err(ContractError::AlreadyStakedTokenIDInCollection {});
+    }
 
-        if config.owner != info.sender {
-        return Err(ContractError::Unauthorized {});
+    // only lock periods with a reward rate can be staked for
+    let reward_rate = REWARD_RATE
+        .may_load(deps.storage, number_days)?
+        .unwrap_or_default();
+    if reward_rate == 0 {
+        return Err(ContractError::InvalidDaysNumber {});
+    }
+
+    let multiplier = reward_multiplier(deps.as_ref(), &collection_addr, &token_id)?;
+
+    // the stake only shares in what is emitted from now on
+    let now = env.block.time.seconds();
+    let mut state = STATE.load(deps.storage)?;
+    state.accrue(&config.reward_mode, now);
+    let stake = StakeInfo {
+        owner,
+        total_earned_tokens: Uint128::zero(),
+        token_id,
+        stake_days: number_days,
+        reward_rate,
+        multiplier,
+        stake_time: now,
+        last_claim_time: now,
+        reward_debt: state.reward_per_weight,
+        collection: collection_addr,
+    };
+    nft_stakes().save(deps.storage, (&stake.token_id, &stake.collection), &stake)?;
+    state.total_stakes += Uint128::one();
+    state.total_weight += stake.weight();
+    STATE.save(deps.storage, &state)?;
+    update_voting_power(deps.storage, &stake.owner, env.block.height, true)?;
+
+    // the staker keeps using the NFT until the lock ends
+    let collection: Cw721Collection =
+        Cw721Contract(stake.collection.clone(), PhantomData, PhantomData);
+    let set_user = match collection.user_of(&deps.querier, &stake.token_id) {
+        // someone else, like a renter, still has the NFT
+        Ok(user) if !user.user.is_empty() && user.user != stake.owner => None,
+        Ok(_) => {
+            let unlock_time = Timestamp::from_seconds(stake.unlock_time());
+            let msg = collection.call(Cw721BaseExecuteMsg::SetUser {
+                token_id: stake.token_id.clone(),
+                user: stake.owner.to_string(),
+                expires: Some(Expiration::AtTime(unlock_time)),
+            })?;
+            Some(SubMsg::reply_on_error(msg, SET_USER_REPLY_ID))
         }
-        // no comment yet
-        let owner_addr = deps.api.addr_validate(&owner)?;
-        let token_address = deps.api.addr_validate(&token_address)?;
+        // the collection does not support users
+        Err(_) => None,
+    };
+
+    let badges = mint_badges(deps.storage, &stake.owner, now)?;
 
-        config.owner = owner_addr;
-        config.token_address = token_address;
-        config.is_staking_allowed = is_staking_allowed;
+    Ok(Response::new()
+        .add_submessages(set_user)
+        .add_submessages(badges)
+        .add_attribute("method", "stake")
+        .add_attribute("owner", stake.owner)
+        .add_attribute("collection", stake.collection)
+        .add_attribute("token_id", stake.token_id)
+        .add_attribute("number_days", number_days.to_string())
+        .add_attribute("rate", reward_rate.to_string())
+        .add_attribute("multiplier", multiplier.to_string()))
+}
 
-        CONFIG.save(deps.storage, &config)?;
+/// Collection multiplier times the highest trait multiplier matching the NFT's metadata.
+/// The metadata is only queried when the collection has trait multipliers.
+fn reward_multiplier(deps: Deps, collection: &Addr, token_id: &str) -> StdResult<Decimal> {
+    let collection_multiplier = COLLECTION_MULTIPLIER
+        .may_load(deps.storage, collection)?
+        .unwrap_or(Decimal::one());
+    if TRAIT_MULTIPLIER
+        .sub_prefix(collection)
+        .keys_raw(deps.storage, None, None, Order::Ascending)
+        .next()
+        .is_none()
+    {
+        return Ok(collection_multiplier);
+    }
 
-        Ok(Response::new()
-        .add_attribute("method", "updateconfig"))
+    let info: NftInfoResponse<Option<TraitsExtension>> = deps.querier.query_wasm_smart(
+        collection,
+        &Cw721QueryMsg::NftInfo {
+            token_id: token_id.to_string(),
+        },
+    )?;
+    let attributes = info
+        .extension
+        .and_then(|extension| extension.attributes)
+        .unwrap_or_default();
+    let mut trait_multiplier = Decimal::one();
+    for attribute in attributes {
+        let key = (
+            collection,
+            attribute.trait_type.as_str(),
+            attribute.value.as_str(),
+        );
+        if let Some(multiplier) = TRAIT_MULTIPLIER.may_load(deps.storage, key)? {
+            trait_multiplier = trait_multiplier.max(multiplier);
+        }
     }
+    Ok(collection_multiplier * trait_multiplier)
+}
 
+pub fn try_unstake(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    collection: String,
+    token_id: String,
+) -> Result<Response, ContractError> {
+    let res = unstake_nfts(
+        deps,
+        env,
+        info,
+        vec![(collection.clone(), token_id.clone())],
+    )?;
+    Ok(res
+        .add_attribute("method", "unstake")
+        .add_attribute("collection", collection)
+        .add_attribute("token_id", token_id))
+}
 
+/// Unstakes several NFTs at once, paying all their rewards in one transfer
+pub fn try_unstake_many(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    tokens: Vec<(String, String)>,
+) -> Result<Response, ContractError> {
+    let count = tokens.len();
+    let res = unstake_nfts(deps, env, info, tokens)?;
+    Ok(res
+        .add_attribute("method", "unstake_many")
+        .add_attribute("count", count.to_string()))
+}
 
-pub fn try_whitelisting_collection(
-    deps: DepsMut, 
+fn unstake_nfts(
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    collection_addr: String,
-    boolean: bool
+    tokens: Vec<(String, String)>,
+) -> Result<Response, ContractError> {
+    Mode::ensure_normal(deps.storage)?;
+    let config = CONFIG.load(deps.storage)?;
+    let mut state = STATE.load(deps.storage)?;
+    let now = env.block.time.seconds();
+    state.accrue(&config.reward_mode, now);
+
+    // milestones are checked while the NFTs still count as staked
+    let badges = mint_badges(deps.storage, &info.sender, now)?;
+    let mut res = Response::new().add_submessages(badges);
+    let mut total_rewards = Uint128::zero();
+    let mut total_forfeited = Uint128::zero();
+    let mut fees: Vec<Coin> = vec![];
+    for (collection, token_id) in tokens {
+        let collection_addr = deps.api.addr_validate(&collection)?;
+        let (rewards, forfeited, msgs) = unstake_nft(
+            deps.branch(),
+            &config,
+            &mut state,
+            &info.sender,
+            &env.block,
+            collection_addr,
+            token_id,
+            &mut fees,
+        )?;
+        total_rewards += rewards;
+        total_forfeited += forfeited;
+        res = res.add_submessages(msgs);
+    }
 
-    ) -> Result<Response, ContractError> { 
+    // early exit fees of all NFTs are paid at once and go to the owner, nothing else is accepted
+    if fees.is_empty() {
+        nonpayable(&info)?;
+    }
+    if let Some(coin) = info
+        .funds
+        .iter()
+        .find(|coin| fees.iter().all(|fee| fee.denom != coin.denom))
+    {
+        return Err(PaymentError::ExtraDenom(coin.denom.clone()).into());
+    }
+    for fee in &fees {
+        let paid: Uint128 = info
+            .funds
+            .iter()
+            .filter(|coin| coin.denom == fee.denom)
+            .map(|coin| coin.amount)
+            .sum();
+        if paid != fee.amount {
+            return Err(ContractError::IncorrectEarlyExitFee { fee: fee.clone() });
+        }
+    }
+    let fee_msg = (!fees.is_empty()).then(|| BankMsg::Send {
+        to_address: config.owner.to_string(),
+        amount: fees,
+    });
+
+    // an underfunded pool must not keep NFTs locked, what it cannot cover stays owed
+    let paid = total_rewards.min(state.funded_tokens);
+    let reward_msg = pay_rewards(&config, &mut state, &info.sender, paid)?;
+    STATE.save(deps.storage, &state)?;
+    if paid < total_rewards {
+        UNPAID_REWARDS.update(deps.storage, &info.sender, |unpaid| -> StdResult<_> {
+            Ok(unpaid.unwrap_or_default() + total_rewards - paid)
+        })?;
+    }
 
-        let config = CONFIG.load(deps.storage)?;
+    if config.unbonding_period > 0 {
+        res = res.add_attribute(
+            "withdrawable_at",
+            (now + config.unbonding_period).to_string(),
+        );
+    }
+    Ok(res
+        .add_messages(reward_msg)
+        .add_messages(fee_msg)
+        .add_attribute("owner", info.sender)
+        .add_attribute("rewards", paid)
+        .add_attribute("unpaid", total_rewards - paid)
+        .add_attribute("forfeited", total_forfeited))
+}
 
-        if config.owner != info.sender {
+/// Removes the stake of an NFT owned by `sender`, returning the rewards to pay, the rewards
+/// forfeited and the messages returning the NFT, if it has no unbonding period. Early exit
+/// fees are added to `fees`.
+#[allow(clippy::too_many_arguments)]
+fn unstake_nft(
+    deps: DepsMut,
+    config: &Config,
+    state: &mut State,
+    sender: &Addr,
+    block: &BlockInfo,
+    collection_addr: Addr,
+    token_id: String,
+    fees: &mut Vec<Coin>,
+) -> Result<(Uint128, Uint128, Vec<SubMsg>), ContractError> {
+    let mut stake = nft_stakes().load(deps.storage, (&token_id, &collection_addr))?;
+    if stake.owner != sender {
         return Err(ContractError::Unauthorized {});
+    }
+
+    // leaving before the lock period is over is only possible on the terms of its policy
+    let now = block.time.seconds();
+    let unlock_time = stake.unlock_time();
+    let mut forfeit_ratio = Decimal::zero();
+    if now < unlock_time {
+        match EARLY_EXIT_POLICY.may_load(deps.storage, stake.stake_days)? {
+            None => return Err(ContractError::UnstakeTooEarly { unlock_time }),
+            Some(EarlyExitPolicy::ForfeitRewards { ratio }) => forfeit_ratio = ratio,
+            Some(EarlyExitPolicy::Fee { fee }) => {
+                match fees.iter_mut().find(|coin| coin.denom == fee.denom) {
+                    Some(coin) => coin.amount += fee.amount,
+                    None => fees.push(fee),
+                }
+            }
         }
+    }
 
-        let collection_addr = deps.api.addr_validate(&collection_addr)?;
-        let  _collection = COLLECTIONS.save(deps.storage, &collection_addr.clone(), boolean.clone())?;
+    let (rewards, forfeited) = settle_rewards(config, state, &mut stake, now, forfeit_ratio);
+    remove_stake(deps.storage, state, &stake, block.height)?;
 
-        Ok(Response::new()
-            .add_attribute("method", "whitelist collection")
-            .add_attribute("whitelist", &boolean.to_string()) 
-        )
- 
-    } 
+    if config.unbonding_period == 0 {
+        let msgs = return_nft_msgs(
+            deps.as_ref(),
+            &stake.owner,
+            &stake.collection,
+            &stake.token_id,
+        )?;
+        return Ok((rewards, forfeited, msgs));
+    }
+    let unbonding = Unbonding {
+        owner: stake.owner,
+        collection: stake.collection,
+        token_id: stake.token_id,
+        withdrawable_at: now + config.unbonding_period,
+    };
+    UNBONDING.save(
+        deps.storage,
+        (&unbonding.owner, &unbonding.collection, &unbonding.token_id),
+        &unbonding,
+    )?;
+    // the staker stops using the NFT as soon as it stops earning
+    let msgs = remove_user_msgs(
+        deps.as_ref(),
+        &unbonding.owner,
+        &unbonding.collection,
+        &unbonding.token_id,
+    )?;
+    Ok((rewards, forfeited, msgs))
+}
 
+fn remove_stake(
+    storage: &mut dyn Storage,
+    state: &mut State,
+    stake: &StakeInfo,
+    height: u64,
+) -> StdResult<()> {
+    nft_stakes().remove(storage, (&stake.token_id, &stake.collection))?;
+    state.total_stakes -= Uint128::one();
+    state.total_weight -= stake.weight();
+    update_voting_power(storage, &stake.owner, height, false)
+}
 
-pub fn try_adding_reward_rate(
-    deps: DepsMut, 
+/// Message clearing the user set when staking, if `owner` still is the user
+fn remove_user_msgs(
+    deps: Deps,
+    owner: &Addr,
+    collection_addr: &Addr,
+    token_id: &str,
+) -> StdResult<Vec<SubMsg>> {
+    let collection: Cw721Collection =
+        Cw721Contract(collection_addr.clone(), PhantomData, PhantomData);
+    let mut msgs = vec![];
+    if let Ok(user) = collection.user_of(&deps.querier, token_id) {
+        if user.user == *owner {
+            let msg = collection.call(Cw721BaseExecuteMsg::RemoveUser {
+                token_id: token_id.to_string(),
+            })?;
+            msgs.push(SubMsg::reply_on_error(msg, REMOVE_USER_REPLY_ID));
+        }
+    }
+    Ok(msgs)
+}
+
+/// Messages giving an NFT held by this contract back to `owner`
+fn return_nft_msgs(
+    deps: Deps,
+    owner: &Addr,
+    collection_addr: &Addr,
+    token_id: &str,
+) -> StdResult<Vec<SubMsg>> {
+    // clear the user set when staking, before the NFT changes hands
+    let mut msgs = remove_user_msgs(deps, owner, collection_addr, token_id)?;
+    let collection: Cw721Collection =
+        Cw721Contract(collection_addr.clone(), PhantomData, PhantomData);
+    let transfer = collection.call(Cw721BaseExecuteMsg::TransferNft {
+        recipient: owner.to_string(),
+        token_id: token_id.to_string(),
+    })?;
+    msgs.push(SubMsg::new(transfer));
+
+    Ok(msgs)
+}
+
+/// Returns unstaked NFTs of the sender once their unbonding period is over. In emergency
+/// mode they can be withdrawn right away.
+pub fn try_withdraw_unbonded(
+    deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    number_days: u64,
-    rate: u64,
+    tokens: Vec<(String, String)>,
+) -> Result<Response, ContractError> {
+    let emergency = Mode::load(deps.storage)? != Mode::Normal;
+    let now = env.block.time.seconds();
 
-    ) -> Result<Response, ContractError> { 
+    let mut res = Response::new();
+    for (collection, token_id) in &tokens {
+        let collection_addr = deps.api.addr_validate(collection)?;
+        let key = (&info.sender, &collection_addr, token_id.as_str());
+        let unbonding = UNBONDING.load(deps.storage, key)?;
+        if !emergency && now < unbonding.withdrawable_at {
+            return Err(ContractError::UnbondingNotOver {
+                withdrawable_at: unbonding.withdrawable_at,
+            });
+        }
+        UNBONDING.remove(deps.storage, key);
+        res = res.add_submessages(return_nft_msgs(
+            deps.as_ref(),
+            &info.sender,
+            &collection_addr,
+            token_id,
+        )?);
+    }
 
-        let config = CONFIG.load(deps.storage)?;
+    Ok(res
+        .add_attribute("method", "withdraw_unbonded")
+        .add_attribute("owner", info.sender)
+        .add_attribute("count", tokens.len().to_string()))
+}
 
-        if config.owner != info.sender {
+pub fn try_entering_emergency(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
         return Err(ContractError::Unauthorized {});
+    }
+    Mode::ensure_normal(deps.storage)?;
+
+    let since = env.block.time.seconds();
+    MODE.save(deps.storage, &Mode::Emergency { since })?;
+
+    let event = Event::new("emergency")
+        .add_attribute("action", "enter")
+        .add_attribute("since", since.to_string())
+        .add_attribute(
+            "withdraw_after",
+            (since + EMERGENCY_WITHDRAW_DELAY).to_string(),
+        );
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("method", "enter_emergency"))
+}
+
+/// Gives staked NFTs back to the sender without paying any rewards, which stay in the pool
+pub fn try_emergency_unstake(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    tokens: Vec<(String, String)>,
+) -> Result<Response, ContractError> {
+    if Mode::load(deps.storage)? == Mode::Normal {
+        return Err(ContractError::NotEmergencyMode {});
+    }
+
+    let mut state = STATE.load(deps.storage)?;
+    let mut res = Response::new();
+    for (collection, token_id) in tokens {
+        let collection_addr = deps.api.addr_validate(&collection)?;
+        let stake = nft_stakes().load(deps.storage, (&token_id, &collection_addr))?;
+        if stake.owner != info.sender {
+            return Err(ContractError::Unauthorized {});
         }
+        remove_stake(deps.storage, &mut state, &stake, env.block.height)?;
+        let msgs = return_nft_msgs(
+            deps.as_ref(),
+            &stake.owner,
+            &stake.collection,
+            &stake.token_id,
+        )?;
+        let event = Event::new("emergency")
+            .add_attribute("action", "unstake")
+            .add_attribute("owner", &stake.owner)
+            .add_attribute("collection", &stake.collection)
+            .add_attribute("token_id", &stake.token_id);
+        res = res.add_submessages(msgs).add_event(event);
+    }
+    STATE.save(deps.storage, &state)?;
 
-        let collection_addr = deps.api.addr_validate(&collection_addr)?;
-        let  _collection = REWARD_RATE.save(deps.storage, number_days.clone(), rate.clone())?;
+    Ok(res
+        .add_attribute("method", "emergency_unstake")
+        .add_attribute("owner", info.sender))
+}
+
+/// Withdraws the reward pool once the emergency timelock is over. Stakers get no rewards
+/// in emergency mode, so all of the pool is unallocated.
+pub fn try_emergency_withdraw(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    let withdraw_after = match Mode::load(deps.storage)? {
+        Mode::Normal => return Err(ContractError::NotEmergencyMode {}),
+        Mode::Emergency { since } => since + EMERGENCY_WITHDRAW_DELAY,
+    };
+    if env.block.time.seconds() < withdraw_after {
+        return Err(ContractError::WithdrawLocked { withdraw_after });
+    }
 
-        Ok(Response::new()
-            .add_attribute("method", "adding reward rate")
-            .add_attribute("number_days", number_days.to_string()) 
-            .add_attribute("rate", rate.to_string()) 
-        )
- 
-    } 
+    let recipient = match recipient {
+        Some(recipient) => deps.api.addr_validate(&recipient)?,
+        None => config.owner.clone(),
+    };
+    let mut state = STATE.load(deps.storage)?;
+    let amount = state.funded_tokens;
+    if amount.is_zero() {
+        return Err(ContractError::NothingToClaim {});
+    }
+    state.funded_tokens = Uint128::zero();
+    STATE.save(deps.storage, &state)?;
+    let transfer = config.reward_asset.transfer_msg(&recipient, amount)?;
 
+    let event = Event::new("emergency")
+        .add_attribute("action", "withdraw")
+        .add_attribute("recipient", &recipient)
+        .add_attribute("amount", amount);
+    Ok(Response::new()
+        .add_message(transfer)
+        .add_event(event)
+        .add_attribute("method", "emergency_withdraw"))
+}
 
-//try_staking(deps, env, info, token_id, number_days),
-pub fn try_staking(
-    deps: DepsMut, 
+pub fn try_claim(
+    deps: DepsMut,
     env: Env,
     info: MessageInfo,
+    collection: String,
     token_id: String,
-    number_days: u64,
+) -> Result<Response, ContractError> {
+    Mode::ensure_normal(deps.storage)?;
+    let collection_addr = deps.api.addr_validate(&collection)?;
+    let mut stake = nft_stakes().load(deps.storage, (&token_id, &collection_addr))?;
+    if stake.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let mut state = STATE.load(deps.storage)?;
+    let now = env.block.time.seconds();
+    state.accrue(&config.reward_mode, now);
+    let (rewards, _) = settle_rewards(&config, &state, &mut stake, now, Decimal::zero());
+    if rewards.is_zero() {
+        return Err(ContractError::NothingToClaim {});
+    }
+    let reward_msg = pay_rewards(&config, &mut state, &stake.owner, rewards)?;
+    nft_stakes().save(deps.storage, (&token_id, &collection_addr), &stake)?;
+    STATE.save(deps.storage, &state)?;
+    let badges = mint_badges(deps.storage, &stake.owner, now)?;
+
+    Ok(Response::new()
+        .add_messages(reward_msg)
+        .add_submessages(badges)
+        .add_attribute("method", "claim")
+        .add_attribute("owner", stake.owner)
+        .add_attribute("collection", collection_addr)
+        .add_attribute("token_id", token_id)
+        .add_attribute("rewards", rewards))
+}
+
+/// Pays out the rewards unstaking left owed to the sender. An underfunded pool pays what it
+/// can, the rest stays owed.
+pub fn try_claiming_unpaid(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    Mode::ensure_normal(deps.storage)?;
+    let unpaid = UNPAID_REWARDS
+        .may_load(deps.storage, &info.sender)?
+        .unwrap_or_default();
+    if unpaid.is_zero() {
+        return Err(ContractError::NothingToClaim {});
+    }
 
-    ) -> Result<Response, ContractError> { 
+    let config = CONFIG.load(deps.storage)?;
+    let mut state = STATE.load(deps.storage)?;
+    if state.funded_tokens.is_zero() {
+        return Err(ContractError::InsufficientRewardFunds {
+            rewards: unpaid,
+            funded_tokens: state.funded_tokens,
+        });
+    }
+    let paid = unpaid.min(state.funded_tokens);
+    let reward_msg = pay_rewards(&config, &mut state, &info.sender, paid)?;
+    STATE.save(deps.storage, &state)?;
+    if paid == unpaid {
+        UNPAID_REWARDS.remove(deps.storage, &info.sender);
+    } else {
+        UNPAID_REWARDS.save(deps.storage, &info.sender, &(unpaid - paid))?;
+    }
 
-        let config = CONFIG.load(deps.storage)?;
+    Ok(Response::new()
+        .add_messages(reward_msg)
+        .add_attribute("method", "claim_unpaid")
+        .add_attribute("owner", info.sender)
+        .add_attribute("rewards", paid)
+        .add_attribute("unpaid", unpaid - paid))
+}
 
-        if !config.is_staking_allowed {
-            return Err(ContractError::StakingNotAllowed {});
+/// Claims the rewards of up to `limit` stakes of the sender, optionally of one collection,
+/// in one transfer. Stakes go in the key order of the owner index, starting after
+/// `start_after`, and at most `MAX_CLAIM_SCAN` are looked at. The `last` attribute is the
+/// stake to pass as `start_after` to continue from.
+pub fn try_claim_all(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    collection: Option<String>,
+    start_after: Option<(String, String)>,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    Mode::ensure_normal(deps.storage)?;
+    let collection_addr = collection
+        .map(|collection| deps.api.addr_validate(&collection))
+        .transpose()?;
+    let limit = limit.unwrap_or(MAX_CLAIM_LIMIT).min(MAX_CLAIM_LIMIT);
+    let start = match start_after {
+        Some((collection, token_id)) => {
+            let collection = deps.api.addr_validate(&collection)?;
+            Some(Bound::exclusive((token_id, collection)))
         }
+        None => None,
+    };
+
+    // the scan is bounded, a full page without rewards still returns `last` to skip it
+    let stakes = nft_stakes()
+        .idx
+        .owner
+        .prefix(info.sender.clone())
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(MAX_CLAIM_SCAN)
+        .map(|item| item.map(|(_, stake)| stake))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let config = CONFIG.load(deps.storage)?;
+    let mut state = STATE.load(deps.storage)?;
+    let now = env.block.time.seconds();
+    state.accrue(&config.reward_mode, now);
 
-        let collection_addr = deps.api.addr_validate(&collection_addr)?;
-        //check that collection is whitelisted
-        let  collection = COLLECTIONS.may_load(deps.storage, &collection_addr.clone())?.unwrap_or_default();
-        if !collection {
-            return Err(ContractError::InvalidCollection {});
+    let full_page = stakes.len() == MAX_CLAIM_SCAN;
+    let mut total_rewards = Uint128::zero();
+    let mut count = 0u32;
+    let mut last = None;
+    for mut stake in stakes {
+        if count == limit {
+            break;
         }
+        last = Some((stake.collection.clone(), stake.token_id.clone()));
+        if collection_addr.as_ref().map_or(false, |collection_addr| {
+            stake.collection != *collection_addr
+        }) {
+            continue;
+        }
+        let (rewards, _) = settle_rewards(&config, &state, &mut stake, now, Decimal::zero());
+        // stakes without rewards keep accruing from their last claim and do not count
+        if rewards.is_zero() {
+            continue;
+        }
+        nft_stakes().save(deps.storage, (&stake.token_id, &stake.collection), &stake)?;
+        total_rewards += rewards;
+        count += 1;
+    }
+    if total_rewards.is_zero() && !full_page {
+        return Err(ContractError::NothingToClaim {});
+    }
+    let reward_msg = pay_rewards(&config, &mut state, &info.sender, total_rewards)?;
+    STATE.save(deps.storage, &state)?;
+    let badges = mint_badges(deps.storage, &info.sender, now)?;
 
-        let  nftstake = NFT_STAKES.may_load(deps.storage, (token_id.clone(), collection_addr))?.unwrap_or_default();
+    Ok(Response::new()
+        .add_messages(reward_msg)
+        .add_submessages(badges)
+        .add_attribute("method", "claim_all")
+        .add_attribute("owner", info.sender)
+        .add_attribute("count", count.to_string())
+        .add_attribute("rewards", total_rewards)
+        .add_attributes(
+            last.map(|(collection, token_id)| ("last", format!("{collection}/{token_id}"))),
+        ))
+}
+
+pub fn try_setting_badge_collection(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    collection: Option<String>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
 
-        //if exist here, error, already staked
-        if nftstake.staked {
-            return Err(ContractError::AlreadyStakedTokenIDInCollection {});
+    let res = Response::new().add_attribute("method", "set_badge_collection");
+    let collection = match collection {
+        Some(collection) => deps.api.addr_validate(&collection)?,
+        None => {
+            BADGE_COLLECTION.remove(deps.storage);
+            return Ok(res.add_attribute("collection", "none"));
         }
+    };
 
-        //check if number days is valid
-        let  rewardrate = REWARD_RATE.may_load(deps.storage, number_days.clone())?.unwrap_or_default();
+    // badges are minted as the collection's minter, its cw-ownable owner
+    let minter: MinterResponse = deps
+        .querier
+        .query_wasm_smart(&collection, &cw721_base::QueryMsg::<Empty>::Minter {})?;
+    if minter.minter.as_deref() != Some(env.contract.address.as_str()) {
+        return Err(ContractError::NotBadgeMinter {});
+    }
+    BADGE_COLLECTION.save(deps.storage, &collection)?;
+
+    Ok(res.add_attribute("collection", collection))
+}
+
+pub fn try_setting_milestone(
+    deps: DepsMut,
+    info: MessageInfo,
+    id: String,
+    milestone: Option<Milestone>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
 
-        if rewardrate == 0 {
-            return Err(ContractError::InvalidDaysNumber {});
+    let res = Response::new()
+        .add_attribute("method", "set_milestone")
+        .add_attribute("milestone", &id);
+    let milestone = match milestone {
+        Some(milestone) => milestone,
+        None => {
+            MILESTONES.remove(deps.storage, &id);
+            return Ok(res);
         }
+    };
+    let valid = match milestone.condition {
+        MilestoneCondition::StakedDays { days } => days > 0,
+        MilestoneCondition::StakedNfts { count } => !count.is_zero(),
+    };
+    if !valid {
+        return Err(ContractError::InvalidMilestone {});
+    }
+    MILESTONES.save(deps.storage, &id, &milestone)?;
+
+    Ok(res)
+}
+
+pub fn try_claiming_badges(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let badges = mint_badges(deps.storage, &info.sender, env.block.time.seconds())?;
+    if badges.is_empty() {
+        return Err(ContractError::NothingToClaim {});
+    }
+
+    Ok(Response::new()
+        .add_attribute("method", "claim_badges")
+        .add_attribute("owner", &info.sender)
+        .add_attribute("count", badges.len().to_string())
+        .add_submessages(badges))
+}
+
+/// Mints a badge for every milestone `owner` has reached and not been awarded yet. A badge
+/// is recorded by the reply to its mint, a failing mint is skipped and can be retried.
+fn mint_badges(storage: &mut dyn Storage, owner: &Addr, now: u64) -> StdResult<Vec<SubMsg>> {
+    let collection = match BADGE_COLLECTION.may_load(storage)? {
+        Some(collection) => collection,
+        None => return Ok(vec![]),
+    };
+    let milestones = MILESTONES
+        .range(storage, None, None, Order::Ascending)
+        .filter(|item| match item {
+            Ok((id, _)) => !BADGES.has(storage, (owner, id)),
+            Err(_) => true,
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    if milestones.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let staked_nfts = STAKED_NFTS.may_load(storage, owner)?.unwrap_or_default();
+    let first_stake_time = nft_stakes()
+        .idx
+        .owner
+        .prefix(owner.clone())
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, stake)| stake.stake_time))
+        .collect::<StdResult<Vec<_>>>()?
+        .into_iter()
+        .min();
+    let staked_days = first_stake_time.map_or(0, |stake_time| (now - stake_time) / DAY_IN_SECONDS);
+
+    let collection: Cw721Collection = Cw721Contract(collection, PhantomData, PhantomData);
+    let mut msgs = vec![];
+    let mut pending = PENDING_BADGES.may_load(storage)?.unwrap_or_default();
+    for (id, milestone) in milestones {
+        let reached = match milestone.condition {
+            MilestoneCondition::StakedDays { days } => staked_days >= days,
+            MilestoneCondition::StakedNfts { count } => staked_nfts >= count,
+        };
+        if !reached {
+            continue;
+        }
+        let (token_uri, extension) = milestone.badge(&id, owner);
+        let msg = collection.call(cw721_base::ExecuteMsg::Mint {
+            token_id: format!("{}-{}", id, owner),
+            owner: owner.to_string(),
+            token_uri,
+            extension,
+        })?;
+        msgs.push(SubMsg::reply_always(msg, BADGE_REPLY_ID));
+        pending.push((owner.clone(), id));
+    }
+    PENDING_BADGES.save(storage, &pending)?;
+    Ok(msgs)
+}
+
+/// Records the badge the reply is for, in the order the mints were sent, or skips it if
+/// its mint failed
+pub fn try_recording_badge(deps: DepsMut, msg: Reply) -> Result<Response, ContractError> {
+    let mut pending = PENDING_BADGES.load(deps.storage)?;
+    let (owner, id) = pending.remove(0);
+    PENDING_BADGES.save(deps.storage, &pending)?;
+    let token_id = format!("{}-{}", id, owner);
+
+    let res = Response::new()
+        .add_attribute("owner", &owner)
+        .add_attribute("milestone", &id);
+    match msg.result {
+        SubMsgResult::Ok(_) => {
+            BADGES.save(deps.storage, (&owner, &id), &token_id)?;
+            Ok(res
+                .add_attribute("method", "record_badge")
+                .add_attribute("token_id", token_id))
+        }
+        SubMsgResult::Err(error) => Ok(res
+            .add_attribute("method", "skip_badge")
+            .add_attribute("error", error)),
+    }
+}
 
-        nftstake.owner = info.sender;
-        nftstake.stake_days = number_days;
-        nftstake.token_id = token_id;
-        nftstake.staked = true;
-        nftstake.collection = collection_addr.clone();
+/// A rejected `SetUser` or `RemoveUser` does not fail staking or unstaking, the NFT is
+/// simply staked or returned without changing its user
+pub fn try_skipping_user(msg: Reply) -> Result<Response, ContractError> {
+    let action = match msg.id {
+        SET_USER_REPLY_ID => "set_user",
+        REMOVE_USER_REPLY_ID => "remove_user",
+        id => return Err(ContractError::UnknownReplyId { id }),
+    };
+    let error = match msg.result {
+        SubMsgResult::Err(error) => error,
+        SubMsgResult::Ok(_) => String::new(),
+    };
 
-        NFT_STAKES.save(deps.storage, (token_id, &collection_addr), nftstake)?;
-        
-        Ok(Response::new()
-            .add_attribute("method", "adding reward rate")
-            .add_attribute("number_days", number_days.to_string()) 
-            .add_attribute("rate", rate.to_string()) 
-            .add_attribute("collection", collection_addr.to_string()) 
-        )
- 
-    } 
\ No newline at end of file
+    Ok(Response::new()
+        .add_attribute("method", "skip_user")
+        .add_attribute("action", action)
+        .add_attribute("error", error))
+}
+
+/// Settles the pending rewards of a stake, returning the amount to pay out and the amount
+/// forfeited. The `forfeit_ratio` share of the pending rewards is not paid out and stays in
+/// the pool. `state` must be accrued up to `now`, the caller saves the updated stake and
+/// pays the rewards.
+fn settle_rewards(
+    config: &Config,
+    state: &State,
+    stake: &mut StakeInfo,
+    now: u64,
+    forfeit_ratio: Decimal,
+) -> (Uint128, Uint128) {
+    let pending = stake.pending_rewards(&config.reward_mode, state.reward_per_weight, now);
+    let forfeited = pending * forfeit_ratio;
+    let rewards = pending - forfeited;
+    stake.last_claim_time = now;
+    stake.reward_debt = state.reward_per_weight;
+    stake.total_earned_tokens += rewards;
+    (rewards, forfeited)
+}
+
+/// Takes `rewards` out of the pool, returning the transfer to `recipient` in the reward
+/// asset. The caller saves the state.
+fn pay_rewards(
+    config: &Config,
+    state: &mut State,
+    recipient: &Addr,
+    rewards: Uint128,
+) -> Result<Option<CosmosMsg>, ContractError> {
+    if rewards > state.funded_tokens {
+        return Err(ContractError::InsufficientRewardFunds {
+            rewards,
+            funded_tokens: state.funded_tokens,
+        });
+    }
+    if rewards.is_zero() {
+        return Ok(None);
+    }
+    state.funded_tokens -= rewards;
+    state.total_claims += rewards;
+
+    let transfer = config.reward_asset.transfer_msg(recipient, rewards)?;
+    Ok(Some(transfer))
+}
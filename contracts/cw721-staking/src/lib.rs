@@ -1,62 +1,64 @@
 pub mod error;
-mod execute;
-pub mod helpers;
+pub mod execute;
 pub mod msg;
-mod query;
+pub mod query;
 pub mod state;
-pub mod upgrades;
 
-#[cfg(test)]
-mod contract_tests;
 #[cfg(test)]
 mod multi_tests;
 
 pub use crate::error::ContractError;
-pub use crate::msg::{ExecuteMsg, InstantiateMsg, MinterResponse, QueryMsg};
-pub use crate::state::Cw721Contract;
-
-// These types are re-exported so that contracts interacting with this
-// one don't need a direct dependency on cw_ownable to use the API.
-//
-// `Action` is used in `ExecuteMsg::UpdateOwnership`, `Ownership` is
-// used in `QueryMsg::Ownership`, and `OwnershipError` is used in
-// `ContractError::Ownership`.
-pub use cw_ownable::{Action, Ownership, OwnershipError};
-
-use cosmwasm_std::Empty;
-
-// This is a simple type to let us handle empty extensions
-pub type Extension = Option<Empty>;
 
 // Version info for migration
-pub const CONTRACT_NAME: &str = "crates.io:cw721-base";
+pub const CONTRACT_NAME: &str = "crates.io:cw721-staking";
 pub const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
-// currently we only support migrating from 0.16.0. this is ok for now because
-// we have not released any 0.16.x where x != 0
-//
-// TODO: parse semvar so that any version 0.16.x can be migrated from
-pub const EXPECTED_FROM_VERSION: &str = "0.16.0";
-
 pub mod entry {
     use super::*;
 
     #[cfg(not(feature = "library"))]
     use cosmwasm_std::entry_point;
-    use cosmwasm_std::{Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
+    use cosmwasm_std::{
+        to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdResult,
+    };
+
+    use crate::execute::{
+        try_adding_reward_rate, try_claim, try_claim_all, try_claiming_badges, try_claiming_unpaid,
+        try_emergency_unstake, try_emergency_withdraw, try_entering_emergency, try_fund,
+        try_receive_cw20, try_receive_nft, try_recording_badge, try_setting_badge_collection,
+        try_setting_collection_multiplier, try_setting_early_exit_policy, try_setting_emission,
+        try_setting_milestone, try_setting_trait_multiplier, try_skipping_user, try_unstake,
+        try_unstake_many, try_update_config, try_whitelisting_collection, try_withdraw_unbonded,
+        BADGE_REPLY_ID,
+    };
+    use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+    use crate::state::{Config, RewardMode, State, CONFIG, DAO, STATE};
 
-    // This makes a conscious choice on the various generics used by the contract
     #[cfg_attr(not(feature = "library"), entry_point)]
     pub fn instantiate(
         deps: DepsMut,
-        env: Env,
+        _env: Env,
         info: MessageInfo,
         msg: InstantiateMsg,
     ) -> Result<Response, ContractError> {
         cw2::set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
-        let tract = Cw721Contract::<Extension, Empty, Empty, Empty>::default();
-        tract.instantiate(deps, env, info, msg)
+        let reward_mode = msg.reward_mode.unwrap_or(RewardMode::FixedRate);
+        reward_mode.validate()?;
+        let config = Config {
+            owner: deps.api.addr_validate(&msg.owner)?,
+            reward_asset: msg.reward_asset.check(deps.api)?,
+            reward_mode,
+            is_staking_allowed: msg.is_staking_allowed,
+            unbonding_period: msg.unbonding_period.unwrap_or_default(),
+        };
+        CONFIG.save(deps.storage, &config)?;
+        STATE.save(deps.storage, &State::default())?;
+        DAO.save(deps.storage, &info.sender)?;
+
+        Ok(Response::new()
+            .add_attribute("method", "instantiate")
+            .add_attribute("sender", info.sender))
     }
 
     #[cfg_attr(not(feature = "library"), entry_point)]
@@ -64,94 +66,154 @@ pub mod entry {
         deps: DepsMut,
         env: Env,
         info: MessageInfo,
-        msg: ExecuteMsg<Extension, Empty>,
+        msg: ExecuteMsg,
     ) -> Result<Response, ContractError> {
-        let tract = Cw721Contract::<Extension, Empty, Empty, Empty>::default();
-        tract.execute(deps, env, info, msg)
+        match msg {
+            ExecuteMsg::ReceiveNft(receive_msg) => try_receive_nft(deps, env, info, receive_msg),
+            ExecuteMsg::Receive(receive_msg) => try_receive_cw20(deps, info, receive_msg),
+            ExecuteMsg::Fund {} => try_fund(deps, info),
+            ExecuteMsg::Claim {
+                collection,
+                token_id,
+            } => try_claim(deps, env, info, collection, token_id),
+            ExecuteMsg::ClaimAll {
+                collection,
+                start_after,
+                limit,
+            } => try_claim_all(deps, env, info, collection, start_after, limit),
+            ExecuteMsg::ClaimUnpaid {} => try_claiming_unpaid(deps, info),
+            ExecuteMsg::UnstakeMany { tokens } => try_unstake_many(deps, env, info, tokens),
+            ExecuteMsg::Unstake {
+                collection,
+                token_id,
+            } => try_unstake(deps, env, info, collection, token_id),
+            ExecuteMsg::WithdrawUnbonded { tokens } => {
+                try_withdraw_unbonded(deps, env, info, tokens)
+            }
+            ExecuteMsg::EnterEmergency {} => try_entering_emergency(deps, env, info),
+            ExecuteMsg::EmergencyUnstake { tokens } => {
+                try_emergency_unstake(deps, env, info, tokens)
+            }
+            ExecuteMsg::EmergencyWithdraw { recipient } => {
+                try_emergency_withdraw(deps, env, info, recipient)
+            }
+            ExecuteMsg::WhitelistCollection {
+                collection_addr,
+                boolean,
+            } => try_whitelisting_collection(deps, info, collection_addr, boolean),
+            ExecuteMsg::AddRewardRate { number_days, rate } => {
+                try_adding_reward_rate(deps, info, number_days, rate)
+            }
+            ExecuteMsg::SetEmission { rate, start, end } => {
+                try_setting_emission(deps, env, info, rate, start, end)
+            }
+            ExecuteMsg::SetCollectionMultiplier {
+                collection,
+                multiplier,
+            } => try_setting_collection_multiplier(deps, info, collection, multiplier),
+            ExecuteMsg::SetTraitMultiplier {
+                collection,
+                trait_type,
+                value,
+                multiplier,
+            } => {
+                try_setting_trait_multiplier(deps, info, collection, trait_type, value, multiplier)
+            }
+            ExecuteMsg::SetEarlyExitPolicy {
+                number_days,
+                policy,
+            } => try_setting_early_exit_policy(deps, info, number_days, policy),
+            ExecuteMsg::SetBadgeCollection { collection } => {
+                try_setting_badge_collection(deps, env, info, collection)
+            }
+            ExecuteMsg::SetMilestone { id, milestone } => {
+                try_setting_milestone(deps, info, id, milestone)
+            }
+            ExecuteMsg::ClaimBadges {} => try_claiming_badges(deps, env, info),
+            ExecuteMsg::UpdateConfig {
+                owner,
+                reward_asset,
+                is_staking_allowed,
+                unbonding_period,
+            } => try_update_config(
+                deps,
+                info,
+                owner,
+                reward_asset,
+                is_staking_allowed,
+                unbonding_period,
+            ),
+        }
     }
 
     #[cfg_attr(not(feature = "library"), entry_point)]
-    pub fn query(deps: Deps, env: Env, msg: QueryMsg<Empty>) -> StdResult<Binary> {
-        let tract = Cw721Contract::<Extension, Empty, Empty, Empty>::default();
-        tract.query(deps, env, msg)
+    pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+        match msg.id {
+            BADGE_REPLY_ID => try_recording_badge(deps, msg),
+            _ => try_skipping_user(msg),
+        }
     }
 
     #[cfg_attr(not(feature = "library"), entry_point)]
-    pub fn migrate(deps: DepsMut, _env: Env, _msg: Empty) -> Result<Response, ContractError> {
-        // make sure the correct contract is being upgraded, and it's being
-        // upgraded from the correct version.
-        cw2::assert_contract_version(deps.as_ref().storage, CONTRACT_NAME, EXPECTED_FROM_VERSION)?;
-
-        // update contract version
-        cw2::set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
-
-        // perform the upgrade
-        upgrades::v0_17::migrate::<Extension, Empty, Empty, Empty>(deps)
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-    use cw2::ContractVersion;
-
-    use super::*;
-
-    /// Make sure cw2 version info is properly initialized during instantiation.
-    #[test]
-    fn proper_cw2_initialization() {
-        let mut deps = mock_dependencies();
-
-        entry::instantiate(
-            deps.as_mut(),
-            mock_env(),
-            mock_info("larry", &[]),
-            InstantiateMsg {
-                name: "".into(),
-                symbol: "".into(),
-                minter: Some("other".into()),
-                withdraw_address: None,
-            },
-        )
-        .unwrap();
-
-        let minter = cw_ownable::get_ownership(deps.as_ref().storage)
-            .unwrap()
-            .owner
-            .map(|a| a.into_string());
-        assert_eq!(minter, Some("other".to_string()));
-
-        let version = cw2::get_contract_version(deps.as_ref().storage).unwrap();
-        assert_eq!(
-            version,
-            ContractVersion {
-                contract: CONTRACT_NAME.into(),
-                version: CONTRACT_VERSION.into(),
-            },
-        );
-    }
-
-    #[test]
-    fn proper_owner_initialization() {
-        let mut deps = mock_dependencies();
-
-        entry::instantiate(
-            deps.as_mut(),
-            mock_env(),
-            mock_info("owner", &[]),
-            InstantiateMsg {
-                name: "".into(),
-                symbol: "".into(),
-                minter: None,
-                withdraw_address: None,
-            },
-        )
-        .unwrap();
-
-        let minter = cw_ownable::get_ownership(deps.as_ref().storage)
-            .unwrap()
-            .owner
-            .map(|a| a.into_string());
-        assert_eq!(minter, Some("owner".to_string()));
+    pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+        match msg {
+            QueryMsg::Config {} => to_json_binary(&query::config(deps)?),
+            QueryMsg::State {} => to_json_binary(&query::state(deps)?),
+            QueryMsg::Mode {} => to_json_binary(&query::mode(deps)?),
+            QueryMsg::Multipliers { collection } => {
+                to_json_binary(&query::multipliers(deps, collection)?)
+            }
+            QueryMsg::Milestones {} => to_json_binary(&query::milestones(deps)?),
+            QueryMsg::Badges { owner } => to_json_binary(&query::badges(deps, owner)?),
+            QueryMsg::UnpaidRewards { owner } => {
+                to_json_binary(&query::unpaid_rewards(deps, owner)?)
+            }
+            QueryMsg::EarlyExitPolicy { number_days } => {
+                to_json_binary(&query::early_exit_policy(deps, number_days)?)
+            }
+            QueryMsg::StakeInfo {
+                collection,
+                token_id,
+            } => to_json_binary(&query::stake_info(deps, collection, token_id)?),
+            QueryMsg::StakesByOwner {
+                owner,
+                start_after,
+                limit,
+            } => to_json_binary(&query::stakes_by_owner(deps, owner, start_after, limit)?),
+            QueryMsg::StakesByCollection {
+                collection,
+                start_after,
+                limit,
+            } => to_json_binary(&query::stakes_by_collection(
+                deps,
+                collection,
+                start_after,
+                limit,
+            )?),
+            QueryMsg::Unbonding {
+                owner,
+                start_after,
+                limit,
+            } => to_json_binary(&query::unbonding(deps, owner, start_after, limit)?),
+            QueryMsg::PendingRewards {
+                owner,
+                start_after,
+                limit,
+            } => to_json_binary(&query::pending_rewards(
+                deps,
+                env,
+                owner,
+                start_after,
+                limit,
+            )?),
+            QueryMsg::VotingPowerAtHeight { address, height } => {
+                to_json_binary(&query::voting_power_at_height(deps, env, address, height)?)
+            }
+            QueryMsg::TotalPowerAtHeight { height } => {
+                to_json_binary(&query::total_power_at_height(deps, env, height)?)
+            }
+            QueryMsg::Dao {} => to_json_binary(&query::dao(deps)?),
+            QueryMsg::Info {} => to_json_binary(&query::info(deps)?),
+        }
     }
 }
@@ -1,200 +1,319 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Binary, Coin};
-use cw721::Expiration;
-use cw_ownable::{cw_ownable_execute, cw_ownable_query};
-use schemars::JsonSchema;
+use cosmwasm_std::{Addr, Api, Decimal, StdResult, Uint128};
+use cw2::ContractVersion;
+use cw20::Cw20ReceiveMsg;
+use cw721::Cw721ReceiveMsg;
+
+use crate::state::{
+    Config, EarlyExitPolicy, Milestone, Mode, RewardAsset, RewardMode, StakeInfo, State, Unbonding,
+};
 
 #[cw_serde]
 pub struct InstantiateMsg {
-    /// Name of the NFT contract
-    pub name: String,
-    /// Symbol of the NFT contract
-    pub symbol: String,
-
-    /// The minter is the only one who can create new NFTs.
-    /// This is designed for a base NFT that is controlled by an external program
-    /// or contract. You will likely replace this with custom logic in custom NFTs
-    pub minter: Option<String>,
-
-    pub withdraw_address: Option<String>,
-}
-
-/// This is like Cw721ExecuteMsg but we add a Mint command for an owner
-/// to make this stand-alone. You will likely want to remove mint and
-/// use other control logic in any contract that inherits this.
-#[cw_ownable_execute]
-#[cw_serde]
-pub enum ExecuteMsg<T, E> {
-    /// Transfer is a base message to move a token to another account without triggering actions
-    TransferNft { recipient: String, token_id: String },
-    /// Send is a base message to transfer a token to a contract and trigger an action
-    /// on the receiving contract.
-    SendNft {
-        contract: String,
-        token_id: String,
-        msg: Binary,
-    },
-    SetUser {
-        token_id: String,
-        user: String,
-        expires: Option<Expiration>,
+    pub owner: String,
+    /// Asset rewards are paid in
+    pub reward_asset: UncheckedRewardAsset,
+    /// Defaults to `RewardMode::FixedRate`
+    pub reward_mode: Option<RewardMode>,
+    pub is_staking_allowed: bool,
+    /// Seconds unstaked NFTs wait before they can be withdrawn, none by default
+    pub unbonding_period: Option<u64>,
+}
+
+/// `RewardAsset` with an unvalidated cw20 address
+#[cw_serde]
+pub enum UncheckedRewardAsset {
+    Cw20 { address: String },
+    Native { denom: String },
+}
+
+impl UncheckedRewardAsset {
+    pub fn check(self, api: &dyn Api) -> StdResult<RewardAsset> {
+        let asset = match self {
+            UncheckedRewardAsset::Cw20 { address } => RewardAsset::Cw20 {
+                address: api.addr_validate(&address)?,
             },
-    /// Allows operator to transfer / send the token from the owner's account.
-    /// If expiration is set, then this allowance has a time/height limit
-    Approve {
-        spender: String,
+            UncheckedRewardAsset::Native { denom } => RewardAsset::Native { denom },
+        };
+        Ok(asset)
+    }
+}
+
+#[cw_serde]
+pub enum ExecuteMsg {
+    /// Stake an NFT. Sent by a whitelisted collection on `SendNft`, with a
+    /// `ReceiveMsg` as the inner message.
+    ReceiveNft(Cw721ReceiveMsg),
+    /// Fund a cw20 reward pool. Sent by the reward token on `Send`, with a
+    /// `Cw20HookMsg` as the inner message. Anyone can fund the pool.
+    Receive(Cw20ReceiveMsg),
+    /// Fund a native reward pool with the reward denom attached. Anyone can fund the pool.
+    Fund {},
+    /// Pay out the rewards a staked NFT earned since the last claim
+    Claim {
+        collection: String,
         token_id: String,
-        expires: Option<Expiration>,
     },
-    /// Remove previously granted Approval
-    Revoke { spender: String, token_id: String },
-    /// Allows operator to transfer / send any token from the owner's account.
-    /// If expiration is set, then this allowance has a time/height limit
-    ApproveAll {
-        operator: String,
-        expires: Option<Expiration>,
+    /// Pay out the rewards of up to `limit` stakes of the sender, optionally only of one
+    /// collection, in one transfer. Stakes go in the order of `StakesByOwner`, `start_after`
+    /// is the (collection, token_id) of the last stake looked at by the previous call.
+    ClaimAll {
+        collection: Option<String>,
+        start_after: Option<(String, String)>,
+        limit: Option<u32>,
     },
-    /// Remove previously granted ApproveAll permission
-    RevokeAll { operator: String },
-
-    /// Mint a new NFT, can only be called by the contract minter
-    Mint {
-        /// Unique ID of the NFT
+    /// Pay out the rewards unstaking left owed to the sender, as far as the pool covers them
+    ClaimUnpaid {},
+    /// Return a staked NFT to its owner, paying out its rewards. Before the lock period is
+    /// over this needs an early exit policy for the lock period, and the fee attached if the
+    /// policy charges one. With an unbonding period the NFT stops earning and is returned
+    /// by `WithdrawUnbonded` once the period is over.
+    Unstake {
+        collection: String,
         token_id: String,
-        /// The owner of the newly minter NFT
+    },
+    /// `Unstake` several (collection, token_id) pairs at once, paying out all their rewards
+    /// in one transfer. Early exit fees of all NFTs must be attached together.
+    UnstakeMany { tokens: Vec<(String, String)> },
+    /// Return unstaked (collection, token_id) pairs whose unbonding period is over
+    WithdrawUnbonded { tokens: Vec<(String, String)> },
+    /// Switch to emergency mode for good. Only owner can call this.
+    EnterEmergency {},
+    /// Take staked (collection, token_id) pairs back without rewards, in emergency mode
+    EmergencyUnstake { tokens: Vec<(String, String)> },
+    /// Withdraw what is left in the reward pool to `recipient`, the owner by default.
+    /// Only owner can call this, in emergency mode once the timelock is over.
+    EmergencyWithdraw { recipient: Option<String> },
+    /// Allow or disallow staking NFTs of a collection. Only owner can call this.
+    WhitelistCollection {
+        collection_addr: String,
+        boolean: bool,
+    },
+    /// Set the reward rate for a lock period. Only owner can call this.
+    AddRewardRate { number_days: u64, rate: u64 },
+    /// Change the emission schedule from now on, emission accrued so far is kept.
+    /// Only owner can call this, in `RewardMode::Emission`.
+    SetEmission { rate: Uint128, start: u64, end: u64 },
+    /// Set or, with `None`, remove the reward multiplier of a collection.
+    /// Only owner can call this.
+    SetCollectionMultiplier {
+        collection: String,
+        multiplier: Option<Decimal>,
+    },
+    /// Set or, with `None`, remove the reward multiplier of the NFTs of a collection
+    /// with a metadata trait. Only owner can call this.
+    SetTraitMultiplier {
+        collection: String,
+        trait_type: String,
+        value: String,
+        multiplier: Option<Decimal>,
+    },
+    /// Set or, with `None`, remove the early exit policy of a lock period.
+    /// Only owner can call this.
+    SetEarlyExitPolicy {
+        number_days: u64,
+        policy: Option<EarlyExitPolicy>,
+    },
+    /// Set or, with `None`, remove the collection badges are minted from. This contract must
+    /// be its minter. Only owner can call this.
+    SetBadgeCollection { collection: Option<String> },
+    /// Set or, with `None`, remove a milestone stakers earn a badge for.
+    /// Only owner can call this.
+    SetMilestone {
+        id: String,
+        milestone: Option<Milestone>,
+    },
+    /// Mint badges for the milestones the sender has reached. Staking, claiming and
+    /// unstaking also do this.
+    ClaimBadges {},
+    /// Only owner can call this. The reward asset can only change while the pool is empty.
+    UpdateConfig {
         owner: String,
-        /// Universal resource identifier for this NFT
-        /// Should point to a JSON file that conforms to the ERC721
-        /// Metadata JSON Schema
-        token_uri: Option<String>,
-        /// Any custom extension used by this contract
-        extension: T,
+        reward_asset: UncheckedRewardAsset,
+        is_staking_allowed: bool,
+        /// Applies to NFTs unstaked from now on
+        unbonding_period: u64,
     },
+}
 
-    /// Burn an NFT the sender has access to
-    Burn { token_id: String },
-
-    /// Extension msg
-    Extension { msg: E },
+/// Message carried in `Cw721ReceiveMsg::msg` when sending an NFT to this contract
+#[cw_serde]
+pub enum ReceiveMsg {
+    /// Lock the NFT for `number_days`, which must have a reward rate
+    Stake { number_days: u64 },
+}
 
-    /// Sets address to send withdrawn fees to. Only owner can call this.
-    SetWithdrawAddress { address: String },
-    /// Removes the withdraw address, so fees are sent to the contract. Only owner can call this.
-    RemoveWithdrawAddress {},
-    /// Withdraw from the contract to the given address. Anyone can call this,
-    /// which is okay since withdraw address has been set by owner.
-    WithdrawFunds { amount: Coin },
+/// Message carried in `Cw20ReceiveMsg::msg` when sending reward tokens to this contract
+#[cw_serde]
+pub enum Cw20HookMsg {
+    /// Add the tokens to the reward pool
+    Fund {},
 }
 
-#[cw_ownable_query]
 #[cw_serde]
 #[derive(QueryResponses)]
-pub enum QueryMsg<Q: JsonSchema> {
-    /// Return the owner of the given token, error if token does not exist
-    #[returns(cw721::OwnerOfResponse)]
-    OwnerOf {
-        token_id: String,
-        /// unset or false will filter out expired approvals, you must set to true to see them
-        include_expired: Option<bool>,
-    },
-    /// Return user that can be considered the renter of the token at the given ID.
-    /// Return type: `UserOfResponse`
-    #[returns(cw721::UserOfResponse)]
-    UserOf {
-        token_id: String,
-        
-    },
-    /// Return operator that can access all of the owner's tokens.
-    #[returns(cw721::ApprovalResponse)]
-    Approval {
-        token_id: String,
-        spender: String,
-        include_expired: Option<bool>,
-    },
-    /// Return approvals that a token has
-    #[returns(cw721::ApprovalsResponse)]
-    Approvals {
+pub enum QueryMsg {
+    #[returns(Config)]
+    Config {},
+    #[returns(State)]
+    State {},
+    /// Normal or emergency mode
+    #[returns(ModeResponse)]
+    Mode {},
+    /// Reward multipliers of a collection
+    #[returns(MultipliersResponse)]
+    Multipliers { collection: String },
+    /// Badge collection and milestones
+    #[returns(MilestonesResponse)]
+    Milestones {},
+    /// Badges minted for an owner
+    #[returns(BadgesResponse)]
+    Badges { owner: String },
+    /// Rewards owed to an owner that unstaking could not pay out
+    #[returns(Uint128)]
+    UnpaidRewards { owner: String },
+    /// Early exit policy of a lock period, if any
+    #[returns(Option<EarlyExitPolicy>)]
+    EarlyExitPolicy { number_days: u64 },
+    /// The stake of an NFT
+    #[returns(StakeInfo)]
+    StakeInfo {
+        collection: String,
         token_id: String,
-        include_expired: Option<bool>,
     },
-    /// Return approval of a given operator for all tokens of an owner, error if not set
-    #[returns(cw721::OperatorResponse)]
-    Operator {
+    /// Stakes of an owner across collections. `start_after` is the
+    /// (collection, token_id) of the last stake of the previous page.
+    #[returns(StakesResponse)]
+    StakesByOwner {
         owner: String,
-        operator: String,
-        include_expired: Option<bool>,
+        start_after: Option<(String, String)>,
+        limit: Option<u32>,
     },
-    /// List all operators that can access all of the owner's tokens
-    #[returns(cw721::OperatorsResponse)]
-    AllOperators {
-        owner: String,
-        /// unset or false will filter out expired items, you must set to true to see them
-        include_expired: Option<bool>,
+    /// Stakes of a collection. `start_after` is the token_id of the last stake
+    /// of the previous page.
+    #[returns(StakesResponse)]
+    StakesByCollection {
+        collection: String,
         start_after: Option<String>,
         limit: Option<u32>,
     },
-    /// Total number of tokens issued
-    #[returns(cw721::NumTokensResponse)]
-    NumTokens {},
-
-    /// With MetaData Extension.
-    /// Returns top-level metadata about the contract
-    #[returns(cw721::ContractInfoResponse)]
-    ContractInfo {},
-    /// With MetaData Extension.
-    /// Returns metadata about one particular token, based on *ERC721 Metadata JSON Schema*
-    /// but directly from the contract
-    #[returns(cw721::NftInfoResponse<Q>)]
-    NftInfo { token_id: String },
-    /// With MetaData Extension.
-    /// Returns the result of both `NftInfo` and `OwnerOf` as one query as an optimization
-    /// for clients
-    #[returns(cw721::AllNftInfoResponse<Q>)]
-    AllNftInfo {
-        token_id: String,
-        /// unset or false will filter out expired approvals, you must set to true to see them
-        include_expired: Option<bool>,
-    },
-
-    /// With Enumerable extension.
-    /// Returns all tokens owned by the given address, [] if unset.
-    #[returns(cw721::TokensResponse)]
-    Tokens {
+    /// NFTs of an owner in their unbonding period. `start_after` is the (collection, token_id)
+    /// of the last NFT of the previous page.
+    #[returns(UnbondingResponse)]
+    Unbonding {
         owner: String,
-        start_after: Option<String>,
+        start_after: Option<(String, String)>,
         limit: Option<u32>,
     },
-    /// With Enumerable extension.
-    /// Requires pagination. Lists all token_ids controlled by the contract.
-    #[returns(cw721::TokensResponse)]
-    AllTokens {
-        start_after: Option<String>,
+    /// Rewards the stakes of an owner could claim at the current block. `start_after` is the
+    /// (collection, token_id) of the last stake of the previous page.
+    #[returns(PendingRewardsResponse)]
+    PendingRewards {
+        owner: String,
+        start_after: Option<(String, String)>,
         limit: Option<u32>,
     },
+    /// Number of NFTs staked by an address at the start of block `height`, the current block
+    /// by default
+    #[returns(VotingPowerAtHeightResponse)]
+    VotingPowerAtHeight {
+        address: String,
+        height: Option<u64>,
+    },
+    /// Number of NFTs staked by everyone at the start of block `height`, the current block
+    /// by default
+    #[returns(TotalPowerAtHeightResponse)]
+    TotalPowerAtHeight { height: Option<u64> },
+    /// The DAO this contract is a voting module of, the address that instantiated it
+    #[returns(Addr)]
+    Dao {},
+    /// Name and version of this contract
+    #[returns(InfoResponse)]
+    Info {},
+}
 
-    /// Return the minter
-    #[returns(MinterResponse)]
-    Minter {},
+#[cw_serde]
+pub struct TraitMultiplier {
+    pub trait_type: String,
+    pub value: String,
+    pub multiplier: Decimal,
+}
 
-    /// Extension query
-    #[returns(())]
-    Extension { msg: Q },
+#[cw_serde]
+pub struct MultipliersResponse {
+    /// Applies to every NFT of the collection
+    pub collection: Decimal,
+    /// The highest one matching an NFT's metadata applies on top of the collection multiplier
+    pub traits: Vec<TraitMultiplier>,
+}
 
-    #[returns(Option<String>)]
-    GetWithdrawAddress {},
+#[cw_serde]
+pub struct MilestoneInfo {
+    pub id: String,
+    pub milestone: Milestone,
 }
 
-/// Shows who can mint these tokens
 #[cw_serde]
-pub struct MinterResponse {
-    pub minter: Option<String>,
+pub struct MilestonesResponse {
+    pub collection: Option<Addr>,
+    pub milestones: Vec<MilestoneInfo>,
 }
 
+#[cw_serde]
+pub struct Badge {
+    pub milestone: String,
+    pub token_id: String,
+}
 
-// #[cw_serde]
-// pub struct UserOfResponse {
-//     /// user of the token
-//     pub user: String,
-//     /// represents a point in time when some event happens, in this case, user seizes to be user
-//     pub expires: Expiration,
-// }
+#[cw_serde]
+pub struct BadgesResponse {
+    pub badges: Vec<Badge>,
+}
+
+#[cw_serde]
+pub struct StakesResponse {
+    pub stakes: Vec<StakeInfo>,
+}
+
+#[cw_serde]
+pub struct UnbondingResponse {
+    pub nfts: Vec<Unbonding>,
+}
+
+#[cw_serde]
+pub struct VotingPowerAtHeightResponse {
+    pub power: Uint128,
+    pub height: u64,
+}
+
+#[cw_serde]
+pub struct TotalPowerAtHeightResponse {
+    pub power: Uint128,
+    pub height: u64,
+}
+
+#[cw_serde]
+pub struct InfoResponse {
+    pub info: ContractVersion,
+}
+
+#[cw_serde]
+pub struct PendingReward {
+    pub collection: Addr,
+    pub token_id: String,
+    pub rewards: Uint128,
+}
+
+#[cw_serde]
+pub struct PendingRewardsResponse {
+    /// Sum of the rewards of the stakes of this page
+    pub total: Uint128,
+    pub stakes: Vec<PendingReward>,
+}
+
+#[cw_serde]
+pub struct ModeResponse {
+    pub mode: Mode,
+    /// When the owner can withdraw the reward pool, in emergency mode
+    pub withdraw_after: Option<u64>,
+}
@@ -1,168 +1,2146 @@
-use cosmwasm_std::{to_json_binary, Addr, Empty, QuerierWrapper, WasmMsg};
-use cw721::OwnerOfResponse;
-use cw_multi_test::{App, Contract, ContractWrapper, Executor};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    coin, coins, to_json_binary, Addr, Binary, Decimal, Decimal256, Deps, DepsMut, Empty, Env,
+    Event, MessageInfo, Response, StdError, StdResult, Timestamp, Uint128,
+};
+use cw20::{BalanceResponse, Cw20Coin, Cw20ExecuteMsg, Cw20QueryMsg};
+use cw721::{NftInfoResponse, OwnerOfResponse, UserOfResponse};
+use cw_multi_test::{
+    next_block, App, AppBuilder, AppResponse, Contract, ContractWrapper, Executor,
+};
+use cw_utils::{Expiration, PaymentError};
 
-use crate::MinterResponse;
+use crate::msg::{
+    Badge, BadgesResponse, Cw20HookMsg, ExecuteMsg, InfoResponse, InstantiateMsg,
+    MilestonesResponse, ModeResponse, MultipliersResponse, PendingReward, PendingRewardsResponse,
+    QueryMsg, ReceiveMsg, StakesResponse, TotalPowerAtHeightResponse, TraitMultiplier,
+    UnbondingResponse, UncheckedRewardAsset, VotingPowerAtHeightResponse,
+};
+use crate::state::{
+    BadgeMetadata, BadgeTrait, EarlyExitPolicy, Milestone, MilestoneCondition, Mode, RewardMode,
+    StakeInfo, State, Unbonding, DAY_IN_SECONDS, EMERGENCY_WITHDRAW_DELAY,
+};
+use crate::{ContractError, CONTRACT_NAME, CONTRACT_VERSION};
 
-fn cw721_base_latest_contract() -> Box<dyn Contract<Empty>> {
+const OWNER: &str = "owner";
+const STAKER: &str = "staker";
+const DENOM: &str = "ureward";
+
+type Cw721ExecuteMsg = cw721_base::ExecuteMsg<Empty, Empty>;
+type Cw721QueryMsg = cw721_base::QueryMsg<Empty>;
+
+fn cw721_base_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        cw721_base::entry::execute,
+        cw721_base::entry::instantiate,
+        cw721_base::entry::query,
+    );
+    Box::new(contract)
+}
+
+#[cw_serde]
+struct Trait {
+    display_type: Option<String>,
+    trait_type: String,
+    value: String,
+}
+
+#[cw_serde]
+struct Metadata {
+    name: Option<String>,
+    attributes: Option<Vec<Trait>>,
+}
+
+type MetadataContract<'a> = cw721_base::Cw721Contract<'a, Option<Metadata>, Empty, Empty, Empty>;
+
+fn metadata_instantiate(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: cw721_base::InstantiateMsg,
+) -> Result<Response, cw721_base::ContractError> {
+    MetadataContract::default().instantiate(deps, env, info, msg)
+}
+
+fn metadata_execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: cw721_base::ExecuteMsg<Option<Metadata>, Empty>,
+) -> Result<Response, cw721_base::ContractError> {
+    MetadataContract::default().execute(deps, env, info, msg)
+}
+
+fn metadata_query(deps: Deps, env: Env, msg: Cw721QueryMsg) -> StdResult<Binary> {
+    MetadataContract::default().query(deps, env, msg)
+}
+
+/// cw721-base with OpenSea style metadata, like cw2981-royalties
+fn cw721_metadata_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(metadata_execute, metadata_instantiate, metadata_query);
+    Box::new(contract)
+}
+
+fn cw20_base_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        cw20_base::contract::execute,
+        cw20_base::contract::instantiate,
+        cw20_base::contract::query,
+    );
+    Box::new(contract)
+}
+
+/// cw721-base that rejects `SetUser`, like a collection without users
+fn no_users_execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: cw721_base::ExecuteMsg<cw721_base::Extension, Empty>,
+) -> Result<Response, cw721_base::ContractError> {
+    match msg {
+        cw721_base::ExecuteMsg::SetUser { .. } => Err(cw721_base::ContractError::Std(
+            StdError::generic_err("unknown variant `set_user`"),
+        )),
+        msg => cw721_base::entry::execute(deps, env, info, msg),
+    }
+}
+
+fn cw721_no_users_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        no_users_execute,
+        cw721_base::entry::instantiate,
+        cw721_base::entry::query,
+    );
+    Box::new(contract)
+}
+
+/// cw721-base that rejects `Mint`, like a paused collection
+fn no_mint_execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: cw721_base::ExecuteMsg<cw721_base::Extension, Empty>,
+) -> Result<Response, cw721_base::ContractError> {
+    match msg {
+        cw721_base::ExecuteMsg::Mint { .. } => Err(cw721_base::ContractError::Std(
+            StdError::generic_err("minting is paused"),
+        )),
+        msg => cw721_base::entry::execute(deps, env, info, msg),
+    }
+}
+
+fn cw721_no_mint_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        no_mint_execute,
+        cw721_base::entry::instantiate,
+        cw721_base::entry::query,
+    );
+    Box::new(contract)
+}
+
+fn staking_contract() -> Box<dyn Contract<Empty>> {
     let contract = ContractWrapper::new(
         crate::entry::execute,
         crate::entry::instantiate,
         crate::entry::query,
     )
-    .with_migrate(crate::entry::migrate);
+    .with_reply(crate::entry::reply);
     Box::new(contract)
 }
 
-fn cw721_base_016_contract() -> Box<dyn Contract<Empty>> {
-    use cw721_base_016 as v16;
-    let contract = ContractWrapper::new(
-        v16::entry::execute,
-        v16::entry::instantiate,
-        v16::entry::query,
-    );
-    Box::new(contract)
+/// Instantiates a collection with token "1" owned by STAKER
+fn instantiate_collection(app: &mut App, code_id: u64) -> Addr {
+    let cw721 = instantiate_empty_collection(app, code_id);
+    mint(app, &cw721, "1");
+    cw721
 }
 
-fn query_owner(querier: QuerierWrapper, cw721: &Addr, token_id: String) -> Addr {
-    let resp: OwnerOfResponse = querier
-        .query_wasm_smart(
-            cw721,
-            &crate::QueryMsg::<Empty>::OwnerOf {
-                token_id,
-                include_expired: None,
+fn instantiate_empty_collection(app: &mut App, code_id: u64) -> Addr {
+    app.instantiate_contract(
+        code_id,
+        Addr::unchecked(OWNER),
+        &cw721_base::InstantiateMsg {
+            name: "collection".to_string(),
+            symbol: "symbol".to_string(),
+            minter: None,
+            withdraw_address: None,
+            eviction_policy: None,
+            rental_config: None,
+            max_history: None,
+            base_uri: None,
+        },
+        &[],
+        "cw721-base",
+        None,
+    )
+    .unwrap()
+}
+
+/// Instantiates a cw20 reward token with a balance of 1_000_000 for OWNER
+fn instantiate_cw20(app: &mut App) -> Addr {
+    let cw20_id = app.store_code(cw20_base_contract());
+    app.instantiate_contract(
+        cw20_id,
+        Addr::unchecked(OWNER),
+        &cw20_base::msg::InstantiateMsg {
+            name: "reward".to_string(),
+            symbol: "REWARD".to_string(),
+            decimals: 6,
+            initial_balances: vec![Cw20Coin {
+                address: OWNER.to_string(),
+                amount: Uint128::new(1_000_000),
+            }],
+            mint: None,
+            marketing: None,
+        },
+        &[],
+        "cw20-base",
+        None,
+    )
+    .unwrap()
+}
+
+/// Instantiates a staking contract with a whitelisted `cw721` paying 100 per day for a 30 day lock
+fn instantiate_staking(
+    app: &mut App,
+    cw721: &Addr,
+    reward_asset: UncheckedRewardAsset,
+    reward_mode: Option<RewardMode>,
+) -> Addr {
+    let staking_id = app.store_code(staking_contract());
+    let staking = app
+        .instantiate_contract(
+            staking_id,
+            Addr::unchecked(OWNER),
+            &InstantiateMsg {
+                owner: OWNER.to_string(),
+                reward_asset,
+                reward_mode,
+                is_staking_allowed: true,
+                unbonding_period: None,
             },
+            &[],
+            "cw721-staking",
+            None,
         )
         .unwrap();
-    Addr::unchecked(resp.owner)
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        staking.clone(),
+        &ExecuteMsg::WhitelistCollection {
+            collection_addr: cw721.to_string(),
+            boolean: true,
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        staking.clone(),
+        &ExecuteMsg::AddRewardRate {
+            number_days: 30,
+            rate: 100,
+        },
+        &[],
+    )
+    .unwrap();
+    staking
+}
+
+/// Sets up a collection and a staking contract paying rewards in a cw20 token
+fn setup() -> (App, Addr, Addr, Addr) {
+    let mut app = App::default();
+
+    let cw721_id = app.store_code(cw721_base_contract());
+    let cw721 = instantiate_collection(&mut app, cw721_id);
+    let cw20 = instantiate_cw20(&mut app);
+    let reward_asset = UncheckedRewardAsset::Cw20 {
+        address: cw20.to_string(),
+    };
+    let staking = instantiate_staking(&mut app, &cw721, reward_asset, None);
+
+    (app, cw721, staking, cw20)
+}
+
+fn fund(app: &mut App, cw20: &Addr, staking: &Addr, amount: u128) {
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        cw20.clone(),
+        &Cw20ExecuteMsg::Send {
+            contract: staking.to_string(),
+            amount: Uint128::new(amount),
+            msg: to_json_binary(&Cw20HookMsg::Fund {}).unwrap(),
+        },
+        &[],
+    )
+    .unwrap();
 }
 
-fn mint_transfer_and_burn(app: &mut App, cw721: Addr, sender: Addr, token_id: String) {
+fn mint(app: &mut App, cw721: &Addr, token_id: &str) {
     app.execute_contract(
-        sender.clone(),
+        Addr::unchecked(OWNER),
         cw721.clone(),
-        &crate::ExecuteMsg::<Empty, Empty>::Mint {
-            token_id: token_id.clone(),
-            owner: sender.to_string(),
+        &Cw721ExecuteMsg::Mint {
+            token_id: token_id.to_string(),
+            owner: STAKER.to_string(),
             token_uri: None,
             extension: Empty::default(),
         },
         &[],
     )
     .unwrap();
+}
 
-    let owner = query_owner(app.wrap(), &cw721, token_id.clone());
-    assert_eq!(owner, sender.to_string());
+fn stake(
+    app: &mut App,
+    cw721: &Addr,
+    staking: &Addr,
+    number_days: u64,
+) -> Result<AppResponse, ContractError> {
+    stake_token(app, cw721, staking, "1", number_days)
+}
 
+fn stake_token(
+    app: &mut App,
+    cw721: &Addr,
+    staking: &Addr,
+    token_id: &str,
+    number_days: u64,
+) -> Result<AppResponse, ContractError> {
     app.execute_contract(
-        sender,
+        Addr::unchecked(STAKER),
         cw721.clone(),
-        &crate::ExecuteMsg::<Empty, Empty>::TransferNft {
-            recipient: "burner".to_string(),
-            token_id: token_id.clone(),
+        &Cw721ExecuteMsg::SendNft {
+            contract: staking.to_string(),
+            token_id: token_id.to_string(),
+            msg: to_json_binary(&ReceiveMsg::Stake { number_days }).unwrap(),
         },
         &[],
     )
-    .unwrap();
+    .map_err(|err| err.downcast().unwrap())
+}
 
-    let owner = query_owner(app.wrap(), &cw721, token_id.clone());
-    assert_eq!(owner, "burner".to_string());
+fn unstake(
+    app: &mut App,
+    sender: &str,
+    cw721: &Addr,
+    staking: &Addr,
+) -> Result<AppResponse, ContractError> {
+    app.execute_contract(
+        Addr::unchecked(sender),
+        staking.clone(),
+        &ExecuteMsg::Unstake {
+            collection: cw721.to_string(),
+            token_id: "1".to_string(),
+        },
+        &[],
+    )
+    .map_err(|err| err.downcast().unwrap())
+}
 
+fn set_early_exit_policy(app: &mut App, staking: &Addr, policy: EarlyExitPolicy) {
     app.execute_contract(
-        Addr::unchecked("burner"),
-        cw721,
-        &crate::ExecuteMsg::<Empty, Empty>::Burn { token_id },
+        Addr::unchecked(OWNER),
+        staking.clone(),
+        &ExecuteMsg::SetEarlyExitPolicy {
+            number_days: 30,
+            policy: Some(policy),
+        },
         &[],
     )
     .unwrap();
 }
 
-/// Instantiates a 0.16 version of this contract and tests that tokens
-/// can be minted, transferred, and burnred after migration.
-#[test]
-fn test_migration_016_to_latest() {
-    use cw721_base_016 as v16;
-    let mut app = App::default();
-    let admin = || Addr::unchecked("admin");
+fn claim(app: &mut App, cw721: &Addr, staking: &Addr) -> Result<AppResponse, ContractError> {
+    claim_token(app, cw721, staking, "1")
+}
+
+fn claim_token(
+    app: &mut App,
+    cw721: &Addr,
+    staking: &Addr,
+    token_id: &str,
+) -> Result<AppResponse, ContractError> {
+    app.execute_contract(
+        Addr::unchecked(STAKER),
+        staking.clone(),
+        &ExecuteMsg::Claim {
+            collection: cw721.to_string(),
+            token_id: token_id.to_string(),
+        },
+        &[],
+    )
+    .map_err(|err| err.downcast().unwrap())
+}
 
-    let code_id_016 = app.store_code(cw721_base_016_contract());
-    let code_id_latest = app.store_code(cw721_base_latest_contract());
+fn query_state(app: &App, staking: &Addr) -> State {
+    app.wrap()
+        .query_wasm_smart(staking, &QueryMsg::State {})
+        .unwrap()
+}
 
-    let cw721 = app
-        .instantiate_contract(
-            code_id_016,
-            admin(),
-            &v16::InstantiateMsg {
-                name: "collection".to_string(),
-                symbol: "symbol".to_string(),
-                minter: admin().into_string(),
+fn query_balance(app: &App, cw20: &Addr, address: &str) -> Uint128 {
+    let res: BalanceResponse = app
+        .wrap()
+        .query_wasm_smart(
+            cw20,
+            &Cw20QueryMsg::Balance {
+                address: address.to_string(),
+            },
+        )
+        .unwrap();
+    res.balance
+}
+
+fn query_user(app: &App, cw721: &Addr) -> UserOfResponse {
+    app.wrap()
+        .query_wasm_smart(
+            cw721,
+            &Cw721QueryMsg::UserOf {
+                token_id: "1".to_string(),
+            },
+        )
+        .unwrap()
+}
+
+fn query_owner(app: &App, cw721: &Addr) -> String {
+    let res: OwnerOfResponse = app
+        .wrap()
+        .query_wasm_smart(
+            cw721,
+            &Cw721QueryMsg::OwnerOf {
+                token_id: "1".to_string(),
+                include_expired: None,
+            },
+        )
+        .unwrap();
+    res.owner
+}
+
+/// Voting power of the staker and total voting power at `height`
+fn query_voting_power(app: &App, staking: &Addr, height: Option<u64>) -> (Uint128, Uint128) {
+    let power: VotingPowerAtHeightResponse = app
+        .wrap()
+        .query_wasm_smart(
+            staking,
+            &QueryMsg::VotingPowerAtHeight {
+                address: STAKER.to_string(),
+                height,
             },
+        )
+        .unwrap();
+    let total: TotalPowerAtHeightResponse = app
+        .wrap()
+        .query_wasm_smart(staking, &QueryMsg::TotalPowerAtHeight { height })
+        .unwrap();
+    assert_eq!(power.height, total.height);
+    (power.power, total.power)
+}
+
+#[test]
+fn stake_and_unstake() {
+    let (mut app, cw721, staking, cw20) = setup();
+    fund(&mut app, &cw20, &staking, 10_000);
+
+    // only lock periods with a reward rate can be staked for
+    let err = stake(&mut app, &cw721, &staking, 7).unwrap_err();
+    assert_eq!(err, ContractError::InvalidDaysNumber {});
+    assert_eq!(query_owner(&app, &cw721), STAKER);
+
+    // staking takes custody of the NFT
+    stake(&mut app, &cw721, &staking, 30).unwrap();
+    assert_eq!(query_owner(&app, &cw721), staking.as_str());
+    assert_eq!(query_state(&app, &staking).total_stakes, Uint128::one());
+
+    // the NFT is locked for 30 days, and only the staker can unstake it
+    let unlock_time = app.block_info().time.seconds() + 30 * DAY_IN_SECONDS;
+    let err = unstake(&mut app, STAKER, &cw721, &staking).unwrap_err();
+    assert_eq!(err, ContractError::UnstakeTooEarly { unlock_time });
+    app.update_block(|block| block.time = block.time.plus_seconds(30 * DAY_IN_SECONDS));
+    let err = unstake(&mut app, "random", &cw721, &staking).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // unstaking returns the NFT and pays out the rewards of the 30 days
+    unstake(&mut app, STAKER, &cw721, &staking).unwrap();
+    assert_eq!(query_owner(&app, &cw721), STAKER);
+    assert_eq!(query_balance(&app, &cw20, STAKER), Uint128::new(3_000));
+    let state = query_state(&app, &staking);
+    assert_eq!(state.total_stakes, Uint128::zero());
+    assert_eq!(state.total_claims, Uint128::new(3_000));
+    assert_eq!(state.funded_tokens, Uint128::new(7_000));
+
+    // it can be staked again afterwards
+    stake(&mut app, &cw721, &staking, 30).unwrap();
+    assert_eq!(query_owner(&app, &cw721), staking.as_str());
+}
+
+#[test]
+fn stake_requires_whitelisted_collection() {
+    let (mut app, _, staking, _) = setup();
+    let other_id = app.store_code(cw721_base_contract());
+    let other = instantiate_collection(&mut app, other_id);
+
+    let err = stake(&mut app, &other, &staking, 30).unwrap_err();
+    assert_eq!(err, ContractError::InvalidCollection {});
+
+    // NFTs cannot be pushed in by calling the receive hook directly
+    let err = app
+        .execute_contract(
+            Addr::unchecked(STAKER),
+            staking,
+            &ExecuteMsg::ReceiveNft(cw721::Cw721ReceiveMsg {
+                sender: STAKER.to_string(),
+                token_id: "1".to_string(),
+                msg: to_json_binary(&ReceiveMsg::Stake { number_days: 30 }).unwrap(),
+            }),
             &[],
-            "cw721-base",
-            Some(admin().into_string()),
         )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InvalidCollection {}
+    );
+}
+
+#[test]
+fn stake_requires_staking_allowed() {
+    let (mut app, cw721, staking, cw20) = setup();
+
+    // random cannot change the config
+    let update_msg = ExecuteMsg::UpdateConfig {
+        owner: OWNER.to_string(),
+        reward_asset: UncheckedRewardAsset::Cw20 {
+            address: cw20.to_string(),
+        },
+        is_staking_allowed: false,
+        unbonding_period: 0,
+    };
+    let err = app
+        .execute_contract(Addr::unchecked("random"), staking.clone(), &update_msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    );
+
+    app.execute_contract(Addr::unchecked(OWNER), staking.clone(), &update_msg, &[])
         .unwrap();
+    let err = stake(&mut app, &cw721, &staking, 30).unwrap_err();
+    assert_eq!(err, ContractError::StakingNotAllowed {});
+}
+
+#[test]
+fn fund_and_claim() {
+    let (mut app, cw721, staking, cw20) = setup();
+
+    // only the reward token can fund the pool
+    let err = app
+        .execute_contract(
+            Addr::unchecked("random"),
+            staking.clone(),
+            &ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+                sender: "random".to_string(),
+                amount: Uint128::new(1_000),
+                msg: to_json_binary(&Cw20HookMsg::Fund {}).unwrap(),
+            }),
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InvalidRewardAsset {
+            reward_asset: format!("cw20:{}", cw20)
+        }
+    );
+    fund(&mut app, &cw20, &staking, 1_500);
+    assert_eq!(
+        query_state(&app, &staking).funded_tokens,
+        Uint128::new(1_500)
+    );
+
+    stake(&mut app, &cw721, &staking, 30).unwrap();
+    let err = claim(&mut app, &cw721, &staking).unwrap_err();
+    assert_eq!(err, ContractError::NothingToClaim {});
+
+    // 100 per day, accrued per second
+    app.update_block(|block| block.time = block.time.plus_seconds(DAY_IN_SECONDS * 3 / 2));
+    claim(&mut app, &cw721, &staking).unwrap();
+    assert_eq!(query_balance(&app, &cw20, STAKER), Uint128::new(150));
+    let state = query_state(&app, &staking);
+    assert_eq!(state.total_claims, Uint128::new(150));
+    assert_eq!(state.funded_tokens, Uint128::new(1_350));
+
+    // claims fail cleanly once the pool runs dry
+    app.update_block(|block| block.time = block.time.plus_seconds(DAY_IN_SECONDS * 14));
+    let err = claim(&mut app, &cw721, &staking).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InsufficientRewardFunds {
+            rewards: Uint128::new(1_400),
+            funded_tokens: Uint128::new(1_350),
+        }
+    );
+    let state = query_state(&app, &staking);
+    assert_eq!(state.total_claims, Uint128::new(150));
+
+    // and go through again once it is topped up
+    fund(&mut app, &cw20, &staking, 50);
+    claim(&mut app, &cw721, &staking).unwrap();
+    assert_eq!(query_balance(&app, &cw20, STAKER), Uint128::new(1_550));
+    let state = query_state(&app, &staking);
+    assert_eq!(state.total_claims, Uint128::new(1_550));
+    assert_eq!(state.funded_tokens, Uint128::zero());
+
+    // unstaking from an underfunded pool returns the NFT and pays what is left
+    fund(&mut app, &cw20, &staking, 400);
+    app.update_block(|block| block.time = block.time.plus_seconds(DAY_IN_SECONDS * 29 / 2));
+    let res = unstake(&mut app, STAKER, &cw721, &staking).unwrap();
+    let wasm = res.events.iter().find(|ev| ev.ty == "wasm").unwrap();
+    assert!(wasm
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "unpaid" && attr.value == "1050"));
+    assert_eq!(query_owner(&app, &cw721), STAKER);
+    assert_eq!(query_balance(&app, &cw20, STAKER), Uint128::new(1_950));
+    assert_eq!(query_state(&app, &staking).funded_tokens, Uint128::zero());
+
+    // the shortfall stays owed until the pool is topped up again
+    let unpaid = |app: &App| -> Uint128 {
+        app.wrap()
+            .query_wasm_smart(
+                &staking,
+                &QueryMsg::UnpaidRewards {
+                    owner: STAKER.to_string(),
+                },
+            )
+            .unwrap()
+    };
+    let claim_unpaid = |app: &mut App| {
+        app.execute_contract(
+            Addr::unchecked(STAKER),
+            staking.clone(),
+            &ExecuteMsg::ClaimUnpaid {},
+            &[],
+        )
+        .map_err(|err| err.downcast::<ContractError>().unwrap())
+    };
+    assert_eq!(unpaid(&app), Uint128::new(1_050));
+    let err = claim_unpaid(&mut app).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InsufficientRewardFunds {
+            rewards: Uint128::new(1_050),
+            funded_tokens: Uint128::zero(),
+        }
+    );
+    fund(&mut app, &cw20, &staking, 1_000);
+    claim_unpaid(&mut app).unwrap();
+    assert_eq!(unpaid(&app), Uint128::new(50));
+    fund(&mut app, &cw20, &staking, 1_000);
+    claim_unpaid(&mut app).unwrap();
+    assert_eq!(unpaid(&app), Uint128::zero());
+    assert_eq!(query_balance(&app, &cw20, STAKER), Uint128::new(3_000));
+    assert_eq!(query_state(&app, &staking).funded_tokens, Uint128::new(950));
+    let err = claim_unpaid(&mut app).unwrap_err();
+    assert_eq!(err, ContractError::NothingToClaim {});
+}
 
-    mint_transfer_and_burn(&mut app, cw721.clone(), admin(), "1".to_string());
+#[test]
+fn fund_and_claim_native() {
+    let mut app = AppBuilder::new().build(|router, _, storage| {
+        router
+            .bank
+            .init_balance(storage, &Addr::unchecked(OWNER), coins(10_000, DENOM))
+            .unwrap()
+    });
+    let cw721_id = app.store_code(cw721_base_contract());
+    let cw721 = instantiate_collection(&mut app, cw721_id);
+    let reward_asset = UncheckedRewardAsset::Native {
+        denom: DENOM.to_string(),
+    };
+    let staking = instantiate_staking(&mut app, &cw721, reward_asset, None);
 
-    app.execute(
-        admin(),
-        WasmMsg::Migrate {
-            contract_addr: cw721.to_string(),
-            new_code_id: code_id_latest,
-            msg: to_json_binary(&Empty::default()).unwrap(),
+    // cw20 funding is rejected in native mode
+    let err = app
+        .execute_contract(
+            Addr::unchecked("random"),
+            staking.clone(),
+            &ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+                sender: "random".to_string(),
+                amount: Uint128::new(1_000),
+                msg: to_json_binary(&Cw20HookMsg::Fund {}).unwrap(),
+            }),
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InvalidRewardAsset {
+            reward_asset: format!("native:{}", DENOM)
         }
-        .into(),
+    );
+
+    // funding needs the reward denom attached
+    let err = app
+        .execute_contract(
+            Addr::unchecked(OWNER),
+            staking.clone(),
+            &ExecuteMsg::Fund {},
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Payment(PaymentError::NoFunds {})
+    );
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        staking.clone(),
+        &ExecuteMsg::Fund {},
+        &coins(1_000, DENOM),
     )
     .unwrap();
+    assert_eq!(
+        query_state(&app, &staking).funded_tokens,
+        Uint128::new(1_000)
+    );
 
-    mint_transfer_and_burn(&mut app, cw721.clone(), admin(), "1".to_string());
+    // the asset cannot be switched while the pool holds funds
+    let err = app
+        .execute_contract(
+            Addr::unchecked(OWNER),
+            staking.clone(),
+            &ExecuteMsg::UpdateConfig {
+                owner: OWNER.to_string(),
+                reward_asset: UncheckedRewardAsset::Native {
+                    denom: "uother".to_string(),
+                },
+                is_staking_allowed: true,
+                unbonding_period: 0,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::RewardPoolNotEmpty {}
+    );
 
-    // check new mint query response works.
-    let m: MinterResponse = app
-        .wrap()
-        .query_wasm_smart(&cw721, &crate::QueryMsg::<Empty>::Minter {})
-        .unwrap();
-    assert_eq!(m.minter, Some(admin().to_string()));
+    // claims pay out with a bank send
+    stake(&mut app, &cw721, &staking, 30).unwrap();
+    app.update_block(|block| block.time = block.time.plus_seconds(DAY_IN_SECONDS * 2));
+    claim(&mut app, &cw721, &staking).unwrap();
+    let balance = app.wrap().query_balance(STAKER, DENOM).unwrap();
+    assert_eq!(balance.amount, Uint128::new(200));
+    let state = query_state(&app, &staking);
+    assert_eq!(state.total_claims, Uint128::new(200));
+    assert_eq!(state.funded_tokens, Uint128::new(800));
+}
+
+#[test]
+fn early_unstake_forfeits_rewards() {
+    let (mut app, cw721, staking, cw20) = setup();
+    fund(&mut app, &cw20, &staking, 10_000);
+
+    // only the owner can set a policy, and at most all rewards can be forfeited
+    let policy_msg = ExecuteMsg::SetEarlyExitPolicy {
+        number_days: 30,
+        policy: Some(EarlyExitPolicy::ForfeitRewards {
+            ratio: Decimal::percent(150),
+        }),
+    };
+    let err = app
+        .execute_contract(Addr::unchecked("random"), staking.clone(), &policy_msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    );
+    let err = app
+        .execute_contract(Addr::unchecked(OWNER), staking.clone(), &policy_msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InvalidEarlyExitPolicy {}
+    );
 
-    // check that the new response is backwards compatable when minter
-    // is not None.
-    let m: v16::MinterResponse = app
+    let policy = EarlyExitPolicy::ForfeitRewards {
+        ratio: Decimal::percent(40),
+    };
+    set_early_exit_policy(&mut app, &staking, policy.clone());
+    let res: Option<EarlyExitPolicy> = app
         .wrap()
-        .query_wasm_smart(&cw721, &crate::QueryMsg::<Empty>::Minter {})
+        .query_wasm_smart(&staking, &QueryMsg::EarlyExitPolicy { number_days: 30 })
         .unwrap();
-    assert_eq!(m.minter, admin().to_string());
+    assert_eq!(res, Some(policy));
+
+    // after 5 of 30 days, 40% of the 500 accrued rewards are forfeited
+    stake(&mut app, &cw721, &staking, 30).unwrap();
+    app.update_block(|block| block.time = block.time.plus_seconds(5 * DAY_IN_SECONDS));
+    unstake(&mut app, STAKER, &cw721, &staking).unwrap();
+    assert_eq!(query_owner(&app, &cw721), STAKER);
+    assert_eq!(query_balance(&app, &cw20, STAKER), Uint128::new(300));
+
+    // forfeited rewards stay in the pool
+    let state = query_state(&app, &staking);
+    assert_eq!(state.total_stakes, Uint128::zero());
+    assert_eq!(state.total_claims, Uint128::new(300));
+    assert_eq!(state.funded_tokens, Uint128::new(9_700));
+}
+
+#[test]
+fn early_unstake_charges_fee() {
+    let mut app = AppBuilder::new().build(|router, _, storage| {
+        router
+            .bank
+            .init_balance(
+                storage,
+                &Addr::unchecked(STAKER),
+                vec![coin(1_000, "uatom"), coin(1_000, "uosmo")],
+            )
+            .unwrap()
+    });
+    let cw721_id = app.store_code(cw721_base_contract());
+    let cw721 = instantiate_collection(&mut app, cw721_id);
+    let cw20 = instantiate_cw20(&mut app);
+    let reward_asset = UncheckedRewardAsset::Cw20 {
+        address: cw20.to_string(),
+    };
+    let staking = instantiate_staking(&mut app, &cw721, reward_asset, None);
+    fund(&mut app, &cw20, &staking, 10_000);
+    set_early_exit_policy(
+        &mut app,
+        &staking,
+        EarlyExitPolicy::Fee {
+            fee: coin(250, "uatom"),
+        },
+    );
+
+    stake(&mut app, &cw721, &staking, 30).unwrap();
+    app.update_block(|block| block.time = block.time.plus_seconds(5 * DAY_IN_SECONDS));
+
+    // the exact fee must be attached
+    let unstake_msg = ExecuteMsg::Unstake {
+        collection: cw721.to_string(),
+        token_id: "1".to_string(),
+    };
+    for funds in [vec![], coins(100, "uatom")] {
+        let err = app
+            .execute_contract(
+                Addr::unchecked(STAKER),
+                staking.clone(),
+                &unstake_msg,
+                &funds,
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.downcast::<ContractError>().unwrap(),
+            ContractError::IncorrectEarlyExitFee {
+                fee: coin(250, "uatom")
+            }
+        );
+    }
+
+    // funds beyond the fee are rejected
+    let err = app
+        .execute_contract(
+            Addr::unchecked(STAKER),
+            staking.clone(),
+            &unstake_msg,
+            &[coin(250, "uatom"), coin(10, "uosmo")],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Payment(PaymentError::ExtraDenom("uosmo".to_string()))
+    );
+
+    // the fee goes to the owner and the rewards are paid in full
+    app.execute_contract(
+        Addr::unchecked(STAKER),
+        staking.clone(),
+        &unstake_msg,
+        &coins(250, "uatom"),
+    )
+    .unwrap();
+    assert_eq!(query_owner(&app, &cw721), STAKER);
+    assert_eq!(query_balance(&app, &cw20, STAKER), Uint128::new(500));
+    let owner_balance = app.wrap().query_balance(OWNER, "uatom").unwrap();
+    assert_eq!(owner_balance.amount, Uint128::new(250));
+
+    // without a policy the lock holds
+    stake(&mut app, &cw721, &staking, 30).unwrap();
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        staking.clone(),
+        &ExecuteMsg::SetEarlyExitPolicy {
+            number_days: 30,
+            policy: None,
+        },
+        &[],
+    )
+    .unwrap();
+    let unlock_time = app.block_info().time.seconds() + 30 * DAY_IN_SECONDS;
+    let err = unstake(&mut app, STAKER, &cw721, &staking).unwrap_err();
+    assert_eq!(err, ContractError::UnstakeTooEarly { unlock_time });
+
+    // once no fee is due no funds may be attached
+    app.update_block(|block| block.time = block.time.plus_seconds(30 * DAY_IN_SECONDS));
+    let err = app
+        .execute_contract(
+            Addr::unchecked(STAKER),
+            staking.clone(),
+            &unstake_msg,
+            &coins(250, "uatom"),
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Payment(PaymentError::NonPayable {})
+    );
 }
 
-/// Test backward compatibility using instantiate msg from a 0.16 version on latest contract.
-/// This ensures existing 3rd party contracts doesnt need to updated as well.
 #[test]
-fn test_instantiate_016_msg() {
-    use cw721_base_016 as v16;
+fn multipliers_apply_at_stake_time() {
     let mut app = App::default();
-    let admin = || Addr::unchecked("admin");
+    let cw721_id = app.store_code(cw721_metadata_contract());
+    let cw721 = instantiate_empty_collection(&mut app, cw721_id);
+    let attributes = vec![
+        Trait {
+            display_type: None,
+            trait_type: "rarity".to_string(),
+            value: "legendary".to_string(),
+        },
+        Trait {
+            display_type: None,
+            trait_type: "background".to_string(),
+            value: "gold".to_string(),
+        },
+    ];
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        cw721.clone(),
+        &cw721_base::ExecuteMsg::<_, Empty>::Mint {
+            token_id: "1".to_string(),
+            owner: STAKER.to_string(),
+            token_uri: None,
+            extension: Some(Metadata {
+                name: Some("legend".to_string()),
+                attributes: Some(attributes),
+            }),
+        },
+        &[],
+    )
+    .unwrap();
+    let cw20 = instantiate_cw20(&mut app);
+    let reward_asset = UncheckedRewardAsset::Cw20 {
+        address: cw20.to_string(),
+    };
+    let staking = instantiate_staking(&mut app, &cw721, reward_asset, None);
+    fund(&mut app, &cw20, &staking, 100_000);
 
-    let code_id_latest = app.store_code(cw721_base_latest_contract());
+    // multipliers are owner only and non-zero
+    let set_collection = |multiplier| ExecuteMsg::SetCollectionMultiplier {
+        collection: cw721.to_string(),
+        multiplier: Some(multiplier),
+    };
+    let err = app
+        .execute_contract(
+            Addr::unchecked("random"),
+            staking.clone(),
+            &set_collection(Decimal::percent(200)),
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    );
+    let err = app
+        .execute_contract(
+            Addr::unchecked(OWNER),
+            staking.clone(),
+            &set_collection(Decimal::zero()),
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InvalidMultiplier {}
+    );
 
-    let cw721 = app
-        .instantiate_contract(
-            code_id_latest,
-            admin(),
-            &v16::InstantiateMsg {
-                name: "collection".to_string(),
-                symbol: "symbol".to_string(),
-                minter: admin().into_string(),
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        staking.clone(),
+        &set_collection(Decimal::percent(200)),
+        &[],
+    )
+    .unwrap();
+    for (trait_type, value, multiplier) in [
+        ("rarity", "legendary", Decimal::percent(300)),
+        ("rarity", "common", Decimal::percent(50)),
+        ("background", "gold", Decimal::percent(150)),
+    ] {
+        app.execute_contract(
+            Addr::unchecked(OWNER),
+            staking.clone(),
+            &ExecuteMsg::SetTraitMultiplier {
+                collection: cw721.to_string(),
+                trait_type: trait_type.to_string(),
+                value: value.to_string(),
+                multiplier: Some(multiplier),
             },
             &[],
-            "cw721-base",
-            Some(admin().into_string()),
         )
         .unwrap();
-
-    // assert withdraw address is None
-    let withdraw_addr: Option<String> = app
+    }
+    let res: MultipliersResponse = app
         .wrap()
-        .query_wasm_smart(cw721, &crate::QueryMsg::<Empty>::GetWithdrawAddress {})
+        .query_wasm_smart(
+            &staking,
+            &QueryMsg::Multipliers {
+                collection: cw721.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.collection, Decimal::percent(200));
+    assert_eq!(
+        res.traits,
+        vec![
+            TraitMultiplier {
+                trait_type: "rarity".to_string(),
+                value: "common".to_string(),
+                multiplier: Decimal::percent(50),
+            },
+            TraitMultiplier {
+                trait_type: "rarity".to_string(),
+                value: "legendary".to_string(),
+                multiplier: Decimal::percent(300),
+            },
+            TraitMultiplier {
+                trait_type: "background".to_string(),
+                value: "gold".to_string(),
+                multiplier: Decimal::percent(150),
+            },
+        ]
+    );
+
+    // the highest matching trait applies on top of the collection: 100 * 2 * 3 per day
+    stake(&mut app, &cw721, &staking, 30).unwrap();
+    app.update_block(|block| block.time = block.time.plus_seconds(DAY_IN_SECONDS));
+    claim(&mut app, &cw721, &staking).unwrap();
+    assert_eq!(query_balance(&app, &cw20, STAKER), Uint128::new(600));
+
+    // changing multipliers later does not affect the stake
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        staking.clone(),
+        &ExecuteMsg::SetTraitMultiplier {
+            collection: cw721.to_string(),
+            trait_type: "rarity".to_string(),
+            value: "legendary".to_string(),
+            multiplier: None,
+        },
+        &[],
+    )
+    .unwrap();
+    app.update_block(|block| block.time = block.time.plus_seconds(DAY_IN_SECONDS / 2));
+    claim(&mut app, &cw721, &staking).unwrap();
+    assert_eq!(query_balance(&app, &cw20, STAKER), Uint128::new(900));
+}
+
+#[test]
+fn query_stakes() {
+    let (mut app, cw721, staking, cw20) = setup();
+    let other_id = app.store_code(cw721_base_contract());
+    let other = instantiate_collection(&mut app, other_id);
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        staking.clone(),
+        &ExecuteMsg::WhitelistCollection {
+            collection_addr: other.to_string(),
+            boolean: true,
+        },
+        &[],
+    )
+    .unwrap();
+    fund(&mut app, &cw20, &staking, 10_000);
+
+    mint(&mut app, &cw721, "2");
+    stake_token(&mut app, &cw721, &staking, "1", 30).unwrap();
+    stake_token(&mut app, &cw721, &staking, "2", 30).unwrap();
+    stake_token(&mut app, &other, &staking, "1", 30).unwrap();
+    let stake_time = app.block_info().time.seconds();
+
+    let res: StakeInfo = app
+        .wrap()
+        .query_wasm_smart(
+            &staking,
+            &QueryMsg::StakeInfo {
+                collection: other.to_string(),
+                token_id: "1".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        res,
+        StakeInfo {
+            owner: Addr::unchecked(STAKER),
+            total_earned_tokens: Uint128::zero(),
+            token_id: "1".to_string(),
+            stake_days: 30,
+            reward_rate: 100,
+            multiplier: Decimal::one(),
+            stake_time,
+            last_claim_time: stake_time,
+            reward_debt: Decimal256::zero(),
+            collection: other.clone(),
+        }
+    );
+
+    // stakes of an owner are paginated across collections
+    let query_by_owner = |app: &App, start_after: Option<(String, String)>| {
+        let res: StakesResponse = app
+            .wrap()
+            .query_wasm_smart(
+                &staking,
+                &QueryMsg::StakesByOwner {
+                    owner: STAKER.to_string(),
+                    start_after,
+                    limit: Some(2),
+                },
+            )
+            .unwrap();
+        res.stakes
+            .into_iter()
+            .map(|stake| (stake.collection.to_string(), stake.token_id))
+            .collect::<Vec<_>>()
+    };
+    let page = query_by_owner(&app, None);
+    assert_eq!(page.len(), 2);
+    let last = page.last().cloned();
+    let mut all = page;
+    all.extend(query_by_owner(&app, last));
+    all.sort();
+    let mut expected = vec![
+        (cw721.to_string(), "1".to_string()),
+        (cw721.to_string(), "2".to_string()),
+        (other.to_string(), "1".to_string()),
+    ];
+    expected.sort();
+    assert_eq!(all, expected);
+    let none: StakesResponse = app
+        .wrap()
+        .query_wasm_smart(
+            &staking,
+            &QueryMsg::StakesByOwner {
+                owner: "random".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert!(none.stakes.is_empty());
+
+    // stakes of a collection
+    let res: StakesResponse = app
+        .wrap()
+        .query_wasm_smart(
+            &staking,
+            &QueryMsg::StakesByCollection {
+                collection: cw721.to_string(),
+                start_after: Some("1".to_string()),
+                limit: None,
+            },
+        )
+        .unwrap();
+    let token_ids: Vec<_> = res.stakes.into_iter().map(|stake| stake.token_id).collect();
+    assert_eq!(token_ids, vec!["2".to_string()]);
+
+    // pending rewards at the current block, without claiming them
+    app.update_block(|block| block.time = block.time.plus_seconds(DAY_IN_SECONDS));
+    claim(&mut app, &cw721, &staking).unwrap();
+    app.update_block(|block| block.time = block.time.plus_seconds(DAY_IN_SECONDS / 2));
+    let res: PendingRewardsResponse = app
+        .wrap()
+        .query_wasm_smart(
+            &staking,
+            &QueryMsg::PendingRewards {
+                owner: STAKER.to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(res.total, Uint128::new(350));
+    assert!(res.stakes.contains(&PendingReward {
+        collection: cw721.clone(),
+        token_id: "1".to_string(),
+        rewards: Uint128::new(50),
+    }));
+    assert!(res.stakes.contains(&PendingReward {
+        collection: other.clone(),
+        token_id: "1".to_string(),
+        rewards: Uint128::new(150),
+    }));
+
+    // paginated like the stakes, with the total of each page
+    let pending_rewards = |start_after: Option<&PendingReward>| -> PendingRewardsResponse {
+        app.wrap()
+            .query_wasm_smart(
+                &staking,
+                &QueryMsg::PendingRewards {
+                    owner: STAKER.to_string(),
+                    start_after: start_after
+                        .map(|stake| (stake.collection.to_string(), stake.token_id.clone())),
+                    limit: Some(2),
+                },
+            )
+            .unwrap()
+    };
+    let first = pending_rewards(None);
+    assert_eq!(first.stakes, res.stakes[..2]);
+    assert_eq!(first.total, res.stakes[0].rewards + res.stakes[1].rewards);
+    let second = pending_rewards(first.stakes.last());
+    assert_eq!(second.stakes, res.stakes[2..]);
+    assert_eq!(first.total + second.total, res.total);
+    assert_eq!(query_state(&app, &staking).total_claims, Uint128::new(100));
+}
+
+#[test]
+fn emission_is_split_pro_rata() {
+    let mut app = App::default();
+    let cw721_id = app.store_code(cw721_base_contract());
+    let cw721 = instantiate_collection(&mut app, cw721_id);
+    mint(&mut app, &cw721, "2");
+    let cw20 = instantiate_cw20(&mut app);
+    let reward_asset = UncheckedRewardAsset::Cw20 {
+        address: cw20.to_string(),
+    };
+
+    // 1 token per second for 10 days
+    let start = app.block_info().time.seconds();
+    let end = start + 10 * DAY_IN_SECONDS;
+    let reward_mode = RewardMode::Emission {
+        rate: Uint128::one(),
+        start,
+        end,
+    };
+    let staking = instantiate_staking(&mut app, &cw721, reward_asset, Some(reward_mode));
+    fund(&mut app, &cw20, &staking, 1_000_000);
+
+    // token 1 gets the whole emission of the first day, then shares it equally with token 2
+    stake_token(&mut app, &cw721, &staking, "1", 30).unwrap();
+    app.update_block(|block| block.time = block.time.plus_seconds(DAY_IN_SECONDS));
+    stake_token(&mut app, &cw721, &staking, "2", 30).unwrap();
+    app.update_block(|block| block.time = block.time.plus_seconds(DAY_IN_SECONDS));
+    claim_token(&mut app, &cw721, &staking, "1").unwrap();
+    assert_eq!(query_balance(&app, &cw20, STAKER), Uint128::new(129_600));
+
+    // a new schedule must end after it starts
+    let err = app
+        .execute_contract(
+            Addr::unchecked(OWNER),
+            staking.clone(),
+            &ExecuteMsg::SetEmission {
+                rate: Uint128::one(),
+                start: end,
+                end: start,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InvalidEmissionSchedule {}
+    );
+
+    // nothing is emitted after the end, so payouts add up to the schedule
+    app.update_block(|block| block.time = block.time.plus_seconds(10 * DAY_IN_SECONDS));
+    let res: PendingRewardsResponse = app
+        .wrap()
+        .query_wasm_smart(
+            &staking,
+            &QueryMsg::PendingRewards {
+                owner: STAKER.to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(res.total, Uint128::new(864_000 - 129_600));
+    claim_token(&mut app, &cw721, &staking, "1").unwrap();
+    claim_token(&mut app, &cw721, &staking, "2").unwrap();
+    assert_eq!(query_balance(&app, &cw20, STAKER), Uint128::new(864_000));
+    let state = query_state(&app, &staking);
+    assert_eq!(state.total_claims, Uint128::new(864_000));
+    assert_eq!(state.funded_tokens, Uint128::new(136_000));
+    let err = claim_token(&mut app, &cw721, &staking, "1").unwrap_err();
+    assert_eq!(err, ContractError::NothingToClaim {});
+
+    // fixed rate contracts have no emission to set
+    let (mut app, _, staking, _) = setup();
+    let err = app
+        .execute_contract(
+            Addr::unchecked(OWNER),
+            staking,
+            &ExecuteMsg::SetEmission {
+                rate: Uint128::one(),
+                start,
+                end,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InvalidRewardMode {}
+    );
+}
+
+#[test]
+fn staker_keeps_using_staked_nft() {
+    let mut app = App::default();
+    let cw721_id = app.store_code(cw721_base_contract());
+    // users persist through transfers, so unstaking has to clear the user itself
+    let cw721 = app
+        .instantiate_contract(
+            cw721_id,
+            Addr::unchecked(OWNER),
+            &cw721_base::InstantiateMsg {
+                name: "collection".to_string(),
+                symbol: "symbol".to_string(),
+                minter: None,
+                withdraw_address: None,
+                eviction_policy: None,
+                rental_config: Some(cw721_base::msg::RentalConfig::PersistUntilExpiry),
+                max_history: None,
+                base_uri: None,
+            },
+            &[],
+            "cw721-base",
+            None,
+        )
+        .unwrap();
+    mint(&mut app, &cw721, "1");
+    let cw20 = instantiate_cw20(&mut app);
+    let reward_asset = UncheckedRewardAsset::Cw20 {
+        address: cw20.to_string(),
+    };
+    let staking = instantiate_staking(&mut app, &cw721, reward_asset, None);
+    fund(&mut app, &cw20, &staking, 10_000);
+    set_early_exit_policy(
+        &mut app,
+        &staking,
+        EarlyExitPolicy::ForfeitRewards {
+            ratio: Decimal::zero(),
+        },
+    );
+
+    // the staker is the user until the lock ends
+    stake(&mut app, &cw721, &staking, 30).unwrap();
+    let unlock_time = app.block_info().time.seconds() + 30 * DAY_IN_SECONDS;
+    assert_eq!(
+        query_user(&app, &cw721),
+        UserOfResponse {
+            user: STAKER.to_string(),
+            expires: Expiration::AtTime(Timestamp::from_seconds(unlock_time)),
+        }
+    );
+
+    // and stops being the user when unstaking early
+    app.update_block(|block| block.time = block.time.plus_seconds(DAY_IN_SECONDS));
+    unstake(&mut app, STAKER, &cw721, &staking).unwrap();
+    assert_eq!(query_owner(&app, &cw721), STAKER);
+    assert_eq!(query_user(&app, &cw721).user, "");
+}
+
+#[test]
+fn stake_without_user_support() {
+    let mut app = App::default();
+    let cw721_id = app.store_code(cw721_no_users_contract());
+    let cw721 = instantiate_collection(&mut app, cw721_id);
+    let cw20 = instantiate_cw20(&mut app);
+    let reward_asset = UncheckedRewardAsset::Cw20 {
+        address: cw20.to_string(),
+    };
+    let staking = instantiate_staking(&mut app, &cw721, reward_asset, None);
+    fund(&mut app, &cw20, &staking, 10_000);
+
+    // the rejected SetUser is skipped and the NFT is staked anyway
+    let res = stake(&mut app, &cw721, &staking, 30).unwrap();
+    assert!(res.events.iter().any(|event| event
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "method" && attr.value == "skip_user")));
+    assert_eq!(query_owner(&app, &cw721), staking.as_str());
+    assert_eq!(query_user(&app, &cw721).user, "");
+
+    app.update_block(|block| block.time = block.time.plus_seconds(30 * DAY_IN_SECONDS));
+    unstake(&mut app, STAKER, &cw721, &staking).unwrap();
+    assert_eq!(query_owner(&app, &cw721), STAKER);
+}
+
+#[test]
+fn claim_all_and_unstake_many() {
+    let (mut app, cw721, staking, cw20) = setup();
+    let other_id = app.store_code(cw721_base_contract());
+    let other = instantiate_collection(&mut app, other_id);
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        staking.clone(),
+        &ExecuteMsg::WhitelistCollection {
+            collection_addr: other.to_string(),
+            boolean: true,
+        },
+        &[],
+    )
+    .unwrap();
+    fund(&mut app, &cw20, &staking, 10_000);
+    mint(&mut app, &cw721, "2");
+    stake_token(&mut app, &cw721, &staking, "1", 30).unwrap();
+    stake_token(&mut app, &cw721, &staking, "2", 30).unwrap();
+    stake_token(&mut app, &other, &staking, "1", 30).unwrap();
+
+    let claim_all = |app: &mut App,
+                     collection: Option<&Addr>,
+                     start_after: Option<(&Addr, &str)>,
+                     limit: Option<u32>| {
+        app.execute_contract(
+            Addr::unchecked(STAKER),
+            staking.clone(),
+            &ExecuteMsg::ClaimAll {
+                collection: collection.map(|collection| collection.to_string()),
+                start_after: start_after
+                    .map(|(collection, token_id)| (collection.to_string(), token_id.to_string())),
+                limit,
+            },
+            &[],
+        )
+        .map_err(|err| err.downcast::<ContractError>().unwrap())
+    };
+    // cw20 transfers made by the staking contract
+    let reward_transfers = |res: &AppResponse| {
+        res.events
+            .iter()
+            .filter(|event| {
+                event.ty == "wasm"
+                    && event
+                        .attributes
+                        .iter()
+                        .any(|attr| attr.value == cw20.as_str())
+                    && event
+                        .attributes
+                        .iter()
+                        .any(|attr| attr.key == "action" && attr.value == "transfer")
+            })
+            .count()
+    };
+
+    // all stakes are claimed in one transfer
+    let err = claim_all(&mut app, None, None, None).unwrap_err();
+    assert_eq!(err, ContractError::NothingToClaim {});
+    app.update_block(|block| block.time = block.time.plus_seconds(DAY_IN_SECONDS));
+    let res = claim_all(&mut app, None, None, None).unwrap();
+    assert_eq!(reward_transfers(&res), 1);
+    assert_eq!(query_balance(&app, &cw20, STAKER), Uint128::new(300));
+
+    // stakes without rewards do not count against the limit
+    app.update_block(|block| block.time = block.time.plus_seconds(DAY_IN_SECONDS));
+    claim_all(&mut app, None, None, Some(1)).unwrap();
+    assert_eq!(query_balance(&app, &cw20, STAKER), Uint128::new(400));
+    claim_all(&mut app, None, None, Some(1)).unwrap();
+    assert_eq!(query_balance(&app, &cw20, STAKER), Uint128::new(500));
+
+    // paging starts after the given stake, the last one of the owner is cw721's "2"
+    let err = claim_all(&mut app, None, Some((&cw721, "2")), None).unwrap_err();
+    assert_eq!(err, ContractError::NothingToClaim {});
+    let res = claim_all(&mut app, None, None, None).unwrap();
+    assert!(res.events.iter().any(|event| event
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "last" && attr.value == format!("{cw721}/2"))));
+    assert_eq!(query_balance(&app, &cw20, STAKER), Uint128::new(600));
+
+    // only the stakes of one collection
+    app.update_block(|block| block.time = block.time.plus_seconds(DAY_IN_SECONDS));
+    claim_all(&mut app, Some(&other), None, None).unwrap();
+    assert_eq!(query_balance(&app, &cw20, STAKER), Uint128::new(700));
+
+    // only the staker can unstake
+    let tokens = vec![
+        (cw721.to_string(), "1".to_string()),
+        (cw721.to_string(), "2".to_string()),
+        (other.to_string(), "1".to_string()),
+    ];
+    app.update_block(|block| block.time = block.time.plus_seconds(27 * DAY_IN_SECONDS));
+    let err = app
+        .execute_contract(
+            Addr::unchecked("random"),
+            staking.clone(),
+            &ExecuteMsg::UnstakeMany {
+                tokens: tokens.clone(),
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    );
+
+    // all NFTs are returned and their remaining 28, 28 and 27 days paid in one transfer
+    let res = app
+        .execute_contract(
+            Addr::unchecked(STAKER),
+            staking.clone(),
+            &ExecuteMsg::UnstakeMany { tokens },
+            &[],
+        )
+        .unwrap();
+    assert_eq!(reward_transfers(&res), 1);
+    assert_eq!(query_balance(&app, &cw20, STAKER), Uint128::new(9_000));
+    assert_eq!(query_owner(&app, &cw721), STAKER);
+    assert_eq!(query_owner(&app, &other), STAKER);
+    let state = query_state(&app, &staking);
+    assert_eq!(state.total_stakes, Uint128::zero());
+    assert_eq!(state.total_weight, Uint128::zero());
+    assert_eq!(state.funded_tokens, Uint128::new(1_000));
+}
+
+#[test]
+fn claim_all_pages_past_stakes_without_rewards() {
+    let (mut app, cw721, staking, cw20) = setup();
+    let other_id = app.store_code(cw721_base_contract());
+    let other = instantiate_empty_collection(&mut app, other_id);
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        staking.clone(),
+        &ExecuteMsg::WhitelistCollection {
+            collection_addr: other.to_string(),
+            boolean: true,
+        },
+        &[],
+    )
+    .unwrap();
+    fund(&mut app, &cw20, &staking, 10_000);
+
+    // a full page of stakes of another collection comes first in key order
+    for i in 0..300 {
+        let token_id = format!("0-{i:03}");
+        mint(&mut app, &other, &token_id);
+        stake_token(&mut app, &other, &staking, &token_id, 30).unwrap();
+    }
+    app.update_block(|block| block.time = block.time.plus_seconds(DAY_IN_SECONDS));
+
+    // the page has nothing to claim, but says where it ended
+    let claim_all = |app: &mut App, start_after: Option<(String, String)>| {
+        app.execute_contract(
+            Addr::unchecked(STAKER),
+            staking.clone(),
+            &ExecuteMsg::ClaimAll {
+                collection: Some(cw721.to_string()),
+                start_after,
+                limit: None,
+            },
+            &[],
+        )
+        .map_err(|err| err.downcast::<ContractError>().unwrap())
+    };
+    let attribute = |res: &AppResponse, key: &str| {
+        res.events
+            .iter()
+            .flat_map(|event| &event.attributes)
+            .find(|attr| attr.key == key)
+            .map(|attr| attr.value.clone())
+    };
+    let res = claim_all(&mut app, None).unwrap();
+    assert_eq!(attribute(&res, "count"), Some("0".to_string()));
+    assert_eq!(attribute(&res, "last"), Some(format!("{other}/0-299")));
+    assert_eq!(query_balance(&app, &cw20, STAKER), Uint128::zero());
+
+    // continuing after it claims the stake of the collection, keys sort by id length first
+    mint(&mut app, &cw721, "staked-later");
+    stake_token(&mut app, &cw721, &staking, "staked-later", 30).unwrap();
+    app.update_block(|block| block.time = block.time.plus_seconds(DAY_IN_SECONDS));
+    let res = claim_all(&mut app, Some((other.to_string(), "0-299".to_string()))).unwrap();
+    assert_eq!(attribute(&res, "count"), Some("1".to_string()));
+    assert_eq!(query_balance(&app, &cw20, STAKER), Uint128::new(100));
+
+    // and past the last stake there is nothing to claim
+    let err = claim_all(
+        &mut app,
+        Some((cw721.to_string(), "staked-later".to_string())),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::NothingToClaim {});
+}
+
+#[test]
+fn emergency_mode() {
+    let (mut app, cw721, staking, cw20) = setup();
+    fund(&mut app, &cw20, &staking, 10_000);
+    mint(&mut app, &cw721, "2");
+    stake(&mut app, &cw721, &staking, 30).unwrap();
+    app.update_block(|block| block.time = block.time.plus_seconds(5 * DAY_IN_SECONDS));
+
+    // only the owner can enter emergency mode, and the pool stays locked until then
+    let withdraw_msg = ExecuteMsg::EmergencyWithdraw { recipient: None };
+    let err = app
+        .execute_contract(Addr::unchecked(OWNER), staking.clone(), &withdraw_msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::NotEmergencyMode {}
+    );
+    let err = app
+        .execute_contract(
+            Addr::unchecked("random"),
+            staking.clone(),
+            &ExecuteMsg::EnterEmergency {},
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    );
+    let res = app
+        .execute_contract(
+            Addr::unchecked(OWNER),
+            staking.clone(),
+            &ExecuteMsg::EnterEmergency {},
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_event(&Event::new("wasm-emergency").add_attribute("action", "enter")));
+
+    let since = app.block_info().time.seconds();
+    let res: ModeResponse = app
+        .wrap()
+        .query_wasm_smart(&staking, &QueryMsg::Mode {})
+        .unwrap();
+    assert_eq!(
+        res,
+        ModeResponse {
+            mode: Mode::Emergency { since },
+            withdraw_after: Some(since + EMERGENCY_WITHDRAW_DELAY),
+        }
+    );
+
+    // staking, claiming, unstaking and funding are closed
+    let err = stake_token(&mut app, &cw721, &staking, "2", 30).unwrap_err();
+    assert_eq!(err, ContractError::EmergencyMode {});
+    let err = claim(&mut app, &cw721, &staking).unwrap_err();
+    assert_eq!(err, ContractError::EmergencyMode {});
+    let err = unstake(&mut app, STAKER, &cw721, &staking).unwrap_err();
+    assert_eq!(err, ContractError::EmergencyMode {});
+    let err = app
+        .execute_contract(
+            Addr::unchecked(OWNER),
+            cw20.clone(),
+            &Cw20ExecuteMsg::Send {
+                contract: staking.to_string(),
+                amount: Uint128::new(1_000),
+                msg: to_json_binary(&Cw20HookMsg::Fund {}).unwrap(),
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::EmergencyMode {}
+    );
+
+    // only the staker can pull the NFT back, before the lock is over and without rewards
+    let unstake_msg = ExecuteMsg::EmergencyUnstake {
+        tokens: vec![(cw721.to_string(), "1".to_string())],
+    };
+    let err = app
+        .execute_contract(
+            Addr::unchecked("random"),
+            staking.clone(),
+            &unstake_msg,
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    );
+    let res = app
+        .execute_contract(Addr::unchecked(STAKER), staking.clone(), &unstake_msg, &[])
+        .unwrap();
+    assert!(res.has_event(&Event::new("wasm-emergency").add_attribute("action", "unstake")));
+    assert_eq!(query_owner(&app, &cw721), STAKER);
+    assert_eq!(query_balance(&app, &cw20, STAKER), Uint128::zero());
+    let state = query_state(&app, &staking);
+    assert_eq!(state.total_stakes, Uint128::zero());
+    assert_eq!(state.funded_tokens, Uint128::new(10_000));
+
+    // the owner can withdraw the pool once the timelock is over
+    let err = app
+        .execute_contract(Addr::unchecked(OWNER), staking.clone(), &withdraw_msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::WithdrawLocked {
+            withdraw_after: since + EMERGENCY_WITHDRAW_DELAY
+        }
+    );
+    app.update_block(|block| block.time = block.time.plus_seconds(EMERGENCY_WITHDRAW_DELAY));
+    let err = app
+        .execute_contract(Addr::unchecked(STAKER), staking.clone(), &withdraw_msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    );
+    let owner_balance = query_balance(&app, &cw20, OWNER);
+    app.execute_contract(Addr::unchecked(OWNER), staking.clone(), &withdraw_msg, &[])
+        .unwrap();
+    assert_eq!(
+        query_balance(&app, &cw20, OWNER),
+        owner_balance + Uint128::new(10_000)
+    );
+    assert_eq!(query_state(&app, &staking).funded_tokens, Uint128::zero());
+}
+
+#[test]
+fn unstake_with_unbonding_period() {
+    let (mut app, cw721, staking, cw20) = setup();
+    fund(&mut app, &cw20, &staking, 10_000);
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        staking.clone(),
+        &ExecuteMsg::UpdateConfig {
+            owner: OWNER.to_string(),
+            reward_asset: UncheckedRewardAsset::Cw20 {
+                address: cw20.to_string(),
+            },
+            is_staking_allowed: true,
+            unbonding_period: 3 * DAY_IN_SECONDS,
+        },
+        &[],
+    )
+    .unwrap();
+
+    // unstaking pays out the rewards of the 30 days but keeps the NFT for 3 more days,
+    // the staker stops being its user right away
+    stake(&mut app, &cw721, &staking, 30).unwrap();
+    assert_eq!(query_user(&app, &cw721).user, STAKER);
+    app.update_block(|block| block.time = block.time.plus_seconds(30 * DAY_IN_SECONDS));
+    unstake(&mut app, STAKER, &cw721, &staking).unwrap();
+    assert_eq!(query_owner(&app, &cw721), staking.as_str());
+    assert_eq!(query_user(&app, &cw721).user, "");
+    assert_eq!(query_balance(&app, &cw20, STAKER), Uint128::new(3_000));
+    assert_eq!(query_state(&app, &staking).total_stakes, Uint128::zero());
+
+    let withdrawable_at = app.block_info().time.seconds() + 3 * DAY_IN_SECONDS;
+    let res: UnbondingResponse = app
+        .wrap()
+        .query_wasm_smart(
+            &staking,
+            &QueryMsg::Unbonding {
+                owner: STAKER.to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        res.nfts,
+        vec![Unbonding {
+            owner: Addr::unchecked(STAKER),
+            collection: cw721.clone(),
+            token_id: "1".to_string(),
+            withdrawable_at,
+        }]
+    );
+
+    // the NFT earns nothing while unbonding
+    app.update_block(|block| block.time = block.time.plus_seconds(DAY_IN_SECONDS));
+    claim(&mut app, &cw721, &staking).unwrap_err();
+
+    // only the staker can withdraw it, once the unbonding period is over
+    let withdraw_msg = ExecuteMsg::WithdrawUnbonded {
+        tokens: vec![(cw721.to_string(), "1".to_string())],
+    };
+    let err = app
+        .execute_contract(Addr::unchecked(STAKER), staking.clone(), &withdraw_msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::UnbondingNotOver { withdrawable_at }
+    );
+    app.update_block(|block| block.time = block.time.plus_seconds(2 * DAY_IN_SECONDS));
+    app.execute_contract(
+        Addr::unchecked("random"),
+        staking.clone(),
+        &withdraw_msg,
+        &[],
+    )
+    .unwrap_err();
+    app.execute_contract(Addr::unchecked(STAKER), staking.clone(), &withdraw_msg, &[])
+        .unwrap();
+    assert_eq!(query_owner(&app, &cw721), STAKER);
+    assert_eq!(query_balance(&app, &cw20, STAKER), Uint128::new(3_000));
+    let res: UnbondingResponse = app
+        .wrap()
+        .query_wasm_smart(
+            &staking,
+            &QueryMsg::Unbonding {
+                owner: STAKER.to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert!(res.nfts.is_empty());
+}
+
+#[test]
+fn voting_power_at_height() {
+    let (mut app, cw721, staking, cw20) = setup();
+    fund(&mut app, &cw20, &staking, 10_000);
+    mint(&mut app, &cw721, "2");
+
+    // staked NFTs count from the block after they are staked
+    let start = app.block_info().height;
+    stake(&mut app, &cw721, &staking, 30).unwrap();
+    app.update_block(next_block);
+    stake_token(&mut app, &cw721, &staking, "2", 30).unwrap();
+    app.update_block(next_block);
+    let one = (Uint128::one(), Uint128::one());
+    let two = (Uint128::new(2), Uint128::new(2));
+    assert_eq!(
+        query_voting_power(&app, &staking, Some(start)),
+        (Uint128::zero(), Uint128::zero())
+    );
+    assert_eq!(query_voting_power(&app, &staking, Some(start + 1)), one);
+    assert_eq!(query_voting_power(&app, &staking, Some(start + 2)), two);
+    assert_eq!(query_voting_power(&app, &staking, None), two);
+
+    // others have no voting power
+    let res: VotingPowerAtHeightResponse = app
+        .wrap()
+        .query_wasm_smart(
+            &staking,
+            &QueryMsg::VotingPowerAtHeight {
+                address: "random".to_string(),
+                height: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(res.power, Uint128::zero());
+
+    // unstaking only lowers the voting power from then on
+    app.update_block(|block| {
+        block.height += 1;
+        block.time = block.time.plus_seconds(30 * DAY_IN_SECONDS);
+    });
+    let unstake_height = app.block_info().height;
+    unstake(&mut app, STAKER, &cw721, &staking).unwrap();
+    app.update_block(next_block);
+    assert_eq!(query_voting_power(&app, &staking, Some(start + 2)), two);
+    assert_eq!(
+        query_voting_power(&app, &staking, Some(unstake_height)),
+        two
+    );
+    assert_eq!(query_voting_power(&app, &staking, None), one);
+
+    // the rest of the voting module interface
+    let dao: Addr = app
+        .wrap()
+        .query_wasm_smart(&staking, &QueryMsg::Dao {})
+        .unwrap();
+    assert_eq!(dao, OWNER);
+    let info: InfoResponse = app
+        .wrap()
+        .query_wasm_smart(&staking, &QueryMsg::Info {})
+        .unwrap();
+    assert_eq!(info.info.contract, CONTRACT_NAME);
+    assert_eq!(info.info.version, CONTRACT_VERSION);
+}
+
+#[test]
+fn milestones_mint_badges() {
+    let (mut app, cw721, staking, cw20) = setup();
+    fund(&mut app, &cw20, &staking, 10_000);
+    mint(&mut app, &cw721, "2");
+
+    // badges are minted by the staking contract, so it must be the collection's minter
+    let set_collection = |collection: &Addr| ExecuteMsg::SetBadgeCollection {
+        collection: Some(collection.to_string()),
+    };
+    let err = app
+        .execute_contract(
+            Addr::unchecked(OWNER),
+            staking.clone(),
+            &set_collection(&cw721),
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::NotBadgeMinter {}
+    );
+    let badges_id = app.store_code(cw721_metadata_contract());
+    let badges = app
+        .instantiate_contract(
+            badges_id,
+            Addr::unchecked(OWNER),
+            &cw721_base::InstantiateMsg {
+                name: "badges".to_string(),
+                symbol: "badge".to_string(),
+                minter: Some(staking.to_string()),
+                withdraw_address: None,
+                eviction_policy: None,
+                rental_config: None,
+                max_history: None,
+                base_uri: None,
+            },
+            &[],
+            "badges",
+            None,
+        )
+        .unwrap();
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        staking.clone(),
+        &set_collection(&badges),
+        &[],
+    )
+    .unwrap();
+
+    let set_milestone = |id: &str, milestone| ExecuteMsg::SetMilestone {
+        id: id.to_string(),
+        milestone: Some(milestone),
+    };
+    let err = app
+        .execute_contract(
+            Addr::unchecked(OWNER),
+            staking.clone(),
+            &set_milestone(
+                "never",
+                Milestone {
+                    condition: MilestoneCondition::StakedDays { days: 0 },
+                    token_uri: None,
+                    extension: None,
+                },
+            ),
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InvalidMilestone {}
+    );
+    let pair = Milestone {
+        condition: MilestoneCondition::StakedNfts {
+            count: Uint128::new(2),
+        },
+        token_uri: Some("ipfs://badges/{milestone}.json".to_string()),
+        extension: None,
+    };
+    let veteran = Milestone {
+        condition: MilestoneCondition::StakedDays { days: 30 },
+        token_uri: None,
+        extension: Some(BadgeMetadata {
+            name: Some("Veteran {staker}".to_string()),
+            description: None,
+            image: None,
+            attributes: Some(vec![BadgeTrait {
+                display_type: None,
+                trait_type: "milestone".to_string(),
+                value: "{milestone}".to_string(),
+            }]),
+        }),
+    };
+    for (id, milestone) in [("pair", pair.clone()), ("veteran", veteran.clone())] {
+        app.execute_contract(
+            Addr::unchecked(OWNER),
+            staking.clone(),
+            &set_milestone(id, milestone),
+            &[],
+        )
+        .unwrap();
+    }
+    let res: MilestonesResponse = app
+        .wrap()
+        .query_wasm_smart(&staking, &QueryMsg::Milestones {})
+        .unwrap();
+    assert_eq!(res.collection, Some(badges.clone()));
+    assert_eq!(res.milestones.len(), 2);
+
+    // staking a second NFT reaches the pair milestone
+    stake(&mut app, &cw721, &staking, 30).unwrap();
+    let err = app
+        .execute_contract(
+            Addr::unchecked(STAKER),
+            staking.clone(),
+            &ExecuteMsg::ClaimBadges {},
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::NothingToClaim {}
+    );
+    stake_token(&mut app, &cw721, &staking, "2", 30).unwrap();
+    let badge: NftInfoResponse<Option<Metadata>> = app
+        .wrap()
+        .query_wasm_smart(
+            &badges,
+            &Cw721QueryMsg::NftInfo {
+                token_id: format!("pair-{}", STAKER),
+            },
+        )
+        .unwrap();
+    assert_eq!(badge.token_uri, Some("ipfs://badges/pair.json".to_string()));
+
+    // unstaking after 30 days reaches the veteran milestone
+    app.update_block(|block| block.time = block.time.plus_seconds(30 * DAY_IN_SECONDS));
+    unstake(&mut app, STAKER, &cw721, &staking).unwrap();
+    let badge: NftInfoResponse<Option<Metadata>> = app
+        .wrap()
+        .query_wasm_smart(
+            &badges,
+            &Cw721QueryMsg::NftInfo {
+                token_id: format!("veteran-{}", STAKER),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        badge.extension,
+        Some(Metadata {
+            name: Some(format!("Veteran {}", STAKER)),
+            attributes: Some(vec![Trait {
+                display_type: None,
+                trait_type: "milestone".to_string(),
+                value: "veteran".to_string(),
+            }]),
+        })
+    );
+
+    // each milestone is only awarded once
+    stake(&mut app, &cw721, &staking, 30).unwrap();
+    let res: BadgesResponse = app
+        .wrap()
+        .query_wasm_smart(
+            &staking,
+            &QueryMsg::Badges {
+                owner: STAKER.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        res.badges,
+        vec![
+            Badge {
+                milestone: "pair".to_string(),
+                token_id: format!("pair-{}", STAKER),
+            },
+            Badge {
+                milestone: "veteran".to_string(),
+                token_id: format!("veteran-{}", STAKER),
+            },
+        ]
+    );
+    let query_badge_owner = |token_id: &str| -> OwnerOfResponse {
+        app.wrap()
+            .query_wasm_smart(
+                &badges,
+                &Cw721QueryMsg::OwnerOf {
+                    token_id: token_id.to_string(),
+                    include_expired: None,
+                },
+            )
+            .unwrap()
+    };
+    assert_eq!(query_badge_owner(&format!("pair-{}", STAKER)).owner, STAKER);
+}
+
+#[test]
+fn failing_badge_mint_is_skipped() {
+    let (mut app, cw721, staking, cw20) = setup();
+    fund(&mut app, &cw20, &staking, 10_000);
+
+    let badges_id = app.store_code(cw721_no_mint_contract());
+    let badges = app
+        .instantiate_contract(
+            badges_id,
+            Addr::unchecked(OWNER),
+            &cw721_base::InstantiateMsg {
+                name: "badges".to_string(),
+                symbol: "badge".to_string(),
+                minter: Some(staking.to_string()),
+                withdraw_address: None,
+                eviction_policy: None,
+                rental_config: None,
+                max_history: None,
+                base_uri: None,
+            },
+            &[],
+            "badges",
+            None,
+        )
+        .unwrap();
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        staking.clone(),
+        &ExecuteMsg::SetBadgeCollection {
+            collection: Some(badges.to_string()),
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        staking.clone(),
+        &ExecuteMsg::SetMilestone {
+            id: "first".to_string(),
+            milestone: Some(Milestone {
+                condition: MilestoneCondition::StakedNfts {
+                    count: Uint128::one(),
+                },
+                token_uri: None,
+                extension: None,
+            }),
+        },
+        &[],
+    )
+    .unwrap();
+
+    // the NFT is staked anyway and the badge is not recorded, so it can be claimed later
+    let skipped_badge = |res: &AppResponse| {
+        res.events.iter().any(|event| {
+            event
+                .attributes
+                .iter()
+                .any(|attr| attr.key == "method" && attr.value == "skip_badge")
+        })
+    };
+    let res = stake(&mut app, &cw721, &staking, 30).unwrap();
+    assert!(skipped_badge(&res));
+    assert_eq!(query_owner(&app, &cw721), staking.as_str());
+    let res: BadgesResponse = app
+        .wrap()
+        .query_wasm_smart(
+            &staking,
+            &QueryMsg::Badges {
+                owner: STAKER.to_string(),
+            },
+        )
+        .unwrap();
+    assert!(res.badges.is_empty());
+    let res = app
+        .execute_contract(
+            Addr::unchecked(STAKER),
+            staking.clone(),
+            &ExecuteMsg::ClaimBadges {},
+            &[],
+        )
         .unwrap();
-    assert!(withdraw_addr.is_none());
+    assert!(skipped_badge(&res));
 }
@@ -1,404 +1,247 @@
-use serde::de::DeserializeOwned;
-use serde::Serialize;
-
-use cosmwasm_std::{to_binary, to_json_binary, Addr, Binary, BlockInfo, CustomMsg, Deps, Env, Order, StdError, StdResult,};
-
-use cw721::{
-    AllNftInfoResponse, ApprovalResponse, ApprovalsResponse, ContractInfoResponse, Cw721Query,
-    Expiration, NftInfoResponse, NumTokensResponse, OperatorResponse, OperatorsResponse,
-    OwnerOfResponse, TokensResponse, UserOfResponse
-};
+use cosmwasm_std::{Addr, Decimal, Deps, Env, Order, StdResult, Uint128};
 use cw_storage_plus::Bound;
-use cw_utils::maybe_addr;
 
-use crate::msg::{MinterResponse, QueryMsg};
-use crate::state::{Approval, Cw721Contract, TokenInfo};
+use crate::msg::{
+    Badge, BadgesResponse, InfoResponse, MilestoneInfo, MilestonesResponse, ModeResponse,
+    MultipliersResponse, PendingReward, PendingRewardsResponse, StakesResponse,
+    TotalPowerAtHeightResponse, TraitMultiplier, UnbondingResponse, VotingPowerAtHeightResponse,
+};
+use crate::state::{
+    nft_stakes, Config, EarlyExitPolicy, Mode, StakeInfo, State, BADGES, BADGE_COLLECTION,
+    COLLECTION_MULTIPLIER, CONFIG, DAO, EARLY_EXIT_POLICY, EMERGENCY_WITHDRAW_DELAY, MILESTONES,
+    STAKED_NFTS, STATE, TOTAL_STAKED_NFTS, TRAIT_MULTIPLIER, UNBONDING, UNPAID_REWARDS,
+};
 
 const DEFAULT_LIMIT: u32 = 10;
-const MAX_LIMIT: u32 = 1000;
-
-impl<'a, T, C, E, Q> Cw721Query<T> for Cw721Contract<'a, T, C, E, Q>
-where
-    T: Serialize + DeserializeOwned + Clone,
-    C: CustomMsg,
-    E: CustomMsg,
-    Q: CustomMsg,
-{
-    fn contract_info(&self, deps: Deps) -> StdResult<ContractInfoResponse> {
-        self.contract_info.load(deps.storage)
-    }
+const MAX_LIMIT: u32 = 100;
 
-    fn num_tokens(&self, deps: Deps) -> StdResult<NumTokensResponse> {
-        let count = self.token_count(deps.storage)?;
-        Ok(NumTokensResponse { count })
-    }
-
-    fn nft_info(&self, deps: Deps, token_id: String) -> StdResult<NftInfoResponse<T>> {
-        let info = self.tokens.load(deps.storage, &token_id)?;
-        Ok(NftInfoResponse {
-            token_uri: info.token_uri,
-            extension: info.extension,
-        })
-    }
-
-    fn owner_of(
-        &self,
-        deps: Deps,
-        env: Env,
-        token_id: String,
-        include_expired: bool,
-    ) -> StdResult<OwnerOfResponse> {
-        let info = self.tokens.load(deps.storage, &token_id)?;
-        Ok(OwnerOfResponse {
-            owner: info.owner.to_string(),
-            approvals: humanize_approvals(&env.block, &info, include_expired),
-        })
-    }
-
-    /// operator returns the approval status of an operator for a given owner if exists
-    fn operator(
-        &self,
-        deps: Deps,
-        env: Env,
-        owner: String,
-        operator: String,
-        include_expired: bool,
-    ) -> StdResult<OperatorResponse> {
-        let owner_addr = deps.api.addr_validate(&owner)?;
-        let operator_addr = deps.api.addr_validate(&operator)?;
-
-        let info = self
-            .operators
-            .may_load(deps.storage, (&owner_addr, &operator_addr))?;
-
-        if let Some(expires) = info {
-            if !include_expired && expires.is_expired(&env.block) {
-                return Err(StdError::not_found("Approval not found"));
-            }
+pub fn config(deps: Deps) -> StdResult<Config> {
+    CONFIG.load(deps.storage)
+}
 
-            return Ok(OperatorResponse {
-                approval: cw721::Approval {
-                    spender: operator,
-                    expires,
-                },
-            });
-        }
+pub fn state(deps: Deps) -> StdResult<State> {
+    STATE.load(deps.storage)
+}
 
-        Err(StdError::not_found("Approval not found"))
-    }
+pub fn early_exit_policy(deps: Deps, number_days: u64) -> StdResult<Option<EarlyExitPolicy>> {
+    EARLY_EXIT_POLICY.may_load(deps.storage, number_days)
+}
 
-    /// operators returns all operators owner given access to
-    fn operators(
-        &self,
-        deps: Deps,
-        env: Env,
-        owner: String,
-        include_expired: bool,
-        start_after: Option<String>,
-        limit: Option<u32>,
-    ) -> StdResult<OperatorsResponse> {
-        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-        let start_addr = maybe_addr(deps.api, start_after)?;
-        let start = start_addr.as_ref().map(Bound::exclusive);
+pub fn mode(deps: Deps) -> StdResult<ModeResponse> {
+    let mode = Mode::load(deps.storage)?;
+    let withdraw_after = match mode {
+        Mode::Normal => None,
+        Mode::Emergency { since } => Some(since + EMERGENCY_WITHDRAW_DELAY),
+    };
+    Ok(ModeResponse {
+        mode,
+        withdraw_after,
+    })
+}
 
-        let owner_addr = deps.api.addr_validate(&owner)?;
-        let res: StdResult<Vec<_>> = self
-            .operators
-            .prefix(&owner_addr)
-            .range(deps.storage, start, None, Order::Ascending)
-            .filter(|r| {
-                include_expired || r.is_err() || !r.as_ref().unwrap().1.is_expired(&env.block)
+pub fn multipliers(deps: Deps, collection: String) -> StdResult<MultipliersResponse> {
+    let collection = deps.api.addr_validate(&collection)?;
+    let traits = TRAIT_MULTIPLIER
+        .sub_prefix(&collection)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            item.map(|((trait_type, value), multiplier)| TraitMultiplier {
+                trait_type,
+                value,
+                multiplier,
             })
-            .take(limit)
-            .map(parse_approval)
-            .collect();
-        Ok(OperatorsResponse { operators: res? })
-    }
-
-    fn user_of(
-        &self,
-        deps: Deps,
-        env: Env,
-        token_id: String,
-    ) -> StdResult<UserOfResponse> {
-        let info = self.tokens.load(deps.storage, &token_id)?;
-        //We could check if it has expired we return the default state
-        //but that would require considering the other variants of Expiration, 
-        //so we allow Ui and cotracts to handle that
-        let check = info.user.is_expired(&env.block);
-        println!("Inside contract, is_expired: {:?}", check);
-        if info.user.is_expired(&env.block) {
-        // If expired, return the default state
-        return Ok(UserOfResponse {
-            user: "".to_string(), // Update with the appropriate default value
-            expires: Expiration::Never {},
-        });
-        }
-        Ok(UserOfResponse {
-            user: info.user.user.to_string(),
-            expires: info.user.expires,
         })
-    }
-
-    fn approval(
-        &self,
-        deps: Deps,
-        env: Env,
-        token_id: String,
-        spender: String,
-        include_expired: bool,
-    ) -> StdResult<ApprovalResponse> {
-        let token = self.tokens.load(deps.storage, &token_id)?;
+        .collect::<StdResult<_>>()?;
 
-        // token owner has absolute approval
-        if token.owner == spender {
-            let approval = cw721::Approval {
-                spender: token.owner.to_string(),
-                expires: Expiration::Never {},
-            };
-            return Ok(ApprovalResponse { approval });
-        }
-
-        let filtered: Vec<_> = token
-            .approvals
-            .into_iter()
-            .filter(|t| t.spender == spender)
-            .filter(|t| include_expired || !t.is_expired(&env.block))
-            .map(|a| cw721::Approval {
-                spender: a.spender.into_string(),
-                expires: a.expires,
-            })
-            .collect();
-
-        if filtered.is_empty() {
-            return Err(StdError::not_found("Approval not found"));
-        }
-        // we expect only one item
-        let approval = filtered[0].clone();
+    Ok(MultipliersResponse {
+        collection: COLLECTION_MULTIPLIER
+            .may_load(deps.storage, &collection)?
+            .unwrap_or(Decimal::one()),
+        traits,
+    })
+}
 
-        Ok(ApprovalResponse { approval })
-    }
+pub fn milestones(deps: Deps) -> StdResult<MilestonesResponse> {
+    let milestones = MILESTONES
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(id, milestone)| MilestoneInfo { id, milestone }))
+        .collect::<StdResult<_>>()?;
+    Ok(MilestonesResponse {
+        collection: BADGE_COLLECTION.may_load(deps.storage)?,
+        milestones,
+    })
+}
 
-    /// approvals returns all approvals owner given access to
-    fn approvals(
-        &self,
-        deps: Deps,
-        env: Env,
-        token_id: String,
-        include_expired: bool,
-    ) -> StdResult<ApprovalsResponse> {
-        let token = self.tokens.load(deps.storage, &token_id)?;
-        let approvals: Vec<_> = token
-            .approvals
-            .into_iter()
-            .filter(|t| include_expired || !t.is_expired(&env.block))
-            .map(|a| cw721::Approval {
-                spender: a.spender.into_string(),
-                expires: a.expires,
+pub fn badges(deps: Deps, owner: String) -> StdResult<BadgesResponse> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let badges = BADGES
+        .prefix(&owner)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            item.map(|(milestone, token_id)| Badge {
+                milestone,
+                token_id,
             })
-            .collect();
-
-        Ok(ApprovalsResponse { approvals })
-    }
-
-    fn tokens(
-        &self,
-        deps: Deps,
-        owner: String,
-        start_after: Option<String>,
-        limit: Option<u32>,
-    ) -> StdResult<TokensResponse> {
-        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-        let start = start_after.map(|s| Bound::ExclusiveRaw(s.into()));
-
-        let owner_addr = deps.api.addr_validate(&owner)?;
-        let tokens: Vec<String> = self
-            .tokens
-            .idx
-            .owner
-            .prefix(owner_addr)
-            .keys(deps.storage, start, None, Order::Ascending)
-            .take(limit)
-            .collect::<StdResult<Vec<_>>>()?;
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(BadgesResponse { badges })
+}
 
-        Ok(TokensResponse { tokens })
-    }
+pub fn unpaid_rewards(deps: Deps, owner: String) -> StdResult<Uint128> {
+    let owner = deps.api.addr_validate(&owner)?;
+    Ok(UNPAID_REWARDS
+        .may_load(deps.storage, &owner)?
+        .unwrap_or_default())
+}
 
-    fn all_tokens(
-        &self,
-        deps: Deps,
-        start_after: Option<String>,
-        limit: Option<u32>,
-    ) -> StdResult<TokensResponse> {
-        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-        let start = start_after.map(|s| Bound::ExclusiveRaw(s.into()));
+pub fn stake_info(deps: Deps, collection: String, token_id: String) -> StdResult<StakeInfo> {
+    let collection = deps.api.addr_validate(&collection)?;
+    nft_stakes().load(deps.storage, (&token_id, &collection))
+}
 
-        let tokens: StdResult<Vec<String>> = self
-            .tokens
-            .range(deps.storage, start, None, Order::Ascending)
-            .take(limit)
-            .map(|item| item.map(|(k, _)| k))
-            .collect();
+pub fn stakes_by_owner(
+    deps: Deps,
+    owner: String,
+    start_after: Option<(String, String)>,
+    limit: Option<u32>,
+) -> StdResult<StakesResponse> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = match start_after {
+        Some((collection, token_id)) => {
+            let collection = deps.api.addr_validate(&collection)?;
+            Some(Bound::exclusive((token_id, collection)))
+        }
+        None => None,
+    };
+
+    let stakes = nft_stakes()
+        .idx
+        .owner
+        .prefix(owner)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, stake)| stake))
+        .collect::<StdResult<_>>()?;
+    Ok(StakesResponse { stakes })
+}
 
-        Ok(TokensResponse { tokens: tokens? })
-    }
+pub fn stakes_by_collection(
+    deps: Deps,
+    collection: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<StakesResponse> {
+    let collection = deps.api.addr_validate(&collection)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|token_id| Bound::exclusive((token_id, collection.clone())));
+
+    let stakes = nft_stakes()
+        .idx
+        .collection
+        .prefix(collection)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, stake)| stake))
+        .collect::<StdResult<_>>()?;
+    Ok(StakesResponse { stakes })
+}
 
-    fn all_nft_info(
-        &self,
-        deps: Deps,
-        env: Env,
-        token_id: String,
-        include_expired: bool,
-    ) -> StdResult<AllNftInfoResponse<T>> {
-        let info = self.tokens.load(deps.storage, &token_id)?;
-        Ok(AllNftInfoResponse {
-            access: OwnerOfResponse {
-                owner: info.owner.to_string(),
-                approvals: humanize_approvals(&env.block, &info, include_expired),
-            },
-            info: NftInfoResponse {
-                token_uri: info.token_uri,
-                extension: info.extension,
-            },
-            user: UserOfResponse{
-                user: info.user.user.to_string(),
-                expires: info.user.expires,
-            }
-        })
-    }
+pub fn unbonding(
+    deps: Deps,
+    owner: String,
+    start_after: Option<(String, String)>,
+    limit: Option<u32>,
+) -> StdResult<UnbondingResponse> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start_after = match start_after {
+        Some((collection, token_id)) => Some((deps.api.addr_validate(&collection)?, token_id)),
+        None => None,
+    };
+    let start = start_after
+        .as_ref()
+        .map(|(collection, token_id)| Bound::exclusive((collection, token_id.as_str())));
+
+    let nfts = UNBONDING
+        .sub_prefix(&owner)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, unbonding)| unbonding))
+        .collect::<StdResult<_>>()?;
+    Ok(UnbondingResponse { nfts })
 }
 
-impl<'a, T, C, E, Q> Cw721Contract<'a, T, C, E, Q>
-where
-    T: Serialize + DeserializeOwned + Clone,
-    C: CustomMsg,
-    E: CustomMsg,
-    Q: CustomMsg,
-{
-    pub fn query(&self, deps: Deps, env: Env, msg: QueryMsg<Q>) -> StdResult<Binary> {
-        match msg {
-            QueryMsg::Minter {} => to_json_binary(&self.minter(deps)?),
-            QueryMsg::ContractInfo {} => to_json_binary(&self.contract_info(deps)?),
-            QueryMsg::NftInfo { token_id } => to_json_binary(&self.nft_info(deps, token_id)?),
-            QueryMsg::OwnerOf {
-                token_id,
-                include_expired,
-            } => to_json_binary(&self.owner_of(
-                deps,
-                env,
-                token_id,
-                include_expired.unwrap_or(false),
-            )?),
-            QueryMsg::UserOf {
-                token_id,
-            } => {
-                to_binary(&self.user_of(deps, env, token_id)?)
-            },
-            QueryMsg::AllNftInfo {
-                token_id,
-                include_expired,
-            } => to_json_binary(&self.all_nft_info(
-                deps,
-                env,
-                token_id,
-                include_expired.unwrap_or(false),
-            )?),
-            QueryMsg::Operator {
-                owner,
-                operator,
-                include_expired,
-            } => to_json_binary(&self.operator(
-                deps,
-                env,
-                owner,
-                operator,
-                include_expired.unwrap_or(false),
-            )?),
-            QueryMsg::AllOperators {
-                owner,
-                include_expired,
-                start_after,
-                limit,
-            } => to_json_binary(&self.operators(
-                deps,
-                env,
-                owner,
-                include_expired.unwrap_or(false),
-                start_after,
-                limit,
-            )?),
-            QueryMsg::NumTokens {} => to_json_binary(&self.num_tokens(deps)?),
-            QueryMsg::Tokens {
-                owner,
-                start_after,
-                limit,
-            } => to_json_binary(&self.tokens(deps, owner, start_after, limit)?),
-            QueryMsg::AllTokens { start_after, limit } => {
-                to_json_binary(&self.all_tokens(deps, start_after, limit)?)
-            }
-            QueryMsg::Approval {
-                token_id,
-                spender,
-                include_expired,
-            } => to_json_binary(&self.approval(
-                deps,
-                env,
-                token_id,
-                spender,
-                include_expired.unwrap_or(false),
-            )?),
-            QueryMsg::Approvals {
-                token_id,
-                include_expired,
-            } => to_json_binary(&self.approvals(
-                deps,
-                env,
-                token_id,
-                include_expired.unwrap_or(false),
-            )?),
-            QueryMsg::Ownership {} => to_json_binary(&Self::ownership(deps)?),
-            QueryMsg::Extension { msg: _ } => Ok(Binary::default()),
-            QueryMsg::GetWithdrawAddress {} => {
-                to_json_binary(&self.withdraw_address.may_load(deps.storage)?)
-            }
+pub fn pending_rewards(
+    deps: Deps,
+    env: Env,
+    owner: String,
+    start_after: Option<(String, String)>,
+    limit: Option<u32>,
+) -> StdResult<PendingRewardsResponse> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = match start_after {
+        Some((collection, token_id)) => {
+            let collection = deps.api.addr_validate(&collection)?;
+            Some(Bound::exclusive((token_id, collection)))
         }
-    }
-
-    pub fn minter(&self, deps: Deps) -> StdResult<MinterResponse> {
-        let minter = cw_ownable::get_ownership(deps.storage)?
-            .owner
-            .map(|a| a.into_string());
-
-        Ok(MinterResponse { minter })
-    }
+        None => None,
+    };
+    let now = env.block.time.seconds();
+    let config = CONFIG.load(deps.storage)?;
+    let mut state = STATE.load(deps.storage)?;
+    state.accrue(&config.reward_mode, now);
+
+    let stakes = nft_stakes()
+        .idx
+        .owner
+        .prefix(owner)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            item.map(|(_, stake)| PendingReward {
+                rewards: stake.pending_rewards(&config.reward_mode, state.reward_per_weight, now),
+                collection: stake.collection,
+                token_id: stake.token_id,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    let total = stakes.iter().map(|stake| stake.rewards).sum::<Uint128>();
+    Ok(PendingRewardsResponse { total, stakes })
+}
 
-    pub fn ownership(deps: Deps) -> StdResult<cw_ownable::Ownership<Addr>> {
-        cw_ownable::get_ownership(deps.storage)
-    }
+pub fn voting_power_at_height(
+    deps: Deps,
+    env: Env,
+    address: String,
+    height: Option<u64>,
+) -> StdResult<VotingPowerAtHeightResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let height = height.unwrap_or(env.block.height);
+    let power = STAKED_NFTS
+        .may_load_at_height(deps.storage, &address, height)?
+        .unwrap_or_default();
+    Ok(VotingPowerAtHeightResponse { power, height })
 }
 
-fn parse_approval(item: StdResult<(Addr, Expiration)>) -> StdResult<cw721::Approval> {
-    item.map(|(spender, expires)| cw721::Approval {
-        spender: spender.to_string(),
-        expires,
-    })
+pub fn total_power_at_height(
+    deps: Deps,
+    env: Env,
+    height: Option<u64>,
+) -> StdResult<TotalPowerAtHeightResponse> {
+    let height = height.unwrap_or(env.block.height);
+    let power = TOTAL_STAKED_NFTS
+        .may_load_at_height(deps.storage, height)?
+        .unwrap_or_default();
+    Ok(TotalPowerAtHeightResponse { power, height })
 }
 
-fn humanize_approvals<T>(
-    block: &BlockInfo,
-    info: &TokenInfo<T>,
-    include_expired: bool,
-) -> Vec<cw721::Approval> {
-    info.approvals
-        .iter()
-        .filter(|apr| include_expired || !apr.is_expired(block))
-        .map(humanize_approval)
-        .collect()
+pub fn dao(deps: Deps) -> StdResult<Addr> {
+    DAO.load(deps.storage)
 }
 
-fn humanize_approval(approval: &Approval) -> cw721::Approval {
-    cw721::Approval {
-        spender: approval.spender.to_string(),
-        expires: approval.expires,
-    }
+pub fn info(deps: Deps) -> StdResult<InfoResponse> {
+    let info = cw2::get_contract_version(deps.storage)?;
+    Ok(InfoResponse { info })
 }
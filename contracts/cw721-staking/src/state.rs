@@ -1,64 +1,419 @@
 use schemars::JsonSchema;
-use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Addr, BlockInfo, CustomMsg, StdResult, Storage};
+use std::fmt;
 
-use cw721::{ContractInfoResponse, Cw721, Expiration};
-use cw_storage_plus::{Item, Map};
+use cosmwasm_std::{
+    to_json_binary, Addr, BankMsg, Coin, CosmosMsg, Decimal, Decimal256, StdResult, Storage,
+    Uint128, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+use cw_storage_plus::{
+    Index, IndexList, IndexedMap, Item, Map, MultiIndex, SnapshotItem, SnapshotMap, Strategy,
+};
 
+use crate::error::ContractError;
+
+/// Lock periods are given in days, measured in seconds of block time
+pub const DAY_IN_SECONDS: u64 = 86_400;
 
 pub const CONFIG: Item<Config> = Item::new("config");
 
 pub const STATE: Item<State> = Item::new("state");
 
-//pub const NFT_STAKES: Map<&Addr, StakeInfo> = Map::new("nft_stakes"); //We need to rewrite this or something else, the token_ids cannot repeat
-//yet they may repeat for different collections
-// Stores token_id and collection address in a turple as key and StakeInfo as value
-pub const NFT_STAKES: Map<(String, &Addr), StakeInfo> = Map::new("nft_stakes");
+pub const MODE: Item<Mode> = Item::new("mode");
+
+/// How long after entering emergency mode the owner can withdraw the reward pool
+pub const EMERGENCY_WITHDRAW_DELAY: u64 = 7 * DAY_IN_SECONDS;
 
+pub struct StakeIndexes<'a> {
+    pub owner: MultiIndex<'a, Addr, StakeInfo, (String, Addr)>,
+    pub collection: MultiIndex<'a, Addr, StakeInfo, (String, Addr)>,
+}
+
+impl<'a> IndexList<StakeInfo> for StakeIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<StakeInfo>> + '_> {
+        let v: Vec<&dyn Index<StakeInfo>> = vec![&self.owner, &self.collection];
+        Box::new(v.into_iter())
+    }
+}
 
+// token_ids may repeat across collections, so stakes are keyed by
+// (token_id, collection address)
+pub fn nft_stakes<'a>() -> IndexedMap<'a, (&'a str, &'a Addr), StakeInfo, StakeIndexes<'a>> {
+    let indexes = StakeIndexes {
+        owner: MultiIndex::new(
+            |_pk, stake| stake.owner.clone(),
+            "nft_stakes",
+            "nft_stakes__owner",
+        ),
+        collection: MultiIndex::new(
+            |_pk, stake| stake.collection.clone(),
+            "nft_stakes",
+            "nft_stakes__collection",
+        ),
+    };
+    IndexedMap::new("nft_stakes", indexes)
+}
+
+/// Reward rate per lock period, keyed by number of days. Only listed periods can be staked for.
+/// The rate is the amount of reward tokens a staked NFT earns per day.
 pub const REWARD_RATE: Map<u64, u64> = Map::new("reward_rate");
 
+/// Terms for unstaking before the lock period is over, keyed by number of days.
+/// Lock periods without a policy cannot be unstaked early.
+pub const EARLY_EXIT_POLICY: Map<u64, EarlyExitPolicy> = Map::new("early_exit_policy");
+
+/// Collections that may be staked, keyed by collection address
 pub const COLLECTIONS: Map<&Addr, bool> = Map::new("collections");
 
-pub const USER_INFO: Map<Addr, UsesrInfo> = Map::new("user_info");
+/// Reward multiplier of every NFT of a collection, 1 if unset
+pub const COLLECTION_MULTIPLIER: Map<&Addr, Decimal> = Map::new("collection_multiplier");
 
+/// Reward multiplier of the NFTs of a collection with a metadata trait,
+/// keyed by (collection address, trait_type, value)
+pub const TRAIT_MULTIPLIER: Map<(&Addr, &str, &str), Decimal> = Map::new("trait_multiplier");
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+/// The DAO this contract is a voting module of, the address that instantiated it
+pub const DAO: Item<Addr> = Item::new("dao");
+
+/// Number of NFTs staked by each owner, their voting power, with its history by block height
+pub const STAKED_NFTS: SnapshotMap<&Addr, Uint128> = SnapshotMap::new(
+    "staked_nfts",
+    "staked_nfts__checkpoints",
+    "staked_nfts__changelog",
+    Strategy::EveryBlock,
+);
+
+/// Number of NFTs staked by all owners, the total voting power, with its history by block height
+pub const TOTAL_STAKED_NFTS: SnapshotItem<Uint128> = SnapshotItem::new(
+    "total_staked_nfts",
+    "total_staked_nfts__checkpoints",
+    "total_staked_nfts__changelog",
+    Strategy::EveryBlock,
+);
+
+/// Records an NFT of `owner` being staked or unstaked at `height`
+pub fn update_voting_power(
+    storage: &mut dyn Storage,
+    owner: &Addr,
+    height: u64,
+    staked: bool,
+) -> StdResult<()> {
+    let update = |power: Option<Uint128>| -> StdResult<Uint128> {
+        let power = power.unwrap_or_default();
+        if staked {
+            Ok(power + Uint128::one())
+        } else {
+            Ok(power - Uint128::one())
+        }
+    };
+    STAKED_NFTS.update(storage, owner, height, update)?;
+    TOTAL_STAKED_NFTS.update(storage, height, update)?;
+    Ok(())
+}
+
+/// Collection badges are minted from, this contract must be its minter
+pub const BADGE_COLLECTION: Item<Addr> = Item::new("badge_collection");
+
+/// Milestones stakers earn a badge for, keyed by milestone id
+pub const MILESTONES: Map<&str, Milestone> = Map::new("milestones");
+
+/// token_id of the badge minted for a milestone, keyed by (owner, milestone id)
+pub const BADGES: Map<(&Addr, &str), String> = Map::new("badges");
+
+/// (owner, milestone id) of the badges being minted, in the order their replies come in
+pub const PENDING_BADGES: Item<Vec<(Addr, String)>> = Item::new("pending_badges");
+
+/// Rewards unstaking could not pay out of an underfunded pool, owed to the owner until
+/// `ClaimUnpaid` pays them
+pub const UNPAID_REWARDS: Map<&Addr, Uint128> = Map::new("unpaid_rewards");
+
+/// NFTs unstaked but not withdrawn yet, keyed by (owner, collection address, token_id)
+pub const UNBONDING: Map<(&Addr, &Addr, &str), Unbonding> = Map::new("unbonding");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct StakeInfo {
-    
+    /// The account that sent the NFT and may unstake it
     pub owner: Addr,
-   
+
     pub total_earned_tokens: Uint128,
-    
+
     pub token_id: String,
-   
+
+    /// Lock period, the NFT cannot be unstaked before `stake_time + stake_days`
     pub stake_days: u64,
 
     pub reward_rate: u64,
 
+    /// Applied to `reward_rate`, fixed when the NFT is staked
+    pub multiplier: Decimal,
+
+    /// Block time in seconds the NFT was staked at
+    pub stake_time: u64,
+
     pub last_claim_time: u64,
 
-    pub staked: bool,
+    /// `State.reward_per_weight` when rewards were last settled, only used by `RewardMode::Emission`
+    pub reward_debt: Decimal256,
 
     pub collection: Addr,
+}
 
+impl StakeInfo {
+    /// Block time in seconds from which the NFT can be unstaked
+    pub fn unlock_time(&self) -> u64 {
+        self.stake_time + self.stake_days * DAY_IN_SECONDS
+    }
 
+    /// Share of the emission this stake earns, its `reward_rate` times its `multiplier`
+    pub fn weight(&self) -> Uint128 {
+        Uint128::from(self.reward_rate) * self.multiplier
+    }
+
+    /// Rewards earned since the last claim. With a fixed rate they accrue per second at
+    /// `reward_rate` times `multiplier` per day. With an emission they are the stake's weight
+    /// times the growth of `reward_per_weight`, which must be accrued up to `now`.
+    pub fn pending_rewards(
+        &self,
+        mode: &RewardMode,
+        reward_per_weight: Decimal256,
+        now: u64,
+    ) -> Uint128 {
+        match mode {
+            RewardMode::FixedRate => {
+                let elapsed = Uint128::from(now.saturating_sub(self.last_claim_time));
+                Uint128::from(self.reward_rate).multiply_ratio(
+                    elapsed * self.multiplier.atomics(),
+                    Uint128::from(DAY_IN_SECONDS) * Decimal::one().atomics(),
+                )
+            }
+            RewardMode::Emission { .. } => {
+                let growth = reward_per_weight.saturating_sub(self.reward_debt);
+                let rewards = (growth * Decimal256::from_ratio(self.weight(), 1u8)).to_uint_floor();
+                // only out of range for absurd emissions, which the pool cannot cover anyway
+                Uint128::try_from(rewards).unwrap_or(Uint128::MAX)
+            }
+        }
+    }
 }
 
+/// An unstaked NFT in its unbonding period, it earns no rewards
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-#[derive(Default)]
+pub struct Unbonding {
+    pub owner: Addr,
+    pub collection: Addr,
+    pub token_id: String,
+    /// Block time in seconds from which the NFT can be withdrawn
+    pub withdrawable_at: u64,
+}
+
+/// A milestone of a staker, reaching it mints a badge NFT once
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Milestone {
+    pub condition: MilestoneCondition,
+    /// `{milestone}` and `{staker}` are replaced with the milestone id and the staker
+    pub token_uri: Option<String>,
+    /// Only for collections with a `Metadata` extension, placeholders are replaced as in
+    /// `token_uri`
+    pub extension: Option<BadgeMetadata>,
+}
+
+impl Milestone {
+    /// token_uri and extension of the badge minted for `staker`
+    pub fn badge(&self, id: &str, staker: &Addr) -> (Option<String>, Option<BadgeMetadata>) {
+        let fill = |template: &String| {
+            template
+                .replace("{milestone}", id)
+                .replace("{staker}", staker.as_str())
+        };
+        let extension = self.extension.as_ref().map(|extension| BadgeMetadata {
+            name: extension.name.as_ref().map(fill),
+            description: extension.description.as_ref().map(fill),
+            image: extension.image.as_ref().map(fill),
+            attributes: extension.attributes.as_ref().map(|attributes| {
+                attributes
+                    .iter()
+                    .map(|attribute| BadgeTrait {
+                        display_type: attribute.display_type.clone(),
+                        trait_type: attribute.trait_type.clone(),
+                        value: fill(&attribute.value),
+                    })
+                    .collect()
+            }),
+        });
+        (self.token_uri.as_ref().map(fill), extension)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MilestoneCondition {
+    /// One of the staker's NFTs has been staked for `days`
+    StakedDays { days: u64 },
+    /// The staker has `count` NFTs staked
+    StakedNfts { count: Uint128 },
+}
+
+/// The OpenSea style fields of a `Metadata` extension a badge is minted with. Unset fields
+/// are left out, so collections only need to support the fields that are set.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BadgeMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<Vec<BadgeTrait>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BadgeTrait {
+    pub display_type: Option<String>,
+    pub trait_type: String,
+    pub value: String,
+}
+
+/// How staked NFTs earn rewards
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RewardMode {
+    /// Every stake earns the reward rate of its lock period, for as long as the pool lasts
+    FixedRate,
+    /// `rate` reward tokens per second are emitted from `start` to `end`, block time in
+    /// seconds, and split among the stakes pro-rata by weight
+    Emission { rate: Uint128, start: u64, end: u64 },
+}
+
+impl RewardMode {
+    pub fn validate(&self) -> Result<(), ContractError> {
+        match self {
+            RewardMode::Emission { start, end, .. } if end <= start => {
+                Err(ContractError::InvalidEmissionSchedule {})
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// What an early unstake costs, the policy in place when unstaking applies
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EarlyExitPolicy {
+    /// Forfeit this share, from 0 to 1, of the rewards accrued since the last claim.
+    /// Forfeited rewards stay in the pool.
+    ForfeitRewards { ratio: Decimal },
+    /// Pay a native fee to the owner, rewards are paid out in full
+    Fee { fee: Coin },
+}
+
+/// Whether the contract runs normally or has been shut down by the owner
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    #[default]
+    Normal,
+    /// Entered by the owner at `since`, block time in seconds, and never left. Stakers can
+    /// only take their NFTs back without rewards, staking, funding and claims are closed.
+    Emergency { since: u64 },
+}
+
+impl Mode {
+    pub fn load(storage: &dyn Storage) -> StdResult<Mode> {
+        Ok(MODE.may_load(storage)?.unwrap_or_default())
+    }
+
+    pub fn ensure_normal(storage: &dyn Storage) -> Result<(), ContractError> {
+        match Mode::load(storage)? {
+            Mode::Normal => Ok(()),
+            Mode::Emergency { .. } => Err(ContractError::EmergencyMode {}),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
 pub struct State {
     pub total_stakes: Uint128,
+    /// Rewards paid out so far
     pub total_claims: Uint128,
+    /// Rewards left in the pool to pay out
     pub funded_tokens: Uint128,
+    /// Sum of the weights of all stakes
+    pub total_weight: Uint128,
+    /// Emission accrued per unit of weight since the start, only grows
+    pub reward_per_weight: Decimal256,
+    /// Block time in seconds the emission was last accrued at
+    pub last_emission_time: u64,
 }
 
+impl State {
+    /// Accrues the emission between the last accrual and `now` into `reward_per_weight`.
+    /// Nothing is emitted while nothing is staked.
+    pub fn accrue(&mut self, mode: &RewardMode, now: u64) {
+        if let RewardMode::Emission { rate, start, end } = mode {
+            let from = self.last_emission_time.max(*start);
+            let to = now.min(*end);
+            if to > from && !self.total_weight.is_zero() {
+                let emitted = rate.full_mul(to - from);
+                self.reward_per_weight += Decimal256::from_ratio(emitted, self.total_weight);
+            }
+        }
+        self.last_emission_time = self.last_emission_time.max(now);
+    }
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
     pub owner: Addr,
-    pub token_address: Addr,
+    /// Asset rewards are paid in
+    pub reward_asset: RewardAsset,
+    /// Fixed when instantiating
+    pub reward_mode: RewardMode,
     pub is_staking_allowed: bool,
-}
\ No newline at end of file
+    /// Seconds between unstaking an NFT and being able to withdraw it, 0 returns it right away
+    pub unbonding_period: u64,
+}
+
+/// Asset the reward pool holds and pays out
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RewardAsset {
+    /// Funded with cw20 `Send`, paid out with cw20 `Transfer`
+    Cw20 { address: Addr },
+    /// Funded with `Fund{}` and attached coins, paid out with `BankMsg::Send`
+    Native { denom: String },
+}
+
+impl RewardAsset {
+    /// Message paying `amount` of the asset to `recipient`
+    pub fn transfer_msg(&self, recipient: &Addr, amount: Uint128) -> StdResult<CosmosMsg> {
+        let msg = match self {
+            RewardAsset::Cw20 { address } => WasmMsg::Execute {
+                contract_addr: address.to_string(),
+                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: recipient.to_string(),
+                    amount,
+                })?,
+                funds: vec![],
+            }
+            .into(),
+            RewardAsset::Native { denom } => BankMsg::Send {
+                to_address: recipient.to_string(),
+                amount: vec![Coin::new(amount.u128(), denom)],
+            }
+            .into(),
+        };
+        Ok(msg)
+    }
+}
+
+impl fmt::Display for RewardAsset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RewardAsset::Cw20 { address } => write!(f, "cw20:{}", address),
+            RewardAsset::Native { denom } => write!(f, "native:{}", denom),
+        }
+    }
+}
@@ -126,6 +126,8 @@ pub struct NumTokensResponse {
 pub struct ContractInfoResponse {
     pub name: String,
     pub symbol: String,
+    /// Token uris default to `base_uri + token_id` for tokens minted without one
+    pub base_uri: Option<String>,
 }
 
 #[cw_serde]
@@ -51,6 +51,14 @@ where
         expires: Option<Expiration>,
     ) -> Result<Response<C>, Self::Err>; 
 
+    fn remove_user(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+    ) -> Result<Response<C>, Self::Err>;
+
     fn approve(
         &self,
         deps: DepsMut,